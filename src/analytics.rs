@@ -0,0 +1,739 @@
+//! Click analytics: event log, daily counters and retention
+//!
+//! Every redirect appends a `ClickEvent` to `TABLE_CLICK_EVENTS`, bumps the
+//! link's counter for the current UTC day in `TABLE_DAILY_STATS` and its
+//! all-time `clicks` on the record itself. The event tables are keyed by
+//! `{id}:...` so per-link queries are cheap range scans.
+//!
+//! Only compiled with the `analytics` cargo feature (on by default). Without
+//! it, redirects still bump `clicks` (see `handler::record_visit`).
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone, Timelike, Utc};
+use redb::{ReadableTable, WriteTransaction};
+use serde_json::json;
+
+use crate::database::{load_record, AppState, TABLE_CLICK_EVENTS, TABLE_DAILY_STATS, TABLE_REF_INDEX, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::{effective_ref_id, increment_clicks};
+use crate::middleware::AuthSubject;
+use crate::preview_token::{self, TokenCheck};
+use crate::model::{ClickEvent, EventsParams, HeatmapParams, PurgeEventsRequest, StatsSummaryParams};
+
+/// Number of keys deleted per write transaction when purging
+const PURGE_BATCH_SIZE: usize = 1000;
+
+/// Longest range, in days, a stats summary may cover
+const SUMMARY_MAX_DAYS: i64 = 366;
+
+/// Events per page when `limit` is omitted
+pub const DEFAULT_EVENTS_PAGE: usize = 100;
+
+/// Most events a single page may hold
+pub const MAX_EVENTS_PAGE: usize = 1000;
+
+/// Builds the `TABLE_CLICK_EVENTS` key for an event
+pub fn event_key(id: &str, timestamp: DateTime<Utc>) -> String {
+    format!("{}:{}", id, timestamp.timestamp_micros())
+}
+
+/// Builds the `TABLE_DAILY_STATS` key for a link on the day of `timestamp`
+pub fn daily_key(id: &str, timestamp: DateTime<Utc>) -> String {
+    format!("{}:{}", id, timestamp.format("%Y-%m-%d"))
+}
+
+/// Splits a `TABLE_CLICK_EVENTS` key into its slug and timestamp (micros)
+///
+/// Slugs may themselves contain ':', so the split happens on the last one.
+fn parse_event_key(key: &str) -> Option<(&str, i64)> {
+    let (id, micros) = key.rsplit_once(':')?;
+    Some((id, micros.parse().ok()?))
+}
+
+/// Returns the timestamps (micros) of every click event recorded for a link
+pub fn event_timestamps(state: &AppState, id: &str) -> Result<Vec<i64>, redb::Error> {
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_CLICK_EVENTS)?;
+
+    // '{' sorts right after ':' so this bounds every "{id}:..." key
+    let start_key = format!("{}:", id);
+    let end_key = format!("{}:{{", id);
+
+    let mut timestamps = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (key, _) = entry?;
+        // Skip keys of other slugs that merely share this prefix (e.g. "{id}:x:...")
+        if let Some((event_id, micros)) = parse_event_key(key.value()) {
+            if event_id == id {
+                timestamps.push(micros);
+            }
+        }
+    }
+
+    Ok(timestamps)
+}
+
+/// Returns a link's newest click events, newest first, at most `limit` of them
+pub fn recent_events(state: &AppState, id: &str, limit: usize) -> Result<Vec<ClickEvent>, redb::Error> {
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_CLICK_EVENTS)?;
+
+    let start_key = format!("{}:", id);
+    let end_key = format!("{}:{{", id);
+
+    let mut events = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())?.rev() {
+        if events.len() >= limit {
+            break;
+        }
+        let (key, value) = entry?;
+        if parse_event_key(key.value()).is_some_and(|(event_id, _)| event_id == id) {
+            if let Ok(event) = serde_json::from_str::<ClickEvent>(value.value()) {
+                events.push(event);
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// One page of a link's click events, newest first
+pub struct EventsPage {
+    /// `(timestamp micros, event)` pairs, newest first
+    pub events: Vec<(i64, ClickEvent)>,
+
+    /// Whether older events remain within the requested window
+    pub has_more: bool,
+}
+
+/// Returns up to `limit` events of a link strictly between `after` and `before`
+///
+/// Bounds are event timestamps in microseconds, i.e. the suffix of the
+/// `{id}:{micros}` keys, so each page is a single reverse range scan.
+pub fn events_page(
+    state: &AppState,
+    id: &str,
+    before: Option<i64>,
+    after: Option<i64>,
+    limit: usize,
+) -> Result<EventsPage, redb::Error> {
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_CLICK_EVENTS)?;
+
+    let start_key = format!("{}:", id);
+    let end_key = match before {
+        Some(before) => event_key(id, DateTime::from_timestamp_micros(before).unwrap_or_default()),
+        None => format!("{}:{{", id),
+    };
+
+    let mut events = Vec::new();
+    let mut has_more = false;
+    for entry in table.range(start_key.as_str()..end_key.as_str())?.rev() {
+        let (key, value) = entry?;
+        let Some((event_id, micros)) = parse_event_key(key.value()) else {
+            continue;
+        };
+        if event_id != id || before.is_some_and(|before| micros >= before) {
+            continue;
+        }
+        if after.is_some_and(|after| micros <= after) {
+            break;
+        }
+        if events.len() >= limit {
+            has_more = true;
+            break;
+        }
+        if let Ok(event) = serde_json::from_str::<ClickEvent>(value.value()) {
+            events.push((micros, event));
+        }
+    }
+
+    Ok(EventsPage { events, has_more })
+}
+
+/// Returns every daily click counter of a link, oldest day first
+pub fn daily_clicks(state: &AppState, id: &str) -> Result<Vec<(NaiveDate, u64)>, redb::Error> {
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_DAILY_STATS)?;
+
+    let start_key = format!("{}:", id);
+    let end_key = format!("{}:{{", id);
+
+    let mut days = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (key, count) = entry?;
+        let Some((day_id, day)) = key.value().rsplit_once(':') else {
+            continue;
+        };
+        if day_id != id {
+            continue;
+        }
+        if let Ok(day) = NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+            days.push((day, count.value()));
+        }
+    }
+
+    Ok(days)
+}
+
+/// Parses a timezone offset given as whole hours (`7`, `-5`) or `±HH:MM`
+pub fn parse_tz_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+
+    let seconds = match value.split_once(':') {
+        Some((hours, minutes)) => {
+            let hours: i32 = hours.parse().ok()?;
+            let minutes: i32 = minutes.parse().ok()?;
+            if !(0..60).contains(&minutes) {
+                return None;
+            }
+            let sign = if value.starts_with('-') { -1 } else { 1 };
+            hours * 3600 + sign * minutes * 60
+        }
+        None => value.parse::<i32>().ok()? * 3600,
+    };
+
+    // Real-world offsets range from UTC-12 to UTC+14
+    if !(-12 * 3600..=14 * 3600).contains(&seconds) {
+        return None;
+    }
+
+    FixedOffset::east_opt(seconds)
+}
+
+/// Buckets click timestamps (micros) into 24 hour-of-day counts in `offset`
+pub fn hourly_buckets(timestamps: &[i64], offset: FixedOffset) -> [u64; 24] {
+    let mut buckets = [0u64; 24];
+
+    for micros in timestamps {
+        if let Some(time) = DateTime::from_timestamp_micros(*micros) {
+            let local = offset.from_utc_datetime(&time.naive_utc());
+            buckets[local.hour() as usize] += 1;
+        }
+    }
+
+    buckets
+}
+
+/// Stores a click event and increments the link's daily and total counters
+///
+/// All writes happen in a single transaction so the event log, the daily
+/// stats and the record's `clicks` never disagree.
+pub fn record_click(state: &AppState, event: &ClickEvent) -> Result<(), redb::Error> {
+    // Serializing a plain struct of strings and timestamps cannot fail
+    let event_json = serde_json::to_string(event).unwrap();
+
+    let write_txn = state.begin_write()?;
+    {
+        let mut events = write_txn.open_table(TABLE_CLICK_EVENTS)?;
+        events.insert(event_key(&event.id, event.timestamp).as_str(), event_json.as_str())?;
+
+        let mut daily = write_txn.open_table(TABLE_DAILY_STATS)?;
+        let key = daily_key(&event.id, event.timestamp);
+        let current = daily.get(key.as_str())?.map(|guard| guard.value()).unwrap_or(0);
+        daily.insert(key.as_str(), current + 1)?;
+
+        increment_clicks(&write_txn, &event.id)?;
+    }
+    write_txn.commit()?;
+
+    Ok(())
+}
+
+/// Moves a link's click events and daily counters from `from` to `to`
+///
+/// Runs inside the caller's transaction so a rename either moves the record
+/// and its history together or not at all.
+pub fn rename_link_stats(write_txn: &WriteTransaction, from: &str, to: &str) -> Result<(), redb::Error> {
+    let start_key = format!("{}:", from);
+    let end_key = format!("{}:{{", from);
+
+    let mut events = write_txn.open_table(TABLE_CLICK_EVENTS)?;
+    let mut moved = Vec::new();
+    for entry in events.range(start_key.as_str()..end_key.as_str())? {
+        let (key, value) = entry?;
+        if let Some((event_id, micros)) = parse_event_key(key.value()) {
+            if event_id == from {
+                moved.push((key.value().to_string(), micros, value.value().to_string()));
+            }
+        }
+    }
+    for (key, micros, value) in moved {
+        let mut event: ClickEvent = match serde_json::from_str(&value) {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+        event.id = to.to_string();
+        events.remove(key.as_str())?;
+        events.insert(
+            format!("{}:{}", to, micros).as_str(),
+            serde_json::to_string(&event).unwrap().as_str(),
+        )?;
+    }
+
+    let mut daily = write_txn.open_table(TABLE_DAILY_STATS)?;
+    let mut moved = Vec::new();
+    for entry in daily.range(start_key.as_str()..end_key.as_str())? {
+        let (key, count) = entry?;
+        if let Some((day_id, day)) = key.value().rsplit_once(':') {
+            if day_id == from {
+                moved.push((key.value().to_string(), day.to_string(), count.value()));
+            }
+        }
+    }
+    for (key, day, count) in moved {
+        daily.remove(key.as_str())?;
+        daily.insert(format!("{}:{}", to, day).as_str(), count)?;
+    }
+
+    Ok(())
+}
+
+/// Counts of entries removed by `purge_older_than`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PurgeOutcome {
+    /// Click events removed from `TABLE_CLICK_EVENTS`
+    pub events: usize,
+
+    /// Daily counters removed from `TABLE_DAILY_STATS`
+    pub daily_stats: usize,
+}
+
+/// Deletes click events older than `cutoff` and daily stats for days before it
+///
+/// Keys are collected with a read transaction and removed in batches of
+/// `PURGE_BATCH_SIZE` per write transaction, so a large purge never holds the
+/// writer lock for long.
+pub fn purge_older_than(state: &AppState, cutoff: DateTime<Utc>) -> Result<PurgeOutcome, redb::Error> {
+    let cutoff_micros = cutoff.timestamp_micros();
+    let cutoff_day = cutoff.format("%Y-%m-%d").to_string();
+
+    // Collect expired keys. Ids may contain ':' so keys split on the last one.
+    let (event_keys, daily_keys) = {
+        let read_txn = state.begin_primary_read()?;
+
+        let mut event_keys = Vec::new();
+        for entry in read_txn.open_table(TABLE_CLICK_EVENTS)?.iter()? {
+            let (key, _) = entry?;
+            let expired = parse_event_key(key.value())
+                .is_some_and(|(_, micros)| micros < cutoff_micros);
+            if expired {
+                event_keys.push(key.value().to_string());
+            }
+        }
+
+        let mut daily_keys = Vec::new();
+        for entry in read_txn.open_table(TABLE_DAILY_STATS)?.iter()? {
+            let (key, _) = entry?;
+            let expired = key
+                .value()
+                .rsplit_once(':')
+                .is_some_and(|(_, day)| day < cutoff_day.as_str());
+            if expired {
+                daily_keys.push(key.value().to_string());
+            }
+        }
+
+        (event_keys, daily_keys)
+    };
+
+    for batch in event_keys.chunks(PURGE_BATCH_SIZE) {
+        let write_txn = state.begin_write()?;
+        {
+            let mut table = write_txn.open_table(TABLE_CLICK_EVENTS)?;
+            for key in batch {
+                table.remove(key.as_str())?;
+            }
+        }
+        write_txn.commit()?;
+    }
+
+    for batch in daily_keys.chunks(PURGE_BATCH_SIZE) {
+        let write_txn = state.begin_write()?;
+        {
+            let mut table = write_txn.open_table(TABLE_DAILY_STATS)?;
+            for key in batch {
+                table.remove(key.as_str())?;
+            }
+        }
+        write_txn.commit()?;
+    }
+
+    Ok(PurgeOutcome {
+        events: event_keys.len(),
+        daily_stats: daily_keys.len(),
+    })
+}
+
+/// Purges click events and daily stats older than a retention window
+///
+/// # Request Body
+///
+/// ```json
+/// { "older_than_days": 90 }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "purged_events": 1200,
+///   "purged_daily_stats": 45,
+///   "cutoff": "2025-10-19T13:40:00Z"
+/// }
+/// ```
+///
+/// - **200 OK** - Purge completed
+/// - **400 Bad Request** - `older_than_days` is 0
+pub async fn purge_events(
+    State(state): State<AppState>,
+    Json(payload): Json<PurgeEventsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if payload.older_than_days == 0 {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "older_than_days must be at least 1",
+                "code": "invalid_retention"
+            })),
+        )
+            .into_response());
+    }
+
+    let cutoff = Utc::now() - Duration::days(i64::from(payload.older_than_days));
+
+    // The purge scans whole tables, so keep it off the async worker threads
+    let outcome = tokio::task::spawn_blocking(move || purge_older_than(&state, cutoff)).await??;
+
+    Ok(Json(json!({
+        "purged_events": outcome.events,
+        "purged_daily_stats": outcome.daily_stats,
+        "cutoff": cutoff,
+    }))
+    .into_response())
+}
+
+/// Returns a link's clicks bucketed by hour of day
+///
+/// # Query Parameters
+///
+/// - `tz` (optional) - Offset applied before bucketing, as whole hours (`7`,
+///   `-5`) or `±HH:MM` (`+05:30`). Defaults to UTC.
+/// - `token` (optional) - Preview token for this slug, replacing the API
+///   credentials (see `preview_token`)
+///
+/// # Example Request
+///
+/// `GET /api/urls/abc123/heatmap?tz=+07:00`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "abc123",
+///   "tz": "+07:00",
+///   "total": 42,
+///   "hours": [0, 0, 1, 5, ...]
+/// }
+/// ```
+///
+/// - **200 OK** - `hours` has 24 entries, index 0 being midnight to 1am
+/// - **400 Bad Request** - Invalid `tz`
+/// - **401 Unauthorized** - `token` is invalid, expired or for another link
+/// - **404 Not Found** - Short URL does not exist
+pub async fn click_heatmap(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<HeatmapParams>,
+) -> Result<impl IntoResponse, AppError> {
+    if let TokenCheck::Rejected(response) = preview_token::check(&state, params.token.as_deref(), &id) {
+        return Ok(response);
+    }
+
+    let offset = match params.tz.as_deref() {
+        None | Some("") => FixedOffset::east_opt(0).unwrap(),
+        Some(tz) => match parse_tz_offset(tz) {
+            Some(offset) => offset,
+            None => {
+                return Ok((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "tz must be an offset in hours (e.g. 7) or ±HH:MM between -12:00 and +14:00",
+                        "code": "invalid_tz"
+                    })),
+                )
+                    .into_response())
+            }
+        },
+    };
+
+    let exists = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+        table.get(id.as_str())?.is_some()
+    };
+    if !exists {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    }
+
+    let timestamps = event_timestamps(&state, &id)?;
+    let hours = hourly_buckets(&timestamps, offset);
+
+    Ok(Json(json!({
+        "id": id,
+        "tz": offset.to_string(),
+        "total": timestamps.len(),
+        "hours": hours,
+    }))
+    .into_response())
+}
+
+/// Pages through a link's click events, newest first
+///
+/// Events are keyed `{id}:{micros}`, so cursors are event timestamps in
+/// microseconds since the epoch and each page is one range scan, however
+/// long the history.
+///
+/// # Query Parameters
+///
+/// - `limit` (optional) - Events per page, max 1000 (default: 100)
+/// - `before` (optional) - Only events older than this cursor; pass the
+///   previous page's `next_before` to continue
+/// - `after` (optional) - Only events newer than this cursor, e.g. to poll
+///   for new clicks since the newest one seen
+/// - `ref_id` (optional) - Verified against the link's owner, as for deletion
+///
+/// # Example Request
+///
+/// `GET /api/urls/abc123/events?limit=100&before=1768657200000000`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "abc123",
+///   "events": [{ "timestamp": "...", "cursor": 1768657199000000, "referrer": null, ... }],
+///   "has_more": true,
+///   "next_before": 1768650000000000
+/// }
+/// ```
+///
+/// - **206 Partial Content** - More events remain; `next_before` continues
+/// - **200 OK** - This page reaches the end of the window; `next_before` is null
+/// - **403 Forbidden** - ref_id does not match the owner
+/// - **404 Not Found** - Short URL does not exist
+pub async fn list_click_events(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<EventsParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+
+    let owner = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+        load_record(&table, &id)?.map(|record| record.ref_id)
+    };
+    let Some(owner) = owner else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    };
+
+    if let Some(request_ref_id) = &ref_id {
+        if owner.as_ref() != Some(request_ref_id) {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "You are not authorized to view this link's events",
+                    "code": "forbidden"
+                })),
+            )
+                .into_response());
+        }
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_EVENTS_PAGE).clamp(1, MAX_EVENTS_PAGE);
+    let page = events_page(&state, &id, params.before, params.after, limit)?;
+
+    let next_before = page.events.last().filter(|_| page.has_more).map(|(micros, _)| *micros);
+    let events: Vec<_> = page
+        .events
+        .into_iter()
+        .map(|(micros, event)| {
+            let mut event = json!(event);
+            event["cursor"] = json!(micros);
+            event
+        })
+        .collect();
+
+    let status = if page.has_more { StatusCode::PARTIAL_CONTENT } else { StatusCode::OK };
+    Ok((
+        status,
+        Json(json!({
+            "id": id,
+            "events": events,
+            "has_more": page.has_more,
+            "next_before": next_before,
+        })),
+    )
+        .into_response())
+}
+
+/// Sums the daily click counters of several links for each day in `from..=to`
+///
+/// Returns one `(day, clicks)` pair per day, including days without clicks.
+pub fn daily_totals(
+    state: &AppState,
+    ids: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(NaiveDate, u64)>, redb::Error> {
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_DAILY_STATS)?;
+
+    let mut totals: Vec<(NaiveDate, u64)> = from
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| (day, 0))
+        .collect();
+
+    for id in ids {
+        // Dates are fixed-width, so "{id}:{from}"..="{id}:{to}" spans the range
+        let start_key = format!("{}:{}", id, from.format("%Y-%m-%d"));
+        let end_key = format!("{}:{}", id, to.format("%Y-%m-%d"));
+
+        for entry in table.range(start_key.as_str()..=end_key.as_str())? {
+            let (key, count) = entry?;
+            let Some((day_id, day)) = key.value().rsplit_once(':') else {
+                continue;
+            };
+            let Ok(day) = NaiveDate::parse_from_str(day, "%Y-%m-%d") else {
+                continue;
+            };
+            if day_id != id {
+                continue;
+            }
+            if let Some(slot) = totals.get_mut((day - from).num_days() as usize) {
+                slot.1 += count.value();
+            }
+        }
+    }
+
+    Ok(totals)
+}
+
+/// Sums the daily clicks of all of a tenant's links over a date range
+///
+/// # Query Parameters
+///
+/// - `ref_id` (required) - Tenant whose links are summed
+/// - `from` (optional) - First UTC day, `YYYY-MM-DD` (default: six days before `to`)
+/// - `to` (optional) - Last UTC day, `YYYY-MM-DD` (default: today)
+///
+/// # Example Request
+///
+/// `GET /api/urls/stats-summary?ref_id=user_123&from=2026-01-12&to=2026-01-18`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "ref_id": "user_123",
+///   "from": "2026-01-12",
+///   "to": "2026-01-18",
+///   "total_clicks": 42,
+///   "by_day": [{ "date": "2026-01-12", "clicks": 5 }, ...]
+/// }
+/// ```
+///
+/// - **200 OK** - `by_day` lists every day in the range, oldest first
+/// - **400 Bad Request** - `ref_id` missing, a malformed date, `from` after
+///   `to`, or a range longer than 366 days
+pub async fn stats_summary(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<StatsSummaryParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let bad_request = |error: &str, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": error,
+                "code": code
+            })),
+        )
+            .into_response()
+    };
+
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok(bad_request("ref_id is required", "missing_ref_id"));
+    };
+
+    let parse_day = |value: Option<&str>| match value.filter(|value| !value.is_empty()) {
+        None => Ok(None),
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d").map(Some),
+    };
+    let (Ok(to), Ok(from)) = (parse_day(params.to.as_deref()), parse_day(params.from.as_deref())) else {
+        return Ok(bad_request("from and to must be dates formatted as YYYY-MM-DD", "invalid_date"));
+    };
+    let to = to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = from.unwrap_or(to - Duration::days(6));
+
+    if from > to || (to - from).num_days() >= SUMMARY_MAX_DAYS {
+        return Ok(bad_request(
+            &format!("from must not be after to, and the range may span at most {} days", SUMMARY_MAX_DAYS),
+            "invalid_range",
+        ));
+    }
+
+    // Every link of the tenant, deduplicated in case the index has stale entries
+    let ids: Vec<String> = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_REF_INDEX)?;
+        let start_key = format!("{}:", ref_id);
+        let end_key = format!("{}:{{", ref_id);
+
+        let mut ids: Vec<String> = table
+            .range(start_key.as_str()..end_key.as_str())?
+            .map(|res| res.map(|(_, id)| id.value().to_string()))
+            .collect::<Result<_, _>>()?;
+        ids.sort();
+        ids.dedup();
+        ids
+    };
+
+    let totals = daily_totals(&state, &ids, from, to)?;
+    let total_clicks: u64 = totals.iter().map(|(_, clicks)| clicks).sum();
+    let by_day: Vec<_> = totals
+        .iter()
+        .map(|(day, clicks)| json!({ "date": day.format("%Y-%m-%d").to_string(), "clicks": clicks }))
+        .collect();
+
+    Ok(Json(json!({
+        "ref_id": ref_id,
+        "from": from.format("%Y-%m-%d").to_string(),
+        "to": to.format("%Y-%m-%d").to_string(),
+        "total_clicks": total_clicks,
+        "by_day": by_day,
+    }))
+    .into_response())
+}
@@ -0,0 +1,405 @@
+//! Runtime configuration loaded from environment variables
+//!
+//! Settings that only need to be read once are collected here at startup and
+//! shared with every handler through `AppState`.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::HeaderName;
+
+use crate::dedup::DedupScope;
+use crate::middleware::DEFAULT_REQUEST_ID_HEADER;
+use crate::slug::{DEFAULT_SLUG_MAX_RETRIES, RANDOM_SLUG_LENGTH};
+use crate::record_format::RecordFormat;
+use crate::slug_generator::SlugStrategy;
+use crate::tags::TagCase;
+
+/// Application configuration resolved at startup
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Contents of the custom HTML page served for unknown short links
+    ///
+    /// Loaded from the file at `NOT_FOUND_TEMPLATE_PATH`. `None` when the variable
+    /// is unset or the file cannot be read, in which case the default response is used.
+    pub not_found_template: Option<String>,
+
+    /// Wrong passwords allowed for a protected link before it is locked
+    pub password_max_attempts: u32,
+
+    /// How long a protected link stays locked after too many wrong passwords
+    pub password_lockout: Duration,
+
+    /// Maximum number of links packed into a single QR bundle archive
+    pub qr_bundle_max_links: usize,
+
+    /// Maximum number of API write requests handled at once
+    ///
+    /// Excess writes are rejected with 503 instead of queueing behind redb's
+    /// single writer lock.
+    pub max_concurrent_writes: usize,
+
+    /// Maximum number of redirects recording their click at once
+    ///
+    /// A slot is held until the click is committed and its click webhook, if
+    /// any, delivered. Redirects beyond this are still served, but their click
+    /// and webhook are dropped rather than queued so latency stays low during
+    /// a spike.
+    pub max_concurrent_redirects: usize,
+
+    /// Times a create or delete is retried after a transient storage error
+    pub write_retries: u32,
+
+    /// Maximum number of entries accepted by any batch endpoint
+    pub max_batch_size: usize,
+
+    /// Maximum number of ids accepted by a single destination validation batch
+    /// (further capped by `max_batch_size`)
+    pub validate_max_batch: usize,
+
+    /// Destinations checked in parallel while validating a batch
+    pub validate_concurrency: usize,
+
+    /// Timeout applied to each outbound destination check
+    pub validate_timeout: Duration,
+
+    /// Redirects followed per destination check before giving up
+    ///
+    /// Clamped to `1..=MAX_REDIRECT_HOPS_LIMIT` when read from the environment.
+    pub max_redirect_hops: usize,
+
+    /// Whether listing a ref_id with no links returns 404 instead of an empty 200
+    pub empty_list_404: bool,
+
+    /// Scheme generating the core of slugs for links without a custom id
+    pub slug_strategy: SlugStrategy,
+
+    /// Length of the random part of generated slugs
+    ///
+    /// Used by the `random` and `hash` strategies.
+    pub slug_length: usize,
+
+    /// Slugs generated after a generated one is already taken, before a create fails
+    pub slug_max_retries: u32,
+
+    /// Marker prepended to every generated slug (e.g. `go-`)
+    pub slug_prefix: String,
+
+    /// Marker appended to every generated slug (e.g. `-promo`)
+    pub slug_suffix: String,
+
+    /// Whether custom ids are wrapped in the prefix/suffix as well
+    ///
+    /// When disabled, custom ids that already carry the prefix or suffix are
+    /// rejected so they cannot collide with generated slugs.
+    pub slug_affix_custom: bool,
+
+    /// Sustained API requests allowed per caller per minute (0 disables limiting)
+    pub rate_limit_per_minute: u32,
+
+    /// Requests a caller may burst before being limited
+    pub rate_limit_burst: u32,
+
+    /// JWT subjects (e.g. internal services) that are never rate limited
+    pub rate_limit_exempt_ref_ids: HashSet<String>,
+
+    /// Reverse proxies whose `X-Forwarded-For` is believed
+    ///
+    /// Requests from any other peer are attributed to the peer address
+    /// itself, so clients cannot pick their own identity (see
+    /// `geoip::trusted_client_ip`).
+    pub trusted_proxies: HashSet<IpAddr>,
+
+    /// Path to a MaxMind GeoLite2/GeoIP2 Country or City database
+    ///
+    /// When set, click events record the visitor's country.
+    pub geoip_db: Option<String>,
+
+    /// Whether submitted URLs are trimmed and stripped of control characters
+    pub trim_urls: bool,
+
+    /// Status returned by a successful create, `201` or `200`
+    pub create_success_status: u16,
+
+    /// Which existing links a `dedup` create may return
+    pub dedup_scope: DedupScope,
+
+    /// How often expired slug reservations are deleted
+    pub reservation_sweep_interval: Duration,
+
+    /// Secret signing stats preview tokens; `None` disables them
+    ///
+    /// Must differ from `JWT_SECRET`, so a preview token is never accepted
+    /// as API credentials.
+    pub preview_token_secret: Option<String>,
+
+    /// Whether `list_urls` checks each ref_id index hit against its record
+    ///
+    /// Mismatched entries are skipped, logged and deleted. Off by default
+    /// since a repair needs a write transaction during a read.
+    pub validate_index_on_read: bool,
+
+    /// Whether requests forwarded as plain http are redirected to https
+    pub force_https: bool,
+
+    /// Header read and echoed by the request-id middleware
+    ///
+    /// Lets the service join whatever correlation header the surrounding
+    /// infrastructure uses (e.g. `X-Correlation-Id`).
+    pub request_id_header: HeaderName,
+
+    /// Encoding used when writing records (see `record_format`)
+    pub record_format: RecordFormat,
+
+    /// Tags given to new links whose request has no `tags`
+    ///
+    /// At most `MAX_TAGS` are kept, in the order configured.
+    pub default_tags: Vec<String>,
+
+    /// Whether the public `/{id}` redirect route is left out, for API-only deployments
+    pub disable_redirect_route: bool,
+
+    /// Whether `/{id}/` redirects like `/{id}` instead of returning 404
+    pub trim_trailing_slash: bool,
+
+    /// Which spelling of case-insensitively duplicate tags is stored (see `tags`)
+    pub tag_case: TagCase,
+
+    /// Time an API handler may take to produce its response before a 408
+    pub request_timeout: Duration,
+
+    /// The same for exports, QR bundles, destination checks and the live log,
+    /// which legitimately take longer (see `route::with_timeouts`)
+    pub long_request_timeout: Duration,
+}
+
+/// Upper bound accepted for `MAX_REDIRECT_HOPS`
+pub const MAX_REDIRECT_HOPS_LIMIT: usize = 20;
+
+/// Upper bound accepted for `SLUG_LENGTH`
+pub const MAX_SLUG_LENGTH: usize = 32;
+
+/// Upper bound accepted for `SLUG_MAX_RETRIES`, keeping a create's write transaction short
+pub const SLUG_MAX_RETRIES_LIMIT: u32 = 100;
+
+/// Most tags a link may carry
+pub const MAX_TAGS: usize = 20;
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            not_found_template: None,
+            password_max_attempts: 5,
+            password_lockout: Duration::from_secs(300),
+            qr_bundle_max_links: 500,
+            max_concurrent_writes: 64,
+            max_concurrent_redirects: 256,
+            write_retries: 3,
+            max_batch_size: 1000,
+            validate_max_batch: 100,
+            validate_concurrency: 8,
+            validate_timeout: Duration::from_secs(5),
+            max_redirect_hops: 5,
+            empty_list_404: false,
+            slug_strategy: SlugStrategy::Random,
+            slug_length: RANDOM_SLUG_LENGTH,
+            slug_max_retries: DEFAULT_SLUG_MAX_RETRIES,
+            slug_prefix: String::new(),
+            slug_suffix: String::new(),
+            slug_affix_custom: false,
+            rate_limit_per_minute: 0,
+            rate_limit_burst: 60,
+            rate_limit_exempt_ref_ids: HashSet::new(),
+            trusted_proxies: HashSet::new(),
+            geoip_db: None,
+            trim_urls: true,
+            create_success_status: 201,
+            dedup_scope: DedupScope::RefId,
+            reservation_sweep_interval: Duration::from_secs(60),
+            preview_token_secret: None,
+            validate_index_on_read: false,
+            force_https: false,
+            request_id_header: DEFAULT_REQUEST_ID_HEADER,
+            record_format: RecordFormat::Json,
+            default_tags: Vec::new(),
+            disable_redirect_route: false,
+            trim_trailing_slash: false,
+            tag_case: TagCase::Preserve,
+            request_timeout: Duration::from_secs(30),
+            long_request_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl Config {
+    /// Builds the configuration from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `NOT_FOUND_TEMPLATE_PATH` - Path to an HTML file served on redirect misses
+    /// - `LINK_PASSWORD_MAX_ATTEMPTS` - Wrong passwords before lockout (default: 5)
+    /// - `LINK_PASSWORD_LOCKOUT_SECS` - Lockout duration in seconds (default: 300)
+    /// - `QR_BUNDLE_MAX_LINKS` - Maximum links per QR bundle archive (default: 500)
+    /// - `MAX_CONCURRENT_WRITES` - Concurrent API writes before 503 (default: 64)
+    /// - `MAX_CONCURRENT_REDIRECTS` - Redirects counting clicks at once before counts are dropped (default: 256)
+    /// - `WRITE_RETRIES` - Retries of a create or delete after a transient storage error (default: 3)
+    /// - `MAX_BATCH_SIZE` - Maximum entries per request on every batch endpoint (default: 1000)
+    /// - `VALIDATE_MAX_BATCH` - Maximum ids per destination validation (default: 100)
+    /// - `VALIDATE_CONCURRENCY` - Parallel destination checks (default: 8)
+    /// - `VALIDATE_TIMEOUT_SECS` - Timeout per destination check (default: 5)
+    /// - `MAX_REDIRECT_HOPS` - Redirects followed per check, 1 to 20 (default: 5)
+    /// - `EMPTY_LIST_404` - `true` to 404 on a ref_id without links (default: false)
+    /// - `SLUG_STRATEGY` - `random`, `base62`, `words` or `hash` (default: random)
+    /// - `SLUG_LENGTH` - Random characters per generated slug, 1 to 32 (default: 6)
+    /// - `SLUG_MAX_RETRIES` - New slugs tried after a generated one is taken, 0 to 100 (default: 4)
+    /// - `SLUG_PREFIX` / `SLUG_SUFFIX` - Markers wrapped around generated slugs (default: none)
+    /// - `SLUG_AFFIX_CUSTOM` - `true` to wrap custom ids too (default: false)
+    /// - `RATE_LIMIT_PER_MINUTE` - API requests per caller per minute, 0 = off (default: 0)
+    /// - `RATE_LIMIT_BURST` - Requests a caller may burst (default: 60)
+    /// - `RATE_LIMIT_EXEMPT_REF_IDS` - Comma-separated JWT subjects never limited
+    /// - `TRUSTED_PROXIES` - Comma-separated proxy IPs whose `X-Forwarded-For` is believed (default: none)
+    /// - `GEOIP_DB` - Path to a MaxMind `.mmdb` file for click countries
+    /// - `TRIM_URLS` - `false` to store submitted URLs verbatim (default: true)
+    /// - `CREATE_SUCCESS_STATUS` - `200` for clients that reject `201` (default: 201)
+    /// - `DEDUP_SCOPE` - `ref_id` or `global`, where `dedup` creates look (default: ref_id)
+    /// - `RESERVATION_SWEEP_SECS` - Interval between expired reservation sweeps (default: 60)
+    /// - `PREVIEW_TOKEN_SECRET` - Secret signing shareable stats tokens (default: none, disabled)
+    /// - `VALIDATE_INDEX_ON_READ` - `true` to skip and repair mismatched ref_id index entries when listing (default: false)
+    /// - `FORCE_HTTPS` - `true` to 301 requests with `X-Forwarded-Proto: http` to https (default: false)
+    /// - `REQUEST_ID_HEADER` - Header carrying the request id (default: X-Request-Id)
+    /// - `RECORD_FORMAT` - `json` or `bincode`, how records are stored (default: json)
+    /// - `DEFAULT_TAGS` - Comma-separated tags for links created without `tags` (default: none)
+    /// - `DISABLE_REDIRECT_ROUTE` - `true` to serve only `/api`, without `/{id}` redirects (default: false)
+    /// - `TRIM_TRAILING_SLASH` - `true` to redirect `/{id}/` like `/{id}` (default: false)
+    /// - `TAG_CASE` - `preserve` or `lowercase`, the spelling kept for duplicate tags (default: preserve)
+    /// - `REQUEST_TIMEOUT_SECS` - Seconds an API handler may take before a 408 (default: 30)
+    /// - `LONG_REQUEST_TIMEOUT_SECS` - The same for export and other slow endpoints (default: 300)
+    pub fn from_env() -> Self {
+        let defaults = Config::default();
+
+        Config {
+            not_found_template: load_not_found_template(),
+            password_max_attempts: env_or("LINK_PASSWORD_MAX_ATTEMPTS", defaults.password_max_attempts),
+            password_lockout: Duration::from_secs(env_or(
+                "LINK_PASSWORD_LOCKOUT_SECS",
+                defaults.password_lockout.as_secs(),
+            )),
+            qr_bundle_max_links: env_or("QR_BUNDLE_MAX_LINKS", defaults.qr_bundle_max_links),
+            max_concurrent_writes: env_or("MAX_CONCURRENT_WRITES", defaults.max_concurrent_writes),
+            max_concurrent_redirects: env_or("MAX_CONCURRENT_REDIRECTS", defaults.max_concurrent_redirects),
+            write_retries: env_or("WRITE_RETRIES", defaults.write_retries),
+            max_batch_size: env_or("MAX_BATCH_SIZE", defaults.max_batch_size),
+            validate_max_batch: env_or("VALIDATE_MAX_BATCH", defaults.validate_max_batch),
+            validate_concurrency: env_or("VALIDATE_CONCURRENCY", defaults.validate_concurrency),
+            validate_timeout: Duration::from_secs(env_or(
+                "VALIDATE_TIMEOUT_SECS",
+                defaults.validate_timeout.as_secs(),
+            )),
+            max_redirect_hops: env_or("MAX_REDIRECT_HOPS", defaults.max_redirect_hops)
+                .clamp(1, MAX_REDIRECT_HOPS_LIMIT),
+            empty_list_404: env_or("EMPTY_LIST_404", defaults.empty_list_404),
+            slug_strategy: env_or("SLUG_STRATEGY", defaults.slug_strategy),
+            slug_length: env_or("SLUG_LENGTH", defaults.slug_length).clamp(1, MAX_SLUG_LENGTH),
+            slug_max_retries: env_or("SLUG_MAX_RETRIES", defaults.slug_max_retries).min(SLUG_MAX_RETRIES_LIMIT),
+            slug_prefix: env::var("SLUG_PREFIX").unwrap_or(defaults.slug_prefix),
+            slug_suffix: env::var("SLUG_SUFFIX").unwrap_or(defaults.slug_suffix),
+            slug_affix_custom: env_or("SLUG_AFFIX_CUSTOM", defaults.slug_affix_custom),
+            rate_limit_per_minute: env_or("RATE_LIMIT_PER_MINUTE", defaults.rate_limit_per_minute),
+            rate_limit_burst: env_or("RATE_LIMIT_BURST", defaults.rate_limit_burst),
+            rate_limit_exempt_ref_ids: env_list("RATE_LIMIT_EXEMPT_REF_IDS"),
+            trusted_proxies: parse_trusted_proxies(&env::var("TRUSTED_PROXIES").unwrap_or_default()),
+            geoip_db: env::var("GEOIP_DB").ok().filter(|path| !path.is_empty()),
+            trim_urls: env_or("TRIM_URLS", defaults.trim_urls),
+            create_success_status: match env_or("CREATE_SUCCESS_STATUS", defaults.create_success_status) {
+                status @ (200 | 201) => status,
+                _ => defaults.create_success_status,
+            },
+            dedup_scope: env_or("DEDUP_SCOPE", defaults.dedup_scope),
+            reservation_sweep_interval: Duration::from_secs(
+                env_or("RESERVATION_SWEEP_SECS", defaults.reservation_sweep_interval.as_secs()).max(1),
+            ),
+            preview_token_secret: env::var("PREVIEW_TOKEN_SECRET").ok().filter(|secret| !secret.is_empty()),
+            validate_index_on_read: env_or("VALIDATE_INDEX_ON_READ", defaults.validate_index_on_read),
+            force_https: env_or("FORCE_HTTPS", defaults.force_https),
+            request_id_header: env_or("REQUEST_ID_HEADER", defaults.request_id_header),
+            record_format: env_or("RECORD_FORMAT", defaults.record_format),
+            default_tags: parse_default_tags(&env::var("DEFAULT_TAGS").unwrap_or_default()),
+            disable_redirect_route: env_or("DISABLE_REDIRECT_ROUTE", defaults.disable_redirect_route),
+            trim_trailing_slash: env_or("TRIM_TRAILING_SLASH", defaults.trim_trailing_slash),
+            tag_case: env_or("TAG_CASE", defaults.tag_case),
+            request_timeout: Duration::from_secs(
+                env_or("REQUEST_TIMEOUT_SECS", defaults.request_timeout.as_secs()).max(1),
+            ),
+            long_request_timeout: Duration::from_secs(
+                env_or("LONG_REQUEST_TIMEOUT_SECS", defaults.long_request_timeout.as_secs()).max(1),
+            ),
+        }
+    }
+}
+
+/// Parses an environment variable, falling back to `default` when unset or invalid
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Parses a comma-separated environment variable, skipping empty entries
+fn env_list(key: &str) -> HashSet<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses `TRUSTED_PROXIES`, skipping (and warning about) entries that are not IPs
+fn parse_trusted_proxies(value: &str) -> HashSet<IpAddr> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.parse() {
+            Ok(address) => Some(address),
+            Err(_) => {
+                tracing::warn!("Ignoring TRUSTED_PROXIES entry {:?}: not an IP address", entry);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Splits `DEFAULT_TAGS`, dropping blanks and repeats and keeping at most `MAX_TAGS`
+fn parse_default_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    if tags.len() > MAX_TAGS {
+        tracing::warn!("DEFAULT_TAGS lists {} tags; only the first {} are applied", tags.len(), MAX_TAGS);
+        tags.truncate(MAX_TAGS);
+    }
+    tags
+}
+
+/// Reads the custom 404 template, logging (but tolerating) unreadable files
+fn load_not_found_template() -> Option<String> {
+    let path = env::var("NOT_FOUND_TEMPLATE_PATH").ok().filter(|p| !p.is_empty())?;
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => Some(contents),
+        Err(err) => {
+            tracing::warn!("Failed to read NOT_FOUND_TEMPLATE_PATH {}: {}", path, err);
+            None
+        }
+    }
+}
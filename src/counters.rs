@@ -0,0 +1,195 @@
+//! Named per-link counters, separate from click tracking
+//!
+//! Apps can keep their own tallies on a link (e.g. conversions) in
+//! `TABLE_LINK_COUNTERS` under `{id}:{name}`. A counter starts at 0, is
+//! bumped by `POST /api/urls/{id}/counter/{name}/incr` inside a write
+//! transaction so concurrent increments are never lost, and is read with
+//! `GET /api/urls/{id}/counter/{name}`. Counters follow renames and are
+//! dropped with the link.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use redb::{ReadableTable, WriteTransaction};
+use serde_json::json;
+
+use crate::database::{load_record, AppState, TABLE_LINK_COUNTERS, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::effective_ref_id;
+use crate::middleware::AuthSubject;
+use crate::model::{CounterParams, UrlRecord};
+
+/// Longest counter name accepted, in characters
+pub const MAX_COUNTER_NAME_LENGTH: usize = 64;
+
+/// Returns whether `name` is 1 to `MAX_COUNTER_NAME_LENGTH` characters of `[A-Za-z0-9_-]`
+pub fn is_valid_counter_name(name: &str) -> bool {
+    (1..=MAX_COUNTER_NAME_LENGTH).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Returns the `TABLE_LINK_COUNTERS` keys of link `id`
+///
+/// Names never contain ':', so entries of longer slugs sharing the `{id}:`
+/// prefix are skipped by checking the part before the last ':'.
+fn counter_keys<T: ReadableTable<&'static str, u64>>(table: &T, id: &str) -> Result<Vec<String>, redb::Error> {
+    let start_key = format!("{}:", id);
+    let end_key = format!("{}:{{", id);
+
+    let mut keys = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (key, _) = entry?;
+        if key.value().rsplit_once(':').is_some_and(|(counter_id, _)| counter_id == id) {
+            keys.push(key.value().to_string());
+        }
+    }
+    Ok(keys)
+}
+
+/// Moves the counters of a renamed link from `from` to `to`
+pub fn rename_counters(write_txn: &WriteTransaction, from: &str, to: &str) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_LINK_COUNTERS)?;
+    for key in counter_keys(&table, from)? {
+        let value = table.remove(key.as_str())?.map(|value| value.value()).unwrap_or_default();
+        let (_, name) = key.rsplit_once(':').unwrap();
+        table.insert(format!("{}:{}", to, name).as_str(), value)?;
+    }
+    Ok(())
+}
+
+/// Drops the counters of a deleted link
+pub fn delete_counters(write_txn: &WriteTransaction, id: &str) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_LINK_COUNTERS)?;
+    for key in counter_keys(&table, id)? {
+        table.remove(key.as_str())?;
+    }
+    Ok(())
+}
+
+/// Shared checks of both endpoints: a valid name, an existing link and its owner
+///
+/// Returns the error response to send, or `None` when the request may proceed.
+fn rejection(record: Option<&UrlRecord>, name: &str, ref_id: Option<&str>) -> Option<Response> {
+    let error = |status: StatusCode, error: String, code: &str| {
+        (status, Json(json!({ "error": error, "code": code }))).into_response()
+    };
+
+    if !is_valid_counter_name(name) {
+        return Some(error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Counter names are 1 to {} letters, digits, '_' or '-'",
+                MAX_COUNTER_NAME_LENGTH
+            ),
+            "invalid_counter_name",
+        ));
+    }
+
+    let Some(record) = record else {
+        return Some(error(StatusCode::NOT_FOUND, "URL not found".to_string(), "not_found"));
+    };
+
+    // Same ownership rule as deletion: only checked when a ref_id is supplied
+    if ref_id.is_some_and(|ref_id| record.ref_id.as_deref() != Some(ref_id)) {
+        return Some(error(
+            StatusCode::FORBIDDEN,
+            "You are not authorized to access this link".to_string(),
+            "forbidden",
+        ));
+    }
+
+    None
+}
+
+/// Atomically adds 1 to a named counter of a link and returns the new value
+///
+/// # Query Parameters
+///
+/// - `ref_id` (optional) - Owner to verify, as for deletion
+///
+/// # Example Request
+///
+/// `POST /api/urls/abc123/counter/conversions/incr?ref_id=user_123`
+///
+/// # Response
+///
+/// - **200 OK** - `{"id": "abc123", "name": "conversions", "value": 4}`
+/// - **400 Bad Request** - The name is not 1 to `MAX_COUNTER_NAME_LENGTH`
+///   characters of `[A-Za-z0-9_-]` (`invalid_counter_name`)
+/// - **403 Forbidden** - ref_id does not match the owner
+/// - **404 Not Found** - URL does not exist
+pub async fn increment_counter(
+    Path((id, name)): Path<(String, String)>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<CounterParams>,
+) -> Result<Response, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+
+    // Read and written in one transaction so concurrent increments serialize
+    let write_txn = state.begin_write()?;
+    let (id, value) = {
+        let record = load_record(&write_txn.open_table(TABLE_URLS)?, &id)?;
+        if let Some(response) = rejection(record.as_ref(), &name, ref_id.as_deref()) {
+            return Ok(response);
+        }
+        let record = record.unwrap();
+
+        let key = format!("{}:{}", record.id, name);
+        let mut table = write_txn.open_table(TABLE_LINK_COUNTERS)?;
+        let value = table
+            .get(key.as_str())?
+            .map(|value| value.value())
+            .unwrap_or(0)
+            .saturating_add(1);
+        table.insert(key.as_str(), value)?;
+        (record.id, value)
+    };
+    write_txn.commit()?;
+
+    Ok(Json(json!({ "id": id, "name": name, "value": value })).into_response())
+}
+
+/// Returns the value of a named counter of a link; never-incremented counters are 0
+///
+/// # Query Parameters
+///
+/// - `ref_id` (optional) - Owner to verify, as for deletion
+///
+/// # Example Request
+///
+/// `GET /api/urls/abc123/counter/conversions?ref_id=user_123`
+///
+/// # Response
+///
+/// - **200 OK** - `{"id": "abc123", "name": "conversions", "value": 4}`
+/// - **400 Bad Request** - The name is invalid (`invalid_counter_name`)
+/// - **403 Forbidden** - ref_id does not match the owner
+/// - **404 Not Found** - URL does not exist
+pub async fn get_counter(
+    Path((id, name)): Path<(String, String)>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<CounterParams>,
+) -> Result<Response, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+
+    let read_txn = state.begin_read()?;
+    let record = load_record(&read_txn.open_table(TABLE_URLS)?, &id)?;
+    if let Some(response) = rejection(record.as_ref(), &name, ref_id.as_deref()) {
+        return Ok(response);
+    }
+    let record = record.unwrap();
+
+    let key = format!("{}:{}", record.id, name);
+    let value = read_txn
+        .open_table(TABLE_LINK_COUNTERS)?
+        .get(key.as_str())?
+        .map(|value| value.value())
+        .unwrap_or(0);
+
+    Ok(Json(json!({ "id": record.id, "name": name, "value": value })).into_response())
+}
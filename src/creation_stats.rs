@@ -0,0 +1,168 @@
+//! Links created over time, for capacity trending
+//!
+//! Every stored link bumps the counter of its creation hour in
+//! `TABLE_CREATE_STATS`, inside the transaction that writes the link.
+//! `GET /api/admin/creation-rate` reads the counters back as a time series
+//! of hourly or daily buckets. Deleted links are still counted, and links
+//! created before the counters existed are not.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, DurationRound, NaiveDate, Utc};
+use redb::{ReadableTable, WriteTransaction};
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::database::{AppState, TABLE_CREATE_STATS};
+use crate::error::AppError;
+use crate::model::{CreationRateParams, RateBucket};
+
+/// Buckets returned when `from` is not given
+pub const DEFAULT_CREATION_RATE_BUCKETS: i64 = 24;
+
+/// Most buckets one request may cover
+pub const MAX_CREATION_RATE_BUCKETS: i64 = 1000;
+
+/// Key of the hour containing `at`, e.g. `2026-01-17T13`
+fn hour_key(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H").to_string()
+}
+
+/// Counts one link created at `created_at`
+pub fn record_creation(write_txn: &WriteTransaction, created_at: DateTime<Utc>) -> Result<(), redb::Error> {
+    let key = hour_key(created_at);
+    let mut table = write_txn.open_table(TABLE_CREATE_STATS)?;
+    let count = table.get(key.as_str())?.map(|value| value.value()).unwrap_or(0);
+    table.insert(key.as_str(), count + 1)?;
+    Ok(())
+}
+
+impl RateBucket {
+    /// Width of one bucket
+    fn width(self) -> Duration {
+        match self {
+            RateBucket::Hour => Duration::hours(1),
+            RateBucket::Day => Duration::days(1),
+        }
+    }
+
+    /// Start of the bucket containing `at`
+    fn start(self, at: DateTime<Utc>) -> DateTime<Utc> {
+        at.duration_trunc(self.width()).unwrap()
+    }
+}
+
+/// Parses an RFC3339 timestamp or a `YYYY-MM-DD` date (midnight UTC)
+fn parse_instant(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+/// Returns links created per hour or day over a time range
+///
+/// # Query Parameters
+///
+/// - `bucket` (optional) - `hour` (default) or `day`, in UTC
+/// - `from` (optional) - Start of the range, RFC3339 or `YYYY-MM-DD`
+///   (default: 23 buckets before `to`)
+/// - `to` (optional) - End of the range, RFC3339 or `YYYY-MM-DD` (default: now)
+///
+/// Both ends are widened to whole buckets and included.
+///
+/// # Example Request
+///
+/// `GET /api/admin/creation-rate?bucket=day&from=2026-01-12&to=2026-01-18`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "bucket": "day",
+///   "from": "2026-01-12T00:00:00Z",
+///   "to": "2026-01-18T00:00:00Z",
+///   "total": 42,
+///   "series": [{ "start": "2026-01-12T00:00:00Z", "created": 5 }, ...]
+/// }
+/// ```
+///
+/// - **200 OK** - `series` lists every bucket in the range, oldest first
+/// - **400 Bad Request** - A malformed `from`/`to` or `bucket`, `from` after
+///   `to`, or more than `MAX_CREATION_RATE_BUCKETS` buckets
+pub async fn creation_rate(
+    State(state): State<AppState>,
+    Query(params): Query<CreationRateParams>,
+) -> Result<Response, AppError> {
+    let bad_request = |error: String, code: &str| {
+        (StatusCode::BAD_REQUEST, Json(json!({ "error": error, "code": code }))).into_response()
+    };
+
+    let bucket = params.bucket.unwrap_or_default();
+    let parse = |value: Option<&str>| value.map(|value| parse_instant(value).ok_or(value.to_string())).transpose();
+    let (to, from) = match (parse(params.to.as_deref()), parse(params.from.as_deref())) {
+        (Ok(to), Ok(from)) => (to, from),
+        (Err(value), _) | (_, Err(value)) => {
+            return Ok(bad_request(
+                format!("'{}' is neither an RFC3339 timestamp nor YYYY-MM-DD", value),
+                "invalid_date",
+            ))
+        }
+    };
+
+    let to = bucket.start(to.unwrap_or_else(Utc::now));
+    let from = from.map_or(to - bucket.width() * (DEFAULT_CREATION_RATE_BUCKETS as i32 - 1), |from| {
+        bucket.start(from)
+    });
+    if from > to {
+        return Ok(bad_request("from must not be after to".to_string(), "invalid_range"));
+    }
+    let buckets = (to - from).num_seconds() / bucket.width().num_seconds() + 1;
+    if buckets > MAX_CREATION_RATE_BUCKETS {
+        return Ok(bad_request(
+            format!("The range spans {} buckets; the maximum is {}", buckets, MAX_CREATION_RATE_BUCKETS),
+            "range_too_long",
+        ));
+    }
+
+    // Hourly counters, summed into the requested buckets
+    let mut counts: HashMap<DateTime<Utc>, u64> = HashMap::new();
+    {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_CREATE_STATS)?;
+        let start_key = hour_key(from);
+        let end_key = hour_key(to + bucket.width());
+        for entry in table.range(start_key.as_str()..end_key.as_str())? {
+            let (key, count) = entry?;
+            let hour = NaiveDate::parse_from_str(&key.value()[..10], "%Y-%m-%d")
+                .ok()
+                .zip(key.value()[11..].parse::<u32>().ok())
+                .and_then(|(date, hour)| date.and_hms_opt(hour, 0, 0));
+            if let Some(hour) = hour {
+                *counts.entry(bucket.start(hour.and_utc())).or_default() += count.value();
+            }
+        }
+    }
+
+    let series: Vec<serde_json::Value> = (0..buckets)
+        .map(|index| {
+            let start = from + bucket.width() * index as i32;
+            json!({ "start": start, "created": counts.get(&start).copied().unwrap_or(0) })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "bucket": bucket,
+        "from": from,
+        "to": to,
+        "total": counts.values().sum::<u64>(),
+        "series": series,
+    }))
+    .into_response())
+}
@@ -3,8 +3,27 @@
 //! This module handles the setup and configuration of the embedded redb database.
 //! It defines the database tables and provides initialization functions.
 
-use redb::{Database, TableDefinition};
+use chrono::{DateTime, Utc};
+use redb::{
+    Database, Key, ReadTransaction, ReadableDatabase, ReadableTable, TableDefinition, TableError, TableHandle,
+    TransactionError, Value, WriteTransaction,
+};
+use std::fmt;
+use std::io::ErrorKind;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Semaphore};
+
+use crate::config::Config;
+use crate::geoip::GeoIp;
+use crate::metrics::Metrics;
+use crate::migrations::{run_migrations, TABLE_META};
+use crate::model::UrlRecord;
+use crate::password::PasswordAttemptLimiter;
+use crate::ratelimit::RateLimiter;
+use crate::record_format;
+use crate::redirect_log::{self, RedirectLogEvent};
+use crate::slug_generator::SlugGenerator;
 
 /// Main table for storing URL records
 /// 
@@ -21,14 +40,152 @@ pub const TABLE_URLS: TableDefinition<&str, &str> = TableDefinition::new("urls_v
 /// This secondary index enables fast lookups and pagination of URLs belonging to a specific ref_id.
 /// 
 /// Key: Composite key in format "{ref_id}:{timestamp_micros}"
-/// Value: JSON-serialized UrlRecord as string
+/// Value: Short URL ID (slug) of the link
 /// 
 /// Example:
 /// - Key: "user_123:1705501234567890"
-/// - Value: '{"id":"abc123","ref_id":"user_123",...}'
+/// - Value: "abc123"
 /// 
 /// The timestamp in the key ensures chronological ordering and uniqueness.
-pub const TABLE_REF_INDEX: TableDefinition<&str, &str> = TableDefinition::new("ref_index_v1");
+/// Only the slug is stored so `TABLE_URLS` stays the single source of truth;
+/// readers resolve each entry with `load_record`. Version 1 of this table
+/// held full record copies and is converted by a migration.
+pub const TABLE_REF_INDEX: TableDefinition<&str, &str> = TableDefinition::new("ref_index_v2");
+
+/// Every link ordered by creation time, oldest first, across all owners
+/// 
+/// Key: Composite key in format "{timestamp_micros, zero-padded to 20 digits}:{id}"
+/// Value: Short URL ID (slug) of the link
+/// 
+/// Example:
+/// - Key: "00001705501234567890:abc123"
+/// - Value: "abc123"
+/// 
+/// The padding makes keys sort chronologically and the slug keeps links
+/// created in the same microsecond apart. Build keys with `created_index_key`.
+pub const TABLE_CREATED_INDEX: TableDefinition<&str, &str> = TableDefinition::new("created_index_v1");
+
+/// Key of a link in `TABLE_CREATED_INDEX`
+pub fn created_index_key(created_at: DateTime<Utc>, id: &str) -> String {
+    format!("{:020}:{}", created_at.timestamp_micros().max(0), id)
+}
+
+/// Loads and deserializes the record stored under `id` in `TABLE_URLS`
+/// 
+/// Returns `Ok(None)` when there is no such record, so stale `TABLE_REF_INDEX`
+/// entries can simply be skipped. A record that cannot be read or decoded is
+/// an error rather than a missing link.
+pub fn load_record<T: ReadableTable<&'static str, &'static str>>(table_main: &T, id: &str) -> Result<Option<UrlRecord>, redb::Error> {
+    match table_main.get(id)? {
+        Some(value) => Ok(Some(record_format::decode(value.value())?)),
+        None => Ok(None),
+    }
+}
+
+/// Password hashes for protected links
+/// 
+/// Kept apart from `TABLE_URLS` so hashes never leak through list responses.
+/// 
+/// Key: Short URL ID (slug) as string
+/// Value: Salted SHA-256 hex digest (see `password::hash_password`)
+pub const TABLE_LINK_PASSWORDS: TableDefinition<&str, &str> = TableDefinition::new("link_passwords_v1");
+
+/// Click event log
+/// 
+/// Key: Composite key in format "{id}:{timestamp_micros}"
+/// Value: JSON-serialized ClickEvent as string
+/// 
+/// Example:
+/// - Key: "abc123:1705501234567890"
+/// - Value: '{"id":"abc123","timestamp":"2026-01-17T13:40:00Z",...}'
+pub const TABLE_CLICK_EVENTS: TableDefinition<&str, &str> = TableDefinition::new("click_events_v1");
+
+/// Per-link daily click counters
+/// 
+/// Key: Composite key in format "{id}:{YYYY-MM-DD}" (UTC date)
+/// Value: Number of clicks on that day
+/// 
+/// Example:
+/// - Key: "abc123:2026-01-17"
+/// - Value: 42
+pub const TABLE_DAILY_STATS: TableDefinition<&str, u64> = TableDefinition::new("daily_stats_v1");
+
+/// Old slugs that keep resolving after a link is renamed
+/// 
+/// Key: Former short URL ID (slug)
+/// Value: Current short URL ID the alias points to
+/// 
+/// Aliases always point directly at a live slug; renaming a link again
+/// repoints its existing aliases instead of chaining them.
+pub const TABLE_ALIASES: TableDefinition<&str, &str> = TableDefinition::new("aliases_v1");
+
+/// Per-tenant preferences
+/// 
+/// Key: Reference ID
+/// Value: JSON-serialized `Preferences` as string
+/// 
+/// Example:
+/// - Key: "user_123"
+/// - Value: '{"default_limit":50}'
+pub const TABLE_PREFERENCES: TableDefinition<&str, &str> = TableDefinition::new("preferences_v1");
+
+/// Reverse index from destination to the link serving it
+/// 
+/// Used by `DEDUP_SCOPE=global` (see the `dedup` module).
+/// 
+/// Key: Original URL
+/// Value: Short URL ID (slug) of the newest link to it
+pub const TABLE_DESTINATIONS: TableDefinition<&str, &str> = TableDefinition::new("destinations_v1");
+
+/// Custom slugs held for later creation (see the `reservations` module)
+/// 
+/// Key: Reserved short URL ID (slug)
+/// Value: JSON-serialized `Reservation` as string
+/// 
+/// Example:
+/// - Key: "spring-sale"
+/// - Value: '{"ref_id":"user_123","expires_at":"2026-01-17T14:00:00Z"}'
+pub const TABLE_RESERVATIONS: TableDefinition<&str, &str> = TableDefinition::new("reservations_v1");
+
+/// Links created per UTC hour, across all owners (see the `creation_stats` module)
+/// 
+/// Key: UTC hour in format "YYYY-MM-DDTHH"
+/// Value: Number of links created in that hour
+/// 
+/// Example:
+/// - Key: "2026-01-17T13"
+/// - Value: 12
+pub const TABLE_CREATE_STATS: TableDefinition<&str, u64> = TableDefinition::new("create_stats_v1");
+
+/// Distinct visitors of links with `max_unique_visitors` (see the `visitors` module)
+/// 
+/// Key: "{id}:{ip_hash}", where `ip_hash` is a salted SHA-256 of the visitor's IP
+/// Value: When the visitor was first seen, in microseconds since the epoch
+/// 
+/// Example:
+/// - Key: "abc123:9f86d081884c7d659a2feaa0c55ad015"
+/// - Value: 1705501234567890
+pub const TABLE_VISITORS: TableDefinition<&str, u64> = TableDefinition::new("visitors_v1");
+
+/// Named app-specific counters of links (see the `counters` module)
+/// 
+/// Key: "{id}:{name}"
+/// Value: Current value of the counter
+/// 
+/// Example:
+/// - Key: "abc123:conversions"
+/// - Value: 4
+pub const TABLE_LINK_COUNTERS: TableDefinition<&str, u64> = TableDefinition::new("link_counters_v1");
+
+/// Destination changes of every link (see the `history` module)
+/// 
+/// Key: "{id}:{changed_at_micros}"
+/// Value: JSON-serialized `DestinationChange` as string
+/// 
+/// Example:
+/// - Key: "abc123:1705487400000000"
+/// - Value: '{"changed_at":"2026-01-17T10:30:00Z","from":"https://old.example","to":"https://new.example"}'
+pub const TABLE_LINK_HISTORY: TableDefinition<&str, &str> = TableDefinition::new("link_history_v1");
 
 /// Application state shared across all request handlers
 /// 
@@ -38,14 +195,247 @@ pub const TABLE_REF_INDEX: TableDefinition<&str, &str> = TableDefinition::new("r
 pub struct AppState {
     /// Thread-safe reference to the embedded database
     pub db: Arc<Database>,
+
+    /// Optional secondary database serving read-only traffic
+    /// 
+    /// When present, redirects and listings read from it (see `reader`) while
+    /// writes always go to `db`. redb locks its file, so the replica must be a
+    /// separate file kept in sync externally (e.g. a periodic copy); reads may
+    /// therefore lag behind writes by up to the sync interval.
+    pub read_db: Option<Arc<Database>>,
+
+    /// Configuration resolved once at startup
+    pub config: Arc<Config>,
+
+    /// Failed-password tracking for protected links
+    pub password_attempts: Arc<PasswordAttemptLimiter>,
+
+    /// Permits bounding concurrent API writes (see `MAX_CONCURRENT_WRITES`)
+    pub write_permits: Arc<Semaphore>,
+
+    /// Permits bounding concurrent click recording (see `MAX_CONCURRENT_REDIRECTS`)
+    pub redirect_permits: Arc<Semaphore>,
+
+    /// In-process operational counters
+    pub metrics: Arc<Metrics>,
+
+    /// Per-caller token buckets (see `RATE_LIMIT_PER_MINUTE`)
+    pub rate_limiter: Arc<RateLimiter>,
+
+    /// Country lookup for click events, when `GEOIP_DB` is configured
+    pub geoip: Option<Arc<GeoIp>>,
+
+    /// Shared client for outbound requests (destination checks, click webhooks)
+    /// 
+    /// Never follows redirects on its own; see `linkcheck::check_destination`.
+    pub http_client: reqwest::Client,
+
+    /// Scheme generating slugs for links without a custom id (see `SLUG_STRATEGY`)
+    pub slug_generator: Arc<dyn SlugGenerator>,
+
+    /// Live feed of served redirects (see `redirect_log`)
+    pub redirect_log: broadcast::Sender<RedirectLogEvent>,
+}
+
+impl AppState {
+    /// Creates the application state with configuration read from the environment
+    pub fn new(db: Database) -> Self {
+        Self::with_config(db, Config::from_env())
+    }
+
+    /// Creates the application state with an explicit configuration
+    /// 
+    /// Mainly useful in tests, where mutating process-wide environment
+    /// variables would race between parallel test cases.
+    pub fn with_config(db: Database, config: Config) -> Self {
+        AppState {
+            db: Arc::new(db),
+            read_db: None,
+            password_attempts: Arc::new(PasswordAttemptLimiter::new(
+                config.password_max_attempts,
+                config.password_lockout,
+            )),
+            write_permits: Arc::new(Semaphore::new(config.max_concurrent_writes.max(1))),
+            redirect_permits: Arc::new(Semaphore::new(config.max_concurrent_redirects.max(1))),
+            metrics: Arc::new(Metrics::default()),
+            geoip: GeoIp::load(config.geoip_db.as_deref()).map(Arc::new),
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limit_per_minute, config.rate_limit_burst)),
+            http_client: reqwest::Client::builder()
+                .timeout(config.validate_timeout)
+                // Redirects are followed manually so hops can be bounded and loops detected
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("Failed to build HTTP client"),
+            slug_generator: config.slug_strategy.generator(),
+            redirect_log: redirect_log::channel(),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Routes read transactions to `replica` instead of the primary database
+    pub fn with_read_replica(mut self, replica: Database) -> Self {
+        self.read_db = Some(Arc::new(replica));
+        self
+    }
+
+    /// Returns the database read-only handlers should use
+    /// 
+    /// This is the read replica when one is configured, otherwise the primary.
+    /// Reads that must observe the latest writes (e.g. conflict checks inside
+    /// a write transaction) should use `db` directly.
+    pub fn reader(&self) -> &Database {
+        self.read_db.as_deref().unwrap_or(&self.db)
+    }
+
+    /// Opens a read transaction on `reader`, counted in `metrics`
+    pub fn begin_read(&self) -> Result<ReadTransaction, TransactionError> {
+        self.metrics.record_read_transaction();
+        self.reader().begin_read()
+    }
+
+    /// Opens a read transaction on the primary, counted in `metrics`
+    /// 
+    /// For reads that must observe the latest writes, even with a replica.
+    pub fn begin_primary_read(&self) -> Result<ReadTransaction, TransactionError> {
+        self.metrics.record_read_transaction();
+        self.db.begin_read()
+    }
+
+    /// Opens a write transaction on the primary, counted in `metrics`
+    pub fn begin_write(&self) -> Result<WriteTransaction, TransactionError> {
+        self.metrics.record_write_transaction();
+        self.db.begin_write()
+    }
+}
+
+/// Delay before the first retry of a failed write; doubled for each further retry
+const WRITE_RETRY_BACKOFF: Duration = Duration::from_millis(5);
+
+/// Whether a storage error is worth retrying with a fresh transaction
+/// 
+/// Only interrupted or timed-out I/O and a still-running transaction are
+/// considered transient. Everything else (corruption, type mismatches, a
+/// previous I/O failure that poisoned the database) fails immediately.
+pub fn is_retryable(err: &redb::Error) -> bool {
+    match err {
+        redb::Error::Io(io) => matches!(
+            io.kind(),
+            ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ),
+        redb::Error::TransactionInProgress => true,
+        _ => false,
+    }
+}
+
+/// Runs a write closure, retrying it up to `retries` times on transient errors
+/// 
+/// `write` must begin and commit its own transaction so every attempt starts
+/// from a clean state; a failed attempt's transaction is dropped, which
+/// aborts it. Business outcomes (conflicts, missing records) belong in `T`,
+/// e.g. as a nested `Result`, so only storage errors are ever retried.
+/// Retries back off briefly (5ms, 10ms, 20ms, ...) without blocking the
+/// runtime's worker thread.
+pub async fn with_write_retry<T, F>(retries: u32, mut write: F) -> Result<T, redb::Error>
+where
+    F: FnMut() -> Result<T, redb::Error>,
+{
+    let mut attempt = 0;
+    loop {
+        match write() {
+            Err(err) if attempt < retries && is_retryable(&err) => {
+                tracing::warn!("Retrying write after transient error (attempt {}): {}", attempt + 1, err);
+                tokio::time::sleep(WRITE_RETRY_BACKOFF * 2u32.saturating_pow(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Errors that can occur while opening the database in `init_db`
+#[derive(Debug)]
+pub enum InitError {
+    /// The database file could not be opened, read or written
+    Storage(redb::Error),
+
+    /// A table exists with a layout this version does not understand, e.g.
+    /// different key/value types written by another release
+    SchemaMismatch {
+        /// Name of the offending table
+        table: String,
+
+        /// redb's description of the mismatch
+        detail: String,
+    },
+
+    /// The recorded schema version is newer than this build's migrations
+    UnsupportedVersion {
+        /// Version recorded in the database
+        found: u64,
+
+        /// Highest version this build can migrate to
+        supported: u64,
+    },
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::Storage(err) => write!(f, "failed to open database: {}", err),
+            InitError::SchemaMismatch { table, detail } => write!(
+                f,
+                "table `{}` has an incompatible schema ({}); the database was likely written by \
+                 a different version of the shortener, restore a matching backup or migrate it",
+                table, detail
+            ),
+            InitError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "database schema version {} is newer than the supported version {}; \
+                 upgrade the shortener before opening this database",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+impl From<redb::Error> for InitError {
+    fn from(err: redb::Error) -> Self {
+        InitError::Storage(err)
+    }
+}
+
+/// Opens (creating if needed) `table`, reporting a layout mismatch by name
+fn ensure_table<K: Key + 'static, V: Value + 'static>(
+    write_txn: &WriteTransaction,
+    table: TableDefinition<K, V>,
+) -> Result<(), InitError> {
+    match write_txn.open_table(table) {
+        Ok(_) => Ok(()),
+        Err(
+            err @ (TableError::TableTypeMismatch { .. }
+            | TableError::TableIsMultimap(_)
+            | TableError::TypeDefinitionChanged { .. }),
+        ) => Err(InitError::SchemaMismatch {
+            table: table.name().to_string(),
+            detail: err.to_string(),
+        }),
+        Err(err) => Err(InitError::Storage(err.into())),
+    }
 }
 
 /// Initializes the embedded database and creates required tables
 /// 
 /// This function:
 /// 1. Creates or opens the database file at the specified path
-/// 2. Opens both the main URLs table and the reference index table
+/// 2. Opens (creating if needed) every table the application uses
 /// 3. Commits the transaction to ensure tables are persisted
+/// 4. Applies pending schema migrations (see the `migrations` module)
+/// 
+/// Tables are opened eagerly: read-only handlers open them in read
+/// transactions, which fail for tables that were never created. Running it
+/// against an already initialized file is a no-op.
 /// 
 /// # Arguments
 /// 
@@ -54,7 +444,10 @@ pub struct AppState {
 /// # Returns
 /// 
 /// * `Ok(Database)` - Successfully initialized database instance
-/// * `Err(redb::Error)` - Database initialization error
+/// * `Err(InitError::SchemaMismatch)` - A table exists with different key/value
+///   types than this version expects; nothing is modified
+/// * `Err(InitError::UnsupportedVersion)` - The file was migrated by a newer release
+/// * `Err(InitError::Storage)` - Any other database error
 /// 
 /// # Example
 /// 
@@ -62,22 +455,61 @@ pub struct AppState {
 /// # use shortener::database::init_db;
 /// let db = init_db("data.db").expect("Failed to initialize database");
 /// ```
-pub fn init_db(db_path: &str) -> Result<Database, redb::Error> {
+pub fn init_db(db_path: &str) -> Result<Database, InitError> {
     // Create or open the database file
-    let db = Database::create(db_path)?;
+    let db = Database::create(db_path).map_err(redb::Error::from)?;
     
     // Begin a write transaction to create tables
-    let write_txn = db.begin_write()?;
-    {
-        // Open (or create if not exists) the main URLs table
-        write_txn.open_table(TABLE_URLS)?;
-        
-        // Open (or create if not exists) the reference index table
-        write_txn.open_table(TABLE_REF_INDEX)?;
-    }
+    let write_txn = db.begin_write().map_err(redb::Error::from)?;
+    
+    // Open (or create if not exists) the main URLs table
+    ensure_table(&write_txn, TABLE_URLS)?;
+    
+    // Open (or create if not exists) the reference index table
+    ensure_table(&write_txn, TABLE_REF_INDEX)?;
+    
+    // Open (or create if not exists) the link password table
+    ensure_table(&write_txn, TABLE_LINK_PASSWORDS)?;
+    
+    // Open (or create if not exists) the analytics tables
+    ensure_table(&write_txn, TABLE_CLICK_EVENTS)?;
+    ensure_table(&write_txn, TABLE_DAILY_STATS)?;
+    
+    // Open (or create if not exists) the slug alias table
+    ensure_table(&write_txn, TABLE_ALIASES)?;
+    
+    // Open (or create if not exists) the tenant preferences table
+    ensure_table(&write_txn, TABLE_PREFERENCES)?;
+    
+    // Open (or create if not exists) the destination reverse index
+    ensure_table(&write_txn, TABLE_DESTINATIONS)?;
+    
+    // Open (or create if not exists) the slug reservation table
+    ensure_table(&write_txn, TABLE_RESERVATIONS)?;
+    
+    // Open (or create if not exists) the destination change history
+    ensure_table(&write_txn, TABLE_LINK_HISTORY)?;
+    
+    // Open (or create if not exists) the hourly creation counters
+    ensure_table(&write_txn, TABLE_CREATE_STATS)?;
+    
+    // Open (or create if not exists) the global creation-time index
+    ensure_table(&write_txn, TABLE_CREATED_INDEX)?;
+    
+    // Open (or create if not exists) the distinct visitors of capped links
+    ensure_table(&write_txn, TABLE_VISITORS)?;
+    
+    // Open (or create if not exists) the named per-link counters
+    ensure_table(&write_txn, TABLE_LINK_COUNTERS)?;
+    
+    // Open (or create if not exists) the schema bookkeeping table
+    ensure_table(&write_txn, TABLE_META)?;
     
     // Commit the transaction to persist the table structures
-    write_txn.commit()?;
+    write_txn.commit().map_err(redb::Error::from)?;
+    
+    // Upgrade existing data to the current layout
+    run_migrations(&db)?;
     
     Ok(db)
-}
\ No newline at end of file
+}
@@ -0,0 +1,141 @@
+//! Duplicate detection for created links
+//!
+//! A create request with `"dedup": true` (and no custom id or password)
+//! returns an existing link to the same destination instead of minting a new
+//! slug. `DEDUP_SCOPE` decides where to look:
+//!
+//! - `ref_id` (default) scans the caller's links through `TABLE_REF_INDEX`;
+//!   callers without a ref_id are never deduplicated
+//! - `global` looks the destination up in `TABLE_DESTINATIONS`, regardless
+//!   of owner, for single-tenant deployments
+//!
+//! Only links that still redirect without a password are reused.
+
+use chrono::Utc;
+use redb::{ReadableTable, WriteTransaction};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::database::{load_record, TABLE_DESTINATIONS, TABLE_REF_INDEX, TABLE_URLS};
+use crate::model::UrlRecord;
+use crate::record_format;
+
+/// Which existing links a deduplicating create may return
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DedupScope {
+    /// Only links owned by the caller's ref_id
+    #[default]
+    RefId,
+
+    /// Any link, whoever owns it
+    Global,
+}
+
+/// Error for an unrecognized `DEDUP_SCOPE` value
+#[derive(Debug)]
+pub struct ParseDedupScopeError;
+
+impl fmt::Display for ParseDedupScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected `ref_id` or `global`")
+    }
+}
+
+impl std::error::Error for ParseDedupScopeError {}
+
+impl FromStr for DedupScope {
+    type Err = ParseDedupScopeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "ref_id" => Ok(DedupScope::RefId),
+            "global" => Ok(DedupScope::Global),
+            _ => Err(ParseDedupScopeError),
+        }
+    }
+}
+
+/// Whether `record` can stand in for a freshly created link
+fn is_reusable(record: &UrlRecord) -> bool {
+    record.is_active
+        && !record.password_protected
+        && record.expires_at.is_none_or(|expires_at| expires_at > Utc::now())
+}
+
+/// Finds a reusable link to `original_url` within `scope`
+///
+/// Records that cannot be deserialized are never reused; storage errors are
+/// returned so the caller's write can be retried.
+pub fn find_existing(
+    write_txn: &WriteTransaction,
+    scope: DedupScope,
+    ref_id: Option<&str>,
+    original_url: &str,
+) -> Result<Option<UrlRecord>, redb::Error> {
+    match scope {
+        DedupScope::RefId => {
+            let Some(ref_id) = ref_id else {
+                return Ok(None);
+            };
+            let table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+            let table_main = write_txn.open_table(TABLE_URLS)?;
+            let start_key = format!("{}:", ref_id);
+            let end_key = format!("{}:{{", ref_id);
+
+            for entry in table_index.range(start_key.as_str()..end_key.as_str())? {
+                let (_, id) = entry?;
+                let Some(record) = load_record(&table_main, id.value())? else {
+                    continue;
+                };
+                if record.original_url == original_url && is_reusable(&record) {
+                    return Ok(Some(record));
+                }
+            }
+            Ok(None)
+        }
+        DedupScope::Global => {
+            let table_destinations = write_txn.open_table(TABLE_DESTINATIONS)?;
+            let Some(id) = table_destinations.get(original_url)? else {
+                return Ok(None);
+            };
+            let id = id.value().to_string();
+
+            let table_main = write_txn.open_table(TABLE_URLS)?;
+            let Some(value) = table_main.get(id.as_str())? else {
+                return Ok(None);
+            };
+            Ok(record_format::decode(value.value())
+                .ok()
+                .filter(is_reusable))
+        }
+    }
+}
+
+/// Points `TABLE_DESTINATIONS` for the record's destination at the record
+///
+/// The newest link wins: with dedup enabled a second link to a destination
+/// only exists when the earlier one could not be reused.
+pub fn index_destination(write_txn: &WriteTransaction, record: &UrlRecord) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_DESTINATIONS)?;
+    table.insert(record.original_url.as_str(), record.id.as_str())?;
+    Ok(())
+}
+
+/// Repoints (`to: Some`) or drops (`to: None`) the entry for `original_url`
+/// if it currently refers to `from`
+pub fn replace_destination(
+    write_txn: &WriteTransaction,
+    original_url: &str,
+    from: &str,
+    to: Option<&str>,
+) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_DESTINATIONS)?;
+    let points_at_from = table.get(original_url)?.is_some_and(|id| id.value() == from);
+    if points_at_from {
+        match to {
+            Some(to) => table.insert(original_url, to)?,
+            None => table.remove(original_url)?,
+        };
+    }
+    Ok(())
+}
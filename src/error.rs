@@ -0,0 +1,125 @@
+//! Errors that abort a request with 500 Internal Server Error
+//!
+//! Handlers return `Result<_, AppError>` and use `?` on storage and
+//! (de)serialization calls, so a failing database or a corrupt record is
+//! reported to the client instead of panicking the worker. The cause is
+//! logged; the response only carries a generic message.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::fmt;
+use tokio::task::JoinError;
+
+use crate::qr::QrError;
+use crate::record_format::DecodeError;
+
+/// Failure inside a handler that the client cannot fix
+#[derive(Debug)]
+pub enum AppError {
+    /// redb failed to open, read, write or commit
+    Storage(redb::Error),
+    /// A stored record could not be decoded
+    Record(DecodeError),
+    /// A stored value could not be (de)serialized as JSON
+    Json(serde_json::Error),
+    /// A QR code or archive could not be rendered
+    Qr(QrError),
+    /// A blocking task panicked or was cancelled
+    Task(JoinError),
+}
+
+impl AppError {
+    /// Machine-readable `code` of the response body
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::Storage(_) | AppError::Record(_) | AppError::Json(_) => "storage_error",
+            AppError::Qr(_) | AppError::Task(_) => "internal_error",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Storage(err) => write!(f, "storage error: {}", err),
+            AppError::Record(err) => err.fmt(f),
+            AppError::Json(err) => write!(f, "invalid stored value: {}", err),
+            AppError::Qr(err) => err.fmt(f),
+            AppError::Task(err) => write!(f, "blocking task failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        tracing::error!("Request failed: {}", self);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "Internal server error",
+                "code": self.code()
+            })),
+        )
+            .into_response()
+    }
+}
+
+impl From<redb::Error> for AppError {
+    fn from(err: redb::Error) -> Self {
+        AppError::Storage(err)
+    }
+}
+
+impl From<redb::TransactionError> for AppError {
+    fn from(err: redb::TransactionError) -> Self {
+        AppError::Storage(err.into())
+    }
+}
+
+impl From<redb::TableError> for AppError {
+    fn from(err: redb::TableError) -> Self {
+        AppError::Storage(err.into())
+    }
+}
+
+impl From<redb::StorageError> for AppError {
+    fn from(err: redb::StorageError) -> Self {
+        AppError::Storage(err.into())
+    }
+}
+
+impl From<redb::CommitError> for AppError {
+    fn from(err: redb::CommitError) -> Self {
+        AppError::Storage(err.into())
+    }
+}
+
+impl From<DecodeError> for AppError {
+    fn from(err: DecodeError) -> Self {
+        AppError::Record(err)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Json(err)
+    }
+}
+
+impl From<QrError> for AppError {
+    fn from(err: QrError) -> Self {
+        AppError::Qr(err)
+    }
+}
+
+impl From<JoinError> for AppError {
+    fn from(err: JoinError) -> Self {
+        AppError::Task(err)
+    }
+}
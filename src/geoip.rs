@@ -0,0 +1,94 @@
+//! Country lookup for click events
+//!
+//! Resolves client IPs against a MaxMind GeoLite2/GeoIP2 Country (or City)
+//! database. The database is read once when `AppState` is built; when
+//! `GEOIP_DB` is unset or unreadable, lookups are simply skipped.
+
+use axum::http::HeaderMap;
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+
+/// Loaded MaxMind database
+pub struct GeoIp {
+    reader: Reader<Vec<u8>>,
+}
+
+impl GeoIp {
+    /// Reads the database at `path` into memory
+    pub fn open(path: &str) -> Result<Self, MaxMindDBError> {
+        Ok(GeoIp {
+            reader: Reader::open_readfile(path)?,
+        })
+    }
+
+    /// Loads the database configured in `GEOIP_DB`, logging (but tolerating) failures
+    pub fn load(path: Option<&str>) -> Option<Self> {
+        let path = path.filter(|path| !path.is_empty())?;
+
+        match GeoIp::open(path) {
+            Ok(geoip) => Some(geoip),
+            Err(err) => {
+                tracing::warn!("Failed to load GEOIP_DB {}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    /// Returns the ISO 3166-1 alpha-2 country code for `ip`, if known
+    pub fn country_code(&self, ip: IpAddr) -> Option<String> {
+        let record: geoip2::Country = self.reader.lookup(ip).ok()?;
+        record.country?.iso_code.map(String::from)
+    }
+}
+
+/// Determines the visitor's IP address
+///
+/// The first `X-Forwarded-For` entry wins, since the service normally runs
+/// behind a reverse proxy; otherwise the socket peer address is used. The
+/// header is client-controlled when no proxy strips it, so treat the result
+/// as a hint for analytics only.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|value| value.trim().parse().ok());
+
+    forwarded.or(peer.map(|peer| peer.ip()))
+}
+
+/// Determines the address a visitor is held to, e.g. for limits and caps
+///
+/// Unlike `client_ip`, `X-Forwarded-For` is only believed when the socket
+/// peer is one of `trusted_proxies`. The entries are then read from the
+/// right, skipping further trusted proxies, so the first address no proxy of
+/// ours vouched for wins and anything the client prepended is ignored.
+pub fn trusted_client_ip(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    trusted_proxies: &HashSet<IpAddr>,
+) -> Option<IpAddr> {
+    let peer = peer?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let entries: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    for entry in entries.iter().rev() {
+        match entry.parse::<IpAddr>() {
+            Ok(address) if trusted_proxies.contains(&address) => continue,
+            Ok(address) => return Some(address),
+            // Garbage cannot have come from a proxy of ours
+            Err(_) => break,
+        }
+    }
+
+    Some(peer)
+}
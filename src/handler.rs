@@ -5,29 +5,210 @@
 //! - Redirecting short URLs to their original destinations
 //! - Listing URLs with pagination and filtering
 //! - Deleting URLs with ownership verification
+//! - Renaming slugs while keeping the old one as an alias
+//! - Resolving full short URLs back to their records
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
+    body::Body,
+    extract::{ConnectInfo, Extension, OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    response::{Html, IntoResponse, Redirect},
     Json,
 };
-use chrono::Utc;
-use rand::{distr::Alphanumeric, Rng};
-use redb::{ReadableDatabase, ReadableTable};
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use futures_util::stream::{self, StreamExt};
+use rand::seq::IndexedRandom;
+use redb::{ReadTransaction, ReadableTable, ReadableTableMetadata, WriteTransaction};
 use serde_json::{self, json};
+use percent_encoding::percent_decode_str;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::net::SocketAddr;
+use std::ops::Bound;
+use tokio::sync::{oneshot, OwnedSemaphorePermit};
+use url::Url;
 
-use crate::model::{CreateRequest, CreateResponse, ListParams, UrlRecord};
+#[cfg(feature = "analytics")]
+use crate::analytics::{record_click, rename_link_stats};
+use crate::config::{Config, MAX_TAGS};
+use crate::counters;
+use crate::creation_stats::record_creation;
+use crate::dedup::{find_existing, index_destination, replace_destination};
+use crate::error::AppError;
+use crate::geoip::{client_ip, trusted_client_ip};
+use crate::history;
+use crate::middleware::{ApiKey, AuthMode, AuthSubject, RequestId};
+use crate::model::{BatchCreateRequest, BatchItem, BulkCreateRequest, BundleRequest, ClickEvent, CreateRequest, CreateResponse, ExportParams, LeaderboardParams, ListFormat, ListParams, ListSort, OldestParams, QrBundleParams, QrParams, QrSvgParams, RotateParams, RedirectParams, UrlRecord};
+use crate::pages::{link_unavailable, redirect_countdown};
+use crate::preferences::{self, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+use crate::preview_token::{self, TokenCheck};
+use crate::password::{hash_password, verify_password};
+use crate::record_format::{self, RecordFormat};
+use crate::qr::{
+    parse_hex_color, render_png, render_svg, render_zip_bundle, DEFAULT_MODULE_SIZE, DEFAULT_SVG_COLOR, MAX_MODULE_SIZE,
+    MIN_MODULE_SIZE,
+};
+use crate::redirect_log::{self, RedirectLogEvent};
+use crate::reservations;
+use crate::slug;
+use crate::slug_generator::SlugContext;
+use crate::tags::normalize_tags;
+use crate::visitors::{self, Admission};
+use crate::webhook::{is_valid_webhook, send_click_webhook};
 use crate::{
-    database::{AppState, TABLE_REF_INDEX, TABLE_URLS},
-    model::DeleteParams,
+    database::{created_index_key, load_record, with_write_retry, AppState, TABLE_ALIASES, TABLE_CREATED_INDEX, TABLE_LINK_PASSWORDS, TABLE_REF_INDEX, TABLE_URLS},
+    model::{ByDateParams, DeleteParams, DestinationsParams, KillRequest, MergeRequest, PreviewUrlParams, RenameRequest,
+        ResolveParams,
+        StatsParams, TagsParams, TimeFormat, TimeFormatParams, ToggleParams, ValidateSlugParams},
 };
 
+/// Most entries accepted by one batch create request (further capped by `MAX_BATCH_SIZE`)
+pub const BATCH_MAX_ITEMS: usize = 500;
+
+/// Index entries read per read transaction by `export_csv`
+pub const EXPORT_BATCH_SIZE: usize = 500;
+
+/// Links moved per write transaction by `rotate_all`
+pub const ROTATE_BATCH_SIZE: usize = 100;
+
+/// Longest creation `source` accepted, in characters
+pub const MAX_SOURCE_LENGTH: usize = 64;
+
+/// Most members one bundle may hold
+pub const MAX_BUNDLE_MEMBERS: usize = 50;
+
+/// Header naming the client that creates a link, used when the body has no `source`
+pub const CLIENT_SOURCE_HEADER: HeaderName = HeaderName::from_static("x-client-source");
+
+/// Reasons a single link cannot be created
+#[derive(Debug)]
+enum CreateError {
+    /// The custom id breaks a slug rule (see `slug::custom`)
+    InvalidCustomId(slug::SlugError),
+
+    /// `url` is empty once whitespace and control characters are removed
+    EmptyUrl,
+
+    /// `url` is not an absolute http(s) URL (e.g. a bare word or `javascript:`)
+    InvalidUrl,
+
+    /// `click_webhook` is not an absolute http(s) URL
+    InvalidWebhook,
+
+    /// `expires_in_seconds` is too far in the future to represent, `expires_at`
+    /// cannot be parsed, or both were given
+    InvalidExpiry,
+
+    /// The id is already used by a link or a renamed link's alias
+    Taken,
+
+    /// More than `MAX_TAGS` tags were given
+    TooManyTags,
+
+    /// `source` is longer than `MAX_SOURCE_LENGTH` or contains control characters
+    InvalidSource,
+
+    /// A bundle has fewer than 2 or more than `MAX_BUNDLE_MEMBERS` members,
+    /// or a member is not an absolute http(s) URL
+    InvalidBundle,
+
+    /// Every generated slug tried was already taken
+    SlugExhausted,
+
+    /// The database kept failing, even after retries (the cause is logged)
+    Storage,
+}
+
+/// Rejects a batch of `len` entries over `MAX_BATCH_SIZE` or the endpoint's own `limit`
+/// 
+/// Returns the `400 batch_too_large` response shared by every batch
+/// endpoint, whose `max` is the cap in effect, or `None` if the batch fits.
+pub fn batch_too_large(config: &Config, len: usize, limit: Option<usize>) -> Option<axum::response::Response> {
+    let max = limit.map_or(config.max_batch_size, |limit| limit.min(config.max_batch_size));
+    if len <= max {
+        return None;
+    }
+    
+    Some(
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("Too many entries; the maximum per request is {}", max),
+                "code": "batch_too_large",
+                "max": max
+            })),
+        )
+            .into_response(),
+    )
+}
+
+impl CreateError {
+    /// HTTP status reported for this error
+    fn status(&self) -> StatusCode {
+        match self {
+            CreateError::Taken => StatusCode::CONFLICT,
+            CreateError::Storage | CreateError::SlugExhausted => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// JSON error body, in the shape every endpoint uses
+    fn body(&self) -> serde_json::Value {
+        match self {
+            CreateError::InvalidCustomId(err) => json!({
+                "error": err.to_string(),
+                "code": "invalid_custom_id"
+            }),
+            CreateError::EmptyUrl => json!({
+                "error": "url must not be empty",
+                "code": "empty_url"
+            }),
+            CreateError::InvalidUrl => json!({
+                "error": "url must be an absolute http(s) URL",
+                "code": "invalid_url"
+            }),
+            CreateError::InvalidWebhook => json!({
+                "error": "click_webhook must be an absolute http(s) URL",
+                "code": "invalid_click_webhook"
+            }),
+            CreateError::InvalidExpiry => json!({
+                "error": "Expiry must be a valid RFC3339 timestamp or duration (e.g. 24h, 7d), given once",
+                "code": "invalid_expiry"
+            }),
+            CreateError::TooManyTags => json!({
+                "error": format!("A link may carry at most {} tags", MAX_TAGS),
+                "code": "too_many_tags"
+            }),
+            CreateError::InvalidSource => json!({
+                "error": format!("source must be at most {} printable characters", MAX_SOURCE_LENGTH),
+                "code": "invalid_source"
+            }),
+            CreateError::InvalidBundle => json!({
+                "error": format!("A bundle needs 2 to {} absolute http(s) member URLs", MAX_BUNDLE_MEMBERS),
+                "code": "invalid_bundle"
+            }),
+            CreateError::Taken => json!({
+                "error": "Custom ID already taken. Please choose another.",
+                "code": "id_taken"
+            }),
+            CreateError::SlugExhausted => json!({
+                "error": "Could not find a free slug; the generated-slug space may be nearly full (see /api/admin/capacity)",
+                "code": "slug_generation_failed"
+            }),
+            CreateError::Storage => json!({
+                "error": "The link could not be stored. Please try again.",
+                "code": "storage_error"
+            }),
+        }
+    }
+}
+
 /// Creates a new short URL
 /// 
 /// This handler:
 /// 1. Accepts a long URL and optional custom ID
-/// 2. Generates a random 6-character ID if no custom ID is provided
+/// 2. Generates a random 6-character ID if no custom ID is provided, wrapped
+///    in `SLUG_PREFIX`/`SLUG_SUFFIX` when configured
 /// 3. Checks if the ID is already taken
 /// 4. Stores the URL record in both the main table and ref_id index
 /// 5. Returns the created short URL details
@@ -38,14 +219,40 @@ use crate::{
 /// {
 ///   "url": "https://example.com/very/long/url",
 ///   "ref_id": "user_123",
-///   "custom_id": "my-link"  // Optional
+///   "custom_id": "my-link",  // Optional
+///   "password": "s3cret",    // Optional
+///   "redirect_delay_seconds": 5,  // Optional
+///   "click_webhook": "https://hooks.example.com/clicks",  // Optional
+///   "tags": ["spring", "email"],  // Optional
+///   "expires_in_seconds": 86400,  // Optional
+///   "expires_at": "7d",  // Optional - RFC3339 or a duration; instead of expires_in_seconds
+///   "initial_clicks": 100,  // Optional - starting click count for migrated links
+///   "dedup": true,  // Optional - reuse an existing link to the same url
+///   "permanent": true,  // Optional - 301 instead of 307 (see `redirect_url`)
+///   "allowed_referrers": ["example.com"],  // Optional - hosts allowed to link here
+///   "require_referrer": false,  // Optional - also refuse visits without a Referer
+///   "source": "cli",  // Optional - creating client, else the X-Client-Source header
+///   "max_unique_visitors": 3  // Optional - distinct visitors (by IP) allowed
 /// }
 /// ```
 /// 
 /// # Response
 /// 
-/// - **201 Created** - URL successfully created
+/// - **201 Created** - URL successfully created (`200 OK` with
+///   `CREATE_SUCCESS_STATUS=200`; the body is the same)
+/// - **200 OK** - `dedup` was set and an existing link to `url` was returned
+///   (see the `dedup` module for where it looks)
+/// - **400 Bad Request** - `url` is blank or not an absolute http(s) URL, custom ID is
+///   blank, longer than 64 characters, uses characters outside `[A-Za-z0-9_-]`,
+///   is reserved or uses the slug prefix/suffix, `click_webhook` is not an absolute http(s) URL, the expiry is out of
+///   range, unparseable or given twice, more than `MAX_TAGS` tags were given (`too_many_tags`),
+///   or `source` is too long (`invalid_source`)
 /// - **409 Conflict** - Custom ID already exists
+/// - **500 Internal Server Error** - Storage kept failing after `WRITE_RETRIES` retries,
+///   or every generated slug tried was taken (`slug_generation_failed`)
+/// 
+/// With `?time_format=epoch_ms`, `created_at`/`expires_at` are integer
+/// milliseconds instead of RFC3339 strings.
 /// 
 /// # Database Operations
 /// 
@@ -54,85 +261,629 @@ use crate::{
 /// 2. `TABLE_REF_INDEX` - Secondary index for querying by ref_id
 pub async fn create_short_url(
     State(state): State<AppState>,
-    Json(payload): Json<CreateRequest>,
+    subject: Option<Extension<AuthSubject>>,
+    headers: HeaderMap,
+    Query(format): Query<TimeFormatParams>,
+    Json(mut payload): Json<CreateRequest>,
+) -> impl IntoResponse {
+    // A JWT subject, when present, is the owner regardless of the body
+    payload.ref_id = effective_ref_id(subject, payload.ref_id);
+    payload.source = payload.source.or_else(|| header_string(&headers, CLIENT_SOURCE_HEADER));
+    
+    let render = |response: CreateResponse| {
+        let mut body = json!(response);
+        format.time_format.apply(&mut body);
+        Json(body)
+    };
+    
+    match store_link(&state, payload).await {
+        Ok(Stored::Existing(response)) => (StatusCode::OK, render(response)).into_response(),
+        Ok(Stored::Created(response)) => {
+            let status = StatusCode::from_u16(state.config.create_success_status).unwrap_or(StatusCode::CREATED);
+            (status, render(response)).into_response()
+        }
+        Err(err) => (err.status(), Json(err.body())).into_response(),
+    }
+}
+
+/// Creates many links at once, applying shared defaults to each
+/// 
+/// Every entry of `urls` is either a bare URL string or a full create
+/// payload; fields set on an entry override the matching `defaults`. Each
+/// link is created independently, so one failure does not prevent the rest.
+/// 
+/// # Request Body
+/// 
+/// ```json
+/// {
+///   "defaults": { "ref_id": "campaign_7", "tags": ["spring"], "expires_in_seconds": 604800 },
+///   "urls": [
+///     "https://example.com/a",
+///     { "url": "https://example.com/b", "custom_id": "spring-b", "tags": ["vip"] }
+///   ]
+/// }
+/// ```
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"created": [...], "failed": [{"index", "url", "error", "code"}]}`,
+///   where `created` holds the same objects single creation returns
+/// - **400 Bad Request** - More than `BATCH_MAX_ITEMS` or `MAX_BATCH_SIZE` entries
+/// 
+/// In JWT mode every link is owned by the token's subject.
+pub async fn batch_create_with_defaults(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<BatchCreateRequest>,
+) -> impl IntoResponse {
+    if let Some(response) = batch_too_large(&state.config, payload.urls.len(), Some(BATCH_MAX_ITEMS)) {
+        return response;
+    }
+    
+    let defaults = payload.defaults;
+    let mut created = Vec::new();
+    let mut failed = Vec::new();
+    
+    for (index, item) in payload.urls.into_iter().enumerate() {
+        let mut request = match item {
+            BatchItem::Url(url) => CreateRequest {
+                url,
+                ..CreateRequest::default()
+            },
+            BatchItem::Link(request) => *request,
+        };
+        request.ref_id = effective_ref_id(
+            subject.clone(),
+            request.ref_id.or_else(|| defaults.ref_id.clone()),
+        );
+        request.tags = request.tags.or_else(|| defaults.tags.clone());
+        // An entry's own expires_at replaces the default lifetime rather than conflicting with it
+        if request.expires_at.is_none() {
+            request.expires_in_seconds = request.expires_in_seconds.or(defaults.expires_in_seconds);
+        }
+        
+        let url = request.url.clone();
+        match store_link(&state, request).await {
+            Ok(Stored::Created(response) | Stored::Existing(response)) => created.push(json!(response)),
+            Err(err) => {
+                let mut failure = err.body();
+                failure["index"] = json!(index);
+                failure["url"] = json!(url);
+                failed.push(failure);
+            }
+        }
+    }
+    
+    Json(json!({
+        "created": created,
+        "failed": failed,
+    }))
+    .into_response()
+}
+
+/// Imports many links in a single write transaction
+/// 
+/// Meant for migrations from another shortener: every item is validated,
+/// then all valid ones are written and committed together. An item that is
+/// invalid or whose `custom_id` is taken (including by an earlier item) is
+/// reported and skipped without affecting the others. Items are created
+/// one microsecond apart, in order, so they list in the order given.
+/// 
+/// # Request Body
+/// 
+/// ```json
+/// {
+///   "items": [
+///     { "url": "https://example.com/a", "ref_id": "user_123", "custom_id": "promo-a" },
+///     { "url": "https://example.com/b" }
+///   ]
+/// }
+/// ```
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"created": 1, "failed": 1, "results": [...]}` with one
+///   result per item, in order: `{"index", "status": "created", "id", "short_url"}`
+///   or `{"index", "status": "failed", "url", "error", "code"}` (`code` as for
+///   single creation, e.g. `id_taken` or `invalid_url`)
+/// - **400 Bad Request** - More than `MAX_BATCH_SIZE` items
+/// - **500 Internal Server Error** - Storage kept failing after `WRITE_RETRIES`
+///   retries; nothing was created
+/// 
+/// In JWT mode every link is owned by the token's subject.
+pub async fn bulk_create(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<BulkCreateRequest>,
 ) -> impl IntoResponse {
-    // Filter out empty custom IDs and treat them as None
-    let effective_custom_id = payload.custom_id.filter(|id| !id.is_empty());
+    if let Some(response) = batch_too_large(&state.config, payload.items.len(), None) {
+        return response;
+    }
+    
+    // Distinct timestamps keep the ref_id index keys of one owner's items apart
+    let now = Utc::now();
+    let urls: Vec<String> = payload.items.iter().map(|item| item.url.clone()).collect();
+    let mut prepared: Vec<Result<PreparedLink, CreateError>> = payload
+        .items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let request = CreateRequest {
+                url: item.url,
+                ref_id: effective_ref_id(subject.clone(), item.ref_id),
+                custom_id: item.custom_id,
+                ..CreateRequest::default()
+            };
+            prepare_link(&state, request, now + Duration::microseconds(index as i64))
+        })
+        .collect();
+    
+    // One outcome per prepared link, in order
+    let inserted = with_write_retry(state.config.write_retries, || {
+        let write_txn = state.begin_write()?;
+        let mut inserted = Vec::new();
+        for link in prepared.iter_mut().flatten() {
+            inserted.push(insert_link(&write_txn, &state, link)?.map(|_| ()));
+        }
+        write_txn.commit()?;
+        Ok(inserted)
+    })
+    .await;
+    let inserted = match inserted {
+        Ok(inserted) => inserted,
+        Err(err) => {
+            tracing::error!("Failed to store bulk import of {} items: {}", prepared.len(), err);
+            let err = CreateError::Storage;
+            return (err.status(), Json(err.body())).into_response();
+        }
+    };
+    
+    let mut inserted = inserted.into_iter();
+    let mut created = 0;
+    let results: Vec<serde_json::Value> = prepared
+        .into_iter()
+        .zip(urls)
+        .enumerate()
+        .map(|(index, (link, url))| {
+            match link.and_then(|link| inserted.next().unwrap().map(|()| link.record)) {
+                Ok(record) => {
+                    created += 1;
+                    json!({
+                        "index": index,
+                        "status": "created",
+                        "id": record.id,
+                        "short_url": record.short_url,
+                    })
+                }
+                Err(err) => {
+                    let mut failure = err.body();
+                    failure["index"] = json!(index);
+                    failure["status"] = json!("failed");
+                    failure["url"] = json!(url);
+                    failure
+                }
+            }
+        })
+        .collect();
+    
+    Json(json!({
+        "created": created,
+        "failed": results.len() - created,
+        "results": results,
+    }))
+    .into_response()
+}
+
+/// Returns whether `url` is an absolute http(s) URL with a host
+/// 
+/// Anything else (`javascript:`, `file:`, `ftp:`, bare words) could not be
+/// followed safely by a browser, so it is never stored as a destination.
+pub(crate) fn is_http_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+}
+
+/// Parses an `expires_at` value: an RFC3339 timestamp, or a duration from `now`
+/// 
+/// Durations are a positive whole number followed by `s`, `m`, `h`, `d` or
+/// `w` (e.g. `90m`, `24h`, `7d`). Timestamps in the past are accepted; the
+/// link is simply expired from the start.
+fn parse_expiry(value: &str, now: chrono::DateTime<Utc>) -> Option<chrono::DateTime<Utc>> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    
+    let unit_at = value.len().checked_sub(1)?;
+    let (amount, unit) = value.split_at_checked(unit_at)?;
+    let amount: i64 = amount.parse().ok().filter(|amount| *amount > 0)?;
+    let lifetime = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    }?;
+    now.checked_add_signed(lifetime)
+}
+
+/// Trims surrounding whitespace and removes control characters (e.g. embedded newlines)
+fn clean_url(url: &str) -> String {
+    url.trim().chars().filter(|ch| !ch.is_control()).collect()
+}
+
+/// Outcome of a successful `store_link`
+enum Stored {
+    /// A new link was written
+    Created(CreateResponse),
+
+    /// A `dedup` request matched an existing link, which is returned instead
+    Existing(CreateResponse),
+}
+
+/// Generates a slug for a link without a custom id (see `SLUG_STRATEGY`)
+fn generate_slug(
+    state: &AppState,
+    url: &str,
+    ref_id: Option<&str>,
+    created_at: chrono::DateTime<Utc>,
+    attempt: u32,
+) -> String {
+    slug::generate(
+        state.slug_generator.as_ref(),
+        &SlugContext {
+            config: &state.config,
+            url,
+            ref_id,
+            created_at,
+            attempt,
+        },
+    )
+}
+
+/// A validated link, ready to be written by `insert_link`
+struct PreparedLink {
+    /// The record to store
+    record: UrlRecord,
+
+    /// Password protecting the link, hashed when stored
+    password: Option<String>,
+
+    /// Whether an existing link to the same destination may be returned instead
+    dedup: bool,
+
+    /// Whether the slug was generated, and may be replaced if it is taken
+    generated: bool,
+}
+
+/// Validates `payload` and builds the link it describes, created at `created_at`
+/// 
+/// `payload.ref_id` must already be the effective owner.
+fn prepare_link(
+    state: &AppState,
+    payload: CreateRequest,
+    created_at: chrono::DateTime<Utc>,
+) -> Result<PreparedLink, CreateError> {
+    let ref_id = payload.ref_id;
+    
+    // Copy-pasted URLs often carry surrounding whitespace or stray newlines
+    let original_url = if state.config.trim_urls {
+        let cleaned = clean_url(&payload.url);
+        if cleaned.is_empty() {
+            return Err(CreateError::EmptyUrl);
+        }
+        cleaned
+    } else {
+        payload.url
+    };
+    
+    // Checked before any transaction so garbage never reaches the database
+    if !is_http_url(&original_url) {
+        return Err(CreateError::InvalidUrl);
+    }
+    
+    // A custom id that is given must be valid; blank ones are rejected below
+    let effective_custom_id = payload.custom_id;
+    
+    // Empty passwords do not protect anything, so treat them as None as well
+    let password = payload.password.filter(|password| !password.is_empty());
+    
+    // A webhook must be a reachable-looking absolute URL; empty means none
+    let click_webhook = payload.click_webhook.filter(|webhook| !webhook.is_empty());
+    if click_webhook.as_deref().is_some_and(|webhook| !is_valid_webhook(webhook)) {
+        return Err(CreateError::InvalidWebhook);
+    }
+    
+    // A lifetime of 0 means the link never expires
+    let expires_in_seconds = payload.expires_in_seconds.filter(|seconds| *seconds > 0);
+    let expires_at = match (expires_in_seconds, payload.expires_at.as_deref().map(str::trim)) {
+        (Some(_), Some(_)) => return Err(CreateError::InvalidExpiry),
+        (Some(seconds), None) => Some(
+            i64::try_from(seconds)
+                .ok()
+                .and_then(Duration::try_seconds)
+                .and_then(|lifetime| created_at.checked_add_signed(lifetime))
+                .ok_or(CreateError::InvalidExpiry)?,
+        ),
+        (None, Some(value)) => Some(parse_expiry(value, created_at).ok_or(CreateError::InvalidExpiry)?),
+        (None, None) => None,
+    };
+    
+    // Hosts compare case-insensitively; blank entries would match nothing
+    let allowed_referrers = payload
+        .allowed_referrers
+        .unwrap_or_default()
+        .into_iter()
+        .map(|host| host.trim().trim_end_matches('.').to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect();
+    
+    // A request without tags gets DEFAULT_TAGS, while any explicit list (even
+    // an empty one) replaces them; blanks and duplicates are dropped either way
+    let tags = normalize_tags(
+        payload.tags.unwrap_or_else(|| state.config.default_tags.clone()),
+        state.config.tag_case,
+    );
+    if tags.len() > MAX_TAGS {
+        return Err(CreateError::TooManyTags);
+    }
+    
+    // A blank source says nothing about the client
+    let source = payload.source.map(|source| source.trim().to_string()).filter(|source| !source.is_empty());
+    if source
+        .as_deref()
+        .is_some_and(|source| source.chars().count() > MAX_SOURCE_LENGTH || source.chars().any(char::is_control))
+    {
+        return Err(CreateError::InvalidSource);
+    }
+    
+    // Only plain requests are deduplicated: a custom id or password asks for a specific link
+    let generated = effective_custom_id.is_none();
+    let dedup = payload.dedup && generated && password.is_none();
     
-    // Use custom ID if provided, otherwise generate a random 6-character ID
+    // Use custom ID if provided, otherwise ask the SLUG_STRATEGY generator
+    // (both subject to the SLUG_PREFIX / SLUG_SUFFIX rules)
     let id_to_use = match effective_custom_id {
-        Some(custom_id) => custom_id,
-        None => rand::rng()
-            .sample_iter(&Alphanumeric)
-            .take(6)
-            .map(char::from)
-            .collect(),
+        Some(custom_id) => slug::custom(&state.config, &custom_id).map_err(CreateError::InvalidCustomId)?,
+        None => generate_slug(state, &original_url, ref_id.as_deref(), created_at, 0),
     };
 
-    let base_url = std::env::var("URL").unwrap_or_else(|_| "http://localhost".to_string());
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let domain = format!("{}:{}", base_url, port);
+    let domain = short_url_domain();
 
     // Create the URL record with all metadata
     let record = UrlRecord {
         id: id_to_use.clone(),
-        original_url: payload.url,
+        original_url,
         short_url: format!("{}/{}", domain, id_to_use.clone()),
-        ref_id: payload.ref_id.clone(),
-        created_at: Utc::now(),
-        clicks: 0,
+        ref_id,
+        created_at,
+        clicks: payload.initial_clicks.unwrap_or(0),
+        password_protected: password.is_some(),
+        redirect_delay_seconds: payload.redirect_delay_seconds.filter(|delay| *delay > 0),
+        is_active: true,
+        killed: false,
+        disabled_at: None,
+        disabled_message: payload.disabled_message.filter(|message| !message.is_empty()),
+        click_webhook,
+        tags,
+        expires_at,
+        last_accessed_at: None,
+        permanent: payload.permanent.unwrap_or(false),
+        allowed_referrers,
+        require_referrer: payload.require_referrer.unwrap_or(false),
+        source,
+        max_unique_visitors: payload.max_unique_visitors.filter(|max| *max > 0),
+        bundle_members: Vec::new(),
     };
     
-    // Serialize the record to JSON for storage
-    let record_json = serde_json::to_string(&record).unwrap();
+    Ok(PreparedLink {
+        record,
+        password,
+        dedup,
+        generated,
+    })
+}
+
+/// Writes a prepared link inside `write_txn`
+/// 
+/// Returns `Ok(Some(existing))` on a dedup hit, in which case nothing is
+/// written. Business failures are checked before anything is written, so a
+/// rejected link leaves the transaction untouched and it can still be used
+/// for other links.
+///
+/// A generated slug that is already taken is replaced (up to
+/// `SLUG_MAX_RETRIES` more slugs), updating `link.record`; a
+/// taken custom id is rejected straight away.
+fn insert_link(
+    write_txn: &WriteTransaction,
+    state: &AppState,
+    link: &mut PreparedLink,
+) -> Result<Result<Option<UrlRecord>, CreateError>, redb::Error> {
 
-    // Begin a write transaction
-    let write_txn = state.db.begin_write().unwrap();
+    // Looked up inside the write transaction so concurrent creates cannot both miss
+    if link.dedup {
+        let record = &link.record;
+        let existing = find_existing(write_txn, state.config.dedup_scope, record.ref_id.as_deref(), &record.original_url)?;
+        if existing.is_some() {
+            return Ok(Ok(existing));
+        }
+    }
+    
     {
         // Open the main URLs table
-        let mut table_main = write_txn.open_table(TABLE_URLS).unwrap();
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+        let table_aliases = write_txn.open_table(TABLE_ALIASES)?;
         
-        // Check if the ID is already taken
-        if table_main.get(id_to_use.as_str()).unwrap().is_some() {
-            return (
-                StatusCode::CONFLICT,
-                Json(serde_json::json!({
-                    "error": "Custom ID already taken. Please choose another."
-                })),
-            )
-                .into_response();
+        let mut attempt = 0;
+        loop {
+            let record = &link.record;
+            let id = record.id.as_str();
+            
+            // The ID is taken by a link or a renamed link's alias, or someone
+            // else's reservation blocks it; the owner's own is consumed
+            let taken = table_main.get(id)?.is_some()
+                || table_aliases.get(id)?.is_some()
+                || !reservations::claim(write_txn, id, record.ref_id.as_deref())?;
+            if !taken {
+                break;
+            }
+            if !link.generated {
+                return Ok(Err(CreateError::Taken));
+            }
+            
+            state.metrics.record_slug_collision();
+            attempt += 1;
+            if attempt > state.config.slug_max_retries {
+                tracing::error!("No free slug after {} attempts", attempt);
+                return Ok(Err(CreateError::SlugExhausted));
+            }
+            tracing::warn!("Generated slug {} is taken, trying another", id);
+            let new_id = generate_slug(state, &record.original_url, record.ref_id.as_deref(), record.created_at, attempt);
+            link.record.short_url = format!("{}/{}", short_url_domain(), new_id);
+            link.record.id = new_id;
         }
-
+        
+        let record = &link.record;
+        let id = record.id.as_str();
+        
         // Insert the record into the main table
-        table_main
-            .insert(id_to_use.as_str(), record_json.as_str())
-            .unwrap();
-
+        table_main.insert(id, state.config.record_format.encode(record).as_str())?;
+        
         // Only insert into ref_id index if ref_id is provided
-        if let Some(ref_id_value) = &payload.ref_id {
+        if let Some(ref_id_value) = &record.ref_id {
             // Create composite key for ref_id index: "ref_id:timestamp_micros"
             // This enables efficient range queries and maintains chronological order
             let index_key = format!("{}:{}", ref_id_value, record.created_at.timestamp_micros());
             
-            let mut table_index = write_txn.open_table(TABLE_REF_INDEX).unwrap();
-            table_index
-                .insert(index_key.as_str(), record_json.as_str())
-                .unwrap();
+            let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+            table_index.insert(index_key.as_str(), id)?;
+        }
+        
+        // Store the password hash separately so it never appears in API responses
+        if let Some(password) = &link.password {
+            let password_hash = hash_password(id, password);
+            let mut table_passwords = write_txn.open_table(TABLE_LINK_PASSWORDS)?;
+            table_passwords.insert(id, password_hash.as_str())?;
         }
     }
     
-    // Commit the transaction to persist the data
-    write_txn.commit().unwrap();
+    // Keep the destination reverse index current for global dedup
+    index_destination(write_txn, &link.record)?;
+    record_creation(write_txn, link.record.created_at)?;
+    
+    let mut table_created = write_txn.open_table(TABLE_CREATED_INDEX)?;
+    table_created.insert(created_index_key(link.record.created_at, &link.record.id).as_str(), link.record.id.as_str())?;
+    Ok(Ok(None))
+}
 
-    // Prepare the response with the created URL details
-    let response = CreateResponse {
-        id: id_to_use.clone(),
-        original_url: record.original_url,
-        short_url: format!("{}/{}", domain, id_to_use),
-        created_at: record.created_at,
+/// Validates `payload` and stores it as a new link
+/// 
+/// `payload.ref_id` must already be the effective owner.
+async fn store_link(state: &AppState, payload: CreateRequest) -> Result<Stored, CreateError> {
+    let link = prepare_link(state, payload, Utc::now())?;
+    store_prepared(state, link).await
+}
+
+/// Stores a prepared link in its own write transaction
+async fn store_prepared(state: &AppState, mut link: PreparedLink) -> Result<Stored, CreateError> {
+    // Transient storage errors retry the whole transaction (see `with_write_retry`);
+    // `Some(existing)` is a dedup hit
+    let existing = with_write_retry(state.config.write_retries, || {
+        let write_txn = state.begin_write()?;
+        let existing = match insert_link(&write_txn, state, &mut link)? {
+            Ok(existing) => existing,
+            Err(err) => return Ok(Err(err)),
+        };
+        
+        // Commit the transaction to persist the data
+        write_txn.commit()?;
+        Ok(Ok(existing))
+    })
+    .await
+    .map_err(|err| {
+        tracing::error!("Failed to store link {}: {}", link.record.id, err);
+        CreateError::Storage
+    })??;
+    
+    if let Some(existing) = existing {
+        return Ok(Stored::Existing(CreateResponse::from(existing)));
+    }
+    
+    Ok(Stored::Created(CreateResponse::from(link.record)))
+}
+
+/// Creates a bundle: one slug that redirects each visit to a random member
+/// 
+/// Useful for link roulette or spreading load across mirrors. The bundle is
+/// stored as a regular link whose `original_url` is the first member, with
+/// all members in `bundle_members`; `redirect_url` picks among them.
+/// 
+/// # Request Body
+/// 
+/// ```json
+/// {
+///   "members": ["https://mirror-a.example.com", "https://mirror-b.example.com"],
+///   "ref_id": "user_123",  // Optional
+///   "custom_id": "mirrors",  // Optional
+///   "tags": ["downloads"]  // Optional
+/// }
+/// ```
+/// 
+/// # Response
+/// 
+/// - **201 Created** - The same body as link creation, plus `members`
+/// - **400 Bad Request** - Fewer than 2 or more than `MAX_BUNDLE_MEMBERS` members,
+///   a member that is not an absolute http(s) URL (`invalid_bundle`), or an
+///   invalid custom ID or tags as for links
+/// - **409 Conflict** - Custom ID already exists
+/// 
+/// In JWT mode the bundle is owned by the token's subject.
+pub async fn create_bundle(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<BundleRequest>,
+) -> impl IntoResponse {
+    let members: Vec<String> = payload
+        .members
+        .into_iter()
+        .map(|member| if state.config.trim_urls { clean_url(&member) } else { member })
+        .collect();
+    if !(2..=MAX_BUNDLE_MEMBERS).contains(&members.len()) || !members.iter().all(|member| is_http_url(member)) {
+        let err = CreateError::InvalidBundle;
+        return (err.status(), Json(err.body())).into_response();
+    }
+    
+    let request = CreateRequest {
+        url: members[0].clone(),
+        ref_id: effective_ref_id(subject, payload.ref_id),
+        custom_id: payload.custom_id,
+        tags: payload.tags,
+        ..Default::default()
+    };
+    let stored = match prepare_link(&state, request, Utc::now()) {
+        Ok(mut link) => {
+            link.record.bundle_members = members.clone();
+            store_prepared(&state, link).await
+        }
+        Err(err) => Err(err),
     };
+    
+    match stored {
+        Ok(Stored::Created(response) | Stored::Existing(response)) => {
+            let mut body = json!(response);
+            body["members"] = json!(members);
+            let status = StatusCode::from_u16(state.config.create_success_status).unwrap_or(StatusCode::CREATED);
+            (status, Json(body)).into_response()
+        }
+        Err(err) => (err.status(), Json(err.body())).into_response(),
+    }
+}
 
-    (StatusCode::CREATED, Json(response)).into_response()
+/// Destination of one visit: a random member for bundles, `original_url` otherwise
+fn pick_destination(record: &UrlRecord) -> &str {
+    record
+        .bundle_members
+        .choose(&mut rand::rng())
+        .map_or(record.original_url.as_str(), String::as_str)
 }
 
 /// Redirects a short URL to its original destination
@@ -147,10 +898,44 @@ pub async fn create_short_url(
 /// 
 /// - `id` - The short URL identifier/slug
 /// 
+/// # Query Parameters
+/// 
+/// - `password` (optional) - Required for password-protected links
+/// - `no_count` (optional) - `true` skips click recording; an `X-No-Count: 1`
+///   header does the same. Intended for monitoring and link checkers. It is
+///   advisory: anyone can send it, so treat counts as a lower bound, or strip
+///   the parameter/header at a proxy for untrusted traffic
+/// 
 /// # Response
 /// 
 /// - **307 Temporary Redirect** - Redirects to the original URL
-/// - **404 Not Found** - Short URL does not exist
+/// - **301 Moved Permanently** - Same, for links created with `permanent: true`
+/// - **200 OK** - Countdown page for links with `redirect_delay_seconds` set
+/// - **401 Unauthorized** - Password missing or wrong for a protected link
+/// - **403 Forbidden** - The link has `allowed_referrers` and the `Referer`
+///   host is not among them (or is missing, with `require_referrer`)
+/// - **410 Gone** - The link was disabled with the admin kill switch; shows its
+///   `disabled_message` as HTML to browsers, JSON otherwise. Also returned
+///   (with `code: link_expired`) once a link's `expires_at` has passed, and
+///   (with `code: visitor_limit_reached`) to a new visitor once a link's
+///   `max_unique_visitors` distinct visitors have been seen
+/// - **429 Too Many Requests** - Too many wrong passwords; the link is locked
+///   for a cooldown period regardless of the password supplied
+/// - **404 Not Found** - Short URL does not exist. Browsers (`Accept: text/html`)
+///   receive the custom page from `NOT_FOUND_TEMPLATE_PATH` when configured
+/// 
+/// Former slugs of renamed links resolve through `TABLE_ALIASES`. Bundles
+/// (see `create_bundle`) redirect each visit to one of their members at random.
+/// 
+/// Every successful visit not marked `no_count` bumps the link's `clicks` and,
+/// with the `analytics` feature, is recorded in `TABLE_CLICK_EVENTS` and the
+/// daily stats table (see the `analytics` module). With `GEOIP_DB` configured the
+/// event carries the visitor's country, resolved from `X-Forwarded-For` or
+/// the peer address. Links with a `click_webhook` also have the visit POSTed
+/// there in the background.
+/// 
+/// Both the redirect and the countdown page carry a
+/// `Link: <short_url>; rel="canonical"` header built from the stored record.
 /// 
 /// # Note
 /// 
@@ -158,39 +943,477 @@ pub async fn create_short_url(
 /// - Allow URL statistics tracking
 /// - Enable URL updates or deletion
 /// - Prevent browser caching
+/// 
+/// Links marked `permanent` trade all three for SEO: browsers cache the 301
+/// and go straight to the destination on later visits, so their clicks are
+/// undercounted and changing their destination may not reach past visitors.
 pub async fn redirect_url(
     Path(id): Path<String>,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    // Begin a read-only transaction
-    let read_txn = state.db.begin_read().unwrap();
-    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    Query(params): Query<RedirectParams>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    // Reserved names belong to the application, never to a link
+    if slug::is_reserved_path(&id) {
+        return Ok(not_found_response(&state, &headers));
+    }
+    
+    // Look up the record (and its password hash when protected) in a read-only transaction
+    let (record, stored_hash) = {
+        let read_txn = state.begin_read()?;
+        
+        // Return 404 if the ID is not found or deserialization fails
+        let Some(record) = find_record(&read_txn, &id)? else {
+            return Ok(not_found_response(&state, &headers));
+        };
+        
+        let stored_hash = if record.password_protected {
+            let table_passwords = read_txn.open_table(TABLE_LINK_PASSWORDS)?;
+            table_passwords
+                .get(record.id.as_str())?
+                .map(|guard| guard.value().to_string())
+        } else {
+            None
+        };
+        
+        (record, stored_hash)
+    };
+    
+    // Everything below is keyed by the canonical slug, even when reached via an alias
+    let id = record.id.clone();
+    
+    if !record.is_active {
+        return Ok(disabled_response(&record, &headers));
+    }
+    
+    if record.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Ok(expired_response(&headers));
+    }
+    
+    if !referrer_allowed(&record, header_string(&headers, header::REFERER).as_deref()) {
+        return Ok(referrer_blocked_response(&headers));
+    }
+    
+    if record.password_protected {
+        if let Some(response) = password_rejection(&state, &id, stored_hash, params.password) {
+            return Ok(response);
+        }
+    }
+    
+    let no_count = params.no_count
+        || header_string(&headers, HeaderName::from_static("x-no-count"))
+            .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+    
+    // Capped links admit each visitor once, identified by the peer unless it
+    // is a trusted proxy; a storage failure lets the visit through
+    let peer = connect_info.map(|Extension(ConnectInfo(peer))| peer);
+    if let Some(max) = record.max_unique_visitors {
+        let ip = trusted_client_ip(&headers, peer, &state.config.trusted_proxies);
+        let hash = visitors::visitor_hash(record.created_at, ip);
+        let admission = if no_count {
+            visitors::check(&state, &id, &hash, max)
+        } else {
+            visitors::admit(&state, &id, &hash, max)
+        };
+        match admission {
+            Ok(Admission::LimitReached) => return Ok(visitor_limit_response(&headers)),
+            Ok(_) => {}
+            Err(err) => tracing::warn!("Failed to check visitors of {}: {}", id, err),
+        }
+    }
     
-    // Look up the short URL ID in the database
-    if let Some(value) = table.get(id.as_str()).unwrap() {
-        // Deserialize the JSON record
-        if let Ok(record) = serde_json::from_str::<UrlRecord>(value.value()) {
-            // TODO: Add logic to increment click counter here
-            // This would require a write transaction to update the clicks field
-            return Redirect::temporary(&record.original_url).into_response();
+    // Record the click event and bump `clicks`; analytics failures must never block the redirect
+    let country = || {
+        state
+            .geoip
+            .as_ref()
+            .and_then(|geoip| client_ip(&headers, peer).and_then(|ip| geoip.country_code(ip)))
+    };
+    let mut counted = false;
+    if !no_count {
+        let event = ClickEvent {
+            id: id.clone(),
+            timestamp: Utc::now(),
+            referrer: header_string(&headers, header::REFERER),
+            user_agent: header_string(&headers, header::USER_AGENT),
+            country: country(),
+        };
+        // Under a spike, drop the count and webhook rather than queue them
+        match state.redirect_permits.clone().try_acquire_owned() {
+            Ok(permit) => {
+                counted = true;
+                let (recorded_tx, recorded) = oneshot::channel();
+                tokio::spawn(count_visit(state.clone(), event, record.click_webhook.clone(), permit, recorded_tx));
+                // Only the commit is awaited, so reads right after the redirect see it
+                let _ = recorded.await;
+            }
+            Err(_) => state.metrics.record_dropped_click(),
         }
     }
     
-    // Return 404 if the ID is not found or deserialization fails
-    (StatusCode::NOT_FOUND, "URL not found").into_response()
+    let destination = pick_destination(&record);
+    
+    // Only build the live-log event when an admin is actually watching
+    if state.redirect_log.receiver_count() > 0 {
+        // A send only fails when the last subscriber disconnected meanwhile
+        let _ = state.redirect_log.send(RedirectLogEvent {
+            id: id.clone(),
+            destination_host: redirect_log::destination_host(destination),
+            timestamp: Utc::now(),
+            referrer: header_string(&headers, header::REFERER),
+            user_agent: header_string(&headers, header::USER_AGENT),
+            country: country(),
+            counted,
+        });
+    }
+    
+    // Advertise the canonical short link so crawlers can discover it
+    let canonical = HeaderValue::from_str(&format!("<{}>; rel=\"canonical\"", record.short_url))
+        .ok()
+        .map(|value| [(header::LINK, value)]);
+    
+    // Links with a delay get a countdown page instead of an immediate redirect
+    if let Some(delay) = record.redirect_delay_seconds.filter(|delay| *delay > 0) {
+        return Ok((canonical, Html(redirect_countdown(destination, delay))).into_response());
+    }
+    
+    let mut response = (canonical, Redirect::temporary(destination)).into_response();
+    // axum's `Redirect::permanent` is a 308; search engines expect a plain 301
+    if record.permanent {
+        *response.status_mut() = StatusCode::MOVED_PERMANENTLY;
+    }
+    Ok(response)
 }
 
-/// Lists URLs with pagination and filtering by ref_id
+/// Records a visit, then fires the link's click webhook
 /// 
-/// This handler enables users to retrieve all their shortened URLs
-/// using efficient pagination. It leverages the ref_id index for
-/// fast lookups without scanning the entire database.
+/// Runs on its own task holding `permit` (a `MAX_CONCURRENT_REDIRECTS` slot)
+/// until both are done, so a spike cannot pile up writes or outbound requests,
+/// and a client hanging up does not abandon either. `recorded` is signalled
+/// once the click is committed; the write is blocking redb I/O and runs on
+/// the blocking pool.
+async fn count_visit(
+    state: AppState,
+    event: ClickEvent,
+    webhook: Option<String>,
+    permit: OwnedSemaphorePermit,
+    recorded: oneshot::Sender<()>,
+) {
+    let blocking_state = state.clone();
+    let blocking_event = event.clone();
+    match tokio::task::spawn_blocking(move || record_visit(&blocking_state, &blocking_event)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => tracing::warn!("Failed to record click event for {}: {}", event.id, err),
+        Err(err) => tracing::warn!("Recording a click for {} panicked: {}", event.id, err),
+    }
+    let _ = recorded.send(());
+    
+    if let Some(webhook) = webhook {
+        send_click_webhook(&state.http_client, &webhook, &event).await;
+    }
+    drop(permit);
+}
+
+/// Records a counted visit
 /// 
-/// # Query Parameters
+/// Logs the event, bumps the daily stats and `clicks` in one transaction.
+#[cfg(feature = "analytics")]
+fn record_visit(state: &AppState, event: &ClickEvent) -> Result<(), redb::Error> {
+    record_click(state, event)
+}
+
+/// Records a counted visit
 /// 
-/// - `ref_id` (required) - Filter URLs by this reference ID
-/// - `page` (optional) - Page number, starts from 1 (default: 1)
-/// - `limit` (optional) - Items per page, max 100 (default: 10)
+/// Without the `analytics` feature only the total `clicks` is kept.
+#[cfg(not(feature = "analytics"))]
+fn record_visit(state: &AppState, event: &ClickEvent) -> Result<(), redb::Error> {
+    let write_txn = state.begin_write()?;
+    increment_clicks(&write_txn, &event.id)?;
+    write_txn.commit()?;
+    Ok(())
+}
+
+/// Bumps `clicks` and `last_accessed_at` on a link's record
+/// 
+/// A record that cannot be deserialized is left as it is; with the
+/// `analytics` feature its events and daily stats are still recorded. The
+/// record is written back in the format it was stored in.
+pub(crate) fn increment_clicks(write_txn: &WriteTransaction, id: &str) -> Result<(), redb::Error> {
+    let mut table_main = write_txn.open_table(TABLE_URLS)?;
+    let record = table_main
+        .get(id)?
+        .map(|guard| (RecordFormat::of(guard.value()), record_format::decode(guard.value())));
+    
+    let (format, mut record) = match record {
+        Some((format, Ok(record))) => (format, record),
+        Some((_, Err(err))) => {
+            tracing::warn!("Not counting click for {}: unreadable record: {}", id, err);
+            return Ok(());
+        }
+        None => return Ok(()),
+    };
+    
+    record.clicks += 1;
+    record.last_accessed_at = Some(Utc::now());
+    let record_value = format.encode(&record);
+    table_main.insert(id, record_value.as_str())?;
+    
+    Ok(())
+}
+
+/// Looks up a record by slug, following the alias of a renamed link
+/// 
+/// Returns `None` when neither a record nor an alias exists. Storage failures
+/// and records that cannot be decoded are returned as errors.
+fn find_record(read_txn: &ReadTransaction, id: &str) -> Result<Option<UrlRecord>, AppError> {
+    let table = read_txn.open_table(TABLE_URLS)?;
+    
+    // Old slugs of renamed links resolve through the alias table
+    let target = match table.get(id)? {
+        Some(_) => id.to_string(),
+        None => {
+            let table_aliases = read_txn.open_table(TABLE_ALIASES)?;
+            let alias = table_aliases.get(id)?;
+            alias.map(|guard| guard.value().to_string()).unwrap_or_else(|| id.to_string())
+        }
+    };
+    
+    Ok(load_record(&table, &target)?)
+}
+
+/// Returns the public base URL short links are built on (`URL` + `PORT`)
+fn short_url_domain() -> String {
+    let base_url = std::env::var("URL").unwrap_or_else(|_| "http://localhost".to_string());
+    let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+    format!("{}:{}", base_url, port)
+}
+
+/// Resolves the ref_id a request acts as
+/// 
+/// In JWT mode the token's subject always wins over a ref_id supplied in the
+/// body or query, so callers cannot act on another tenant's links.
+pub(crate) fn effective_ref_id(subject: Option<Extension<AuthSubject>>, requested: Option<String>) -> Option<String> {
+    match subject {
+        Some(Extension(AuthSubject(subject))) => Some(subject),
+        None => requested,
+    }
+}
+
+/// Reads a header as an owned string, ignoring missing or non-UTF-8 values
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+/// Builds the 429 response for a slug locked out after wrong passwords
+fn password_locked_response(remaining: std::time::Duration) -> axum::response::Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, remaining.as_secs().max(1).to_string())],
+        Json(json!({
+            "error": "Too many wrong passwords. Try again later.",
+            "code": "password_locked"
+        })),
+    )
+        .into_response()
+}
+
+/// Verifies the password supplied for a protected link
+/// 
+/// Returns the rejection response when access must be denied, or `None`
+/// when the link may be followed.
+/// 
+/// Locked slugs are rejected before the password is even compared so that a
+/// correct guess during the cooldown reveals nothing. A successful unlock
+/// clears the failure count.
+fn password_rejection(
+    state: &AppState,
+    id: &str,
+    stored_hash: Option<String>,
+    password: Option<String>,
+) -> Option<axum::response::Response> {
+    if let Some(remaining) = state.password_attempts.locked_for(id) {
+        return Some(password_locked_response(remaining));
+    }
+    
+    let Some(password) = password.filter(|password| !password.is_empty()) else {
+        return Some((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "This link is password protected",
+                "code": "password_required"
+            })),
+        )
+            .into_response());
+    };
+    
+    // A protected record without a stored hash can never be unlocked
+    let matches = stored_hash
+        .map(|hash| verify_password(id, &password, &hash))
+        .unwrap_or(false);
+    
+    if !matches {
+        state.password_attempts.record_failure(id);
+        return Some((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Invalid password",
+                "code": "invalid_password"
+            })),
+        )
+            .into_response());
+    }
+    
+    state.password_attempts.reset(id);
+    None
+}
+
+/// Whether the client asked for an HTML page (i.e. is a browser)
+fn accepts_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// Builds the 410 response for a link past its `expires_at`
+fn expired_response(headers: &HeaderMap) -> axum::response::Response {
+    if accepts_html(headers) {
+        return (StatusCode::GONE, Html(link_unavailable("This link has expired."))).into_response();
+    }
+    
+    (
+        StatusCode::GONE,
+        Json(json!({
+            "error": "This link has expired",
+            "code": "link_expired"
+        })),
+    )
+        .into_response()
+}
+
+/// Builds the 410 response for a new visitor once `max_unique_visitors` is reached
+fn visitor_limit_response(headers: &HeaderMap) -> axum::response::Response {
+    if accepts_html(headers) {
+        return (
+            StatusCode::GONE,
+            Html(link_unavailable("This link has reached its maximum number of visitors.")),
+        )
+            .into_response();
+    }
+    
+    (
+        StatusCode::GONE,
+        Json(json!({
+            "error": "This link has reached its maximum number of visitors",
+            "code": "visitor_limit_reached"
+        })),
+    )
+        .into_response()
+}
+
+/// Whether a visit with this `Referer` may follow the link
+/// 
+/// Links without `allowed_referrers` accept every visit. Otherwise the
+/// referrer's host must equal an entry or be a subdomain of one; visits
+/// without a (parsable) referrer pass unless `require_referrer` is set.
+pub fn referrer_allowed(record: &UrlRecord, referrer: Option<&str>) -> bool {
+    if record.allowed_referrers.is_empty() {
+        return true;
+    }
+    
+    let host = referrer
+        .and_then(|referrer| Url::parse(referrer).ok())
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+    let Some(host) = host else {
+        return !record.require_referrer;
+    };
+    
+    record.allowed_referrers.iter().any(|allowed| {
+        host == *allowed || host.strip_suffix(allowed.as_str()).is_some_and(|sub| sub.ends_with('.'))
+    })
+}
+
+/// Builds the 403 response for a visit from a referrer the link does not allow
+fn referrer_blocked_response(headers: &HeaderMap) -> axum::response::Response {
+    if accepts_html(headers) {
+        return (
+            StatusCode::FORBIDDEN,
+            Html(link_unavailable("This link cannot be opened from this site.")),
+        )
+            .into_response();
+    }
+    
+    (
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": "This link cannot be opened from this referrer",
+            "code": "referrer_not_allowed"
+        })),
+    )
+        .into_response()
+}
+
+/// Builds the 410 response for a disabled link
+/// 
+/// Shows the link's `disabled_message` (or a generic text) as an HTML page to
+/// browsers and as JSON to everything else.
+fn disabled_response(record: &UrlRecord, headers: &HeaderMap) -> axum::response::Response {
+    let message = record
+        .disabled_message
+        .as_deref()
+        .filter(|message| !message.is_empty())
+        .unwrap_or("This link is no longer available.");
+    
+    if accepts_html(headers) {
+        return (StatusCode::GONE, Html(link_unavailable(message))).into_response();
+    }
+    
+    (
+        StatusCode::GONE,
+        Json(json!({
+            "error": "This link has been disabled",
+            "code": "link_disabled",
+            "message": message
+        })),
+    )
+        .into_response()
+}
+
+/// Builds the 404 response for a redirect miss
+/// 
+/// Browser requests get the operator's custom HTML page when one was loaded
+/// at startup; everything else gets the plain-text default.
+fn not_found_response(state: &AppState, headers: &HeaderMap) -> axum::response::Response {
+    match &state.config.not_found_template {
+        Some(template) if accepts_html(headers) => {
+            (StatusCode::NOT_FOUND, Html(template.clone())).into_response()
+        }
+        _ => (StatusCode::NOT_FOUND, "URL not found").into_response(),
+    }
+}
+
+/// Header carrying the total number of links matching a list request
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Lists URLs with pagination and filtering by ref_id
+/// 
+/// This handler enables users to retrieve all their shortened URLs
+/// using efficient pagination. It leverages the ref_id index for
+/// fast lookups without scanning the entire database.
+/// 
+/// # Query Parameters
+/// 
+/// - `ref_id` (required) - Filter URLs by this reference ID
+/// - `page` (optional) - Page number, starts from 1 (default: 1)
+/// - `limit` (optional) - Items per page, max 100 (default: the ref_id's
+///   `default_limit` preference, else 10)
+/// - `sort` (optional) - `last_accessed_desc` orders by `last_accessed_at`,
+///   most recent first and never-accessed links last; this loads every
+///   matching record before paginating
 /// 
 /// # Example Request
 /// 
@@ -203,35 +1426,105 @@ pub async fn redirect_url(
 ///   "page": 2,
 ///   "limit": 20,
 ///   "total_fetched": 15,
+///   "total": 35,
+///   "links": {
+///     "first": "/api/urls?ref_id=user_123&page=1&limit=20",
+///     "prev": "/api/urls?ref_id=user_123&page=1&limit=20",
+///     "next": null,
+///     "last": "/api/urls?ref_id=user_123&page=2&limit=20"
+///   },
 ///   "data": [...]
 /// }
 /// ```
 /// 
+/// `links` holds ready-to-use navigation URLs built from the request path and
+/// query; `prev`/`next` are `null` on the first/last page.
+/// 
+/// With `?format=csv` (or `Accept: text/csv` and no `format`) the same page
+/// is returned as `text/csv` instead: an `id,original_url,clicks,created_at`
+/// header row, then one row per link. `time_format` applies to `created_at`,
+/// and the total is still in `X-Total-Count`.
+/// 
+/// A ref_id without any links returns the empty page above, or
+/// `404 {"code": "no_links"}` when `EMPTY_LIST_404` is enabled.
+/// 
+/// `total` counts every matching link, not just this page; it is also sent in
+/// `X-Total-Count`. `HEAD`
+/// returns the same status and headers without a body, and skips loading
+/// the page entirely, for cheap liveness and metadata checks.
+/// 
 /// # Performance
 /// 
-/// Uses range queries on the ref_id index table for O(log n) lookup time.
+/// Uses range queries on the ref_id index table for O(log n) lookup time;
+/// each index entry holds only a slug, resolved with one `TABLE_URLS` lookup.
 /// The composite key format "{ref_id}:{timestamp}" ensures results are
 /// returned in chronological order (newest first due to descending range).
+/// Without a ref_id every record is loaded and sorted by `created_at`, newest
+/// first, before paginating.
+/// 
+/// Index entries resolving to an already-seen record id are dropped (first
+/// occurrence wins) and logged, so stale duplicates never reach clients.
+/// Entries whose slug has no record are always skipped. With
+/// `VALIDATE_INDEX_ON_READ`, entries whose key does not match their record's
+/// ref_id and creation time are skipped too, and every mismatched entry seen
+/// is logged and deleted.
 pub async fn list_urls(
+    method: Method,
     State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    subject: Option<Extension<AuthSubject>>,
+    headers: HeaderMap,
     Query(params): Query<ListParams>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+    let csv = match params.format {
+        Some(format) => format == ListFormat::Csv,
+        None => header_string(&headers, header::ACCEPT).is_some_and(|accept| accept.contains("text/csv")),
+    };
+    let content_type = if csv { "text/csv; charset=utf-8" } else { "application/json" };
+    
     // Ensure page is at least 1
     let page = params.page.unwrap_or(1).max(1);
     
+    // Begin a read-only transaction
+    let read_txn = state.begin_read()?;
+    
+    // Without an explicit limit, use the tenant's preferred page size
+    let default_limit = ref_id
+        .as_deref()
+        .map(|ref_id| preferences::load(&read_txn, ref_id))
+        .transpose()?
+        .and_then(|preferences| preferences.default_limit)
+        .unwrap_or(DEFAULT_PAGE_LIMIT);
+    
     // Limit to maximum of 100 items per page
-    let limit = params.limit.unwrap_or(10).min(100);
+    let limit = params.limit.unwrap_or(default_limit).min(MAX_PAGE_LIMIT);
     
     // Calculate offset for pagination
     let offset = (page - 1) * limit;
+    
+    // Counting never deserializes values, so it is cheap compared to loading a page
+    let total = count_links(&read_txn, ref_id.as_deref())?;
+    let empty_404 = state.config.empty_list_404 && ref_id.is_some() && total == 0;
+    
+    // HEAD only needs the metadata, so skip loading the page
+    if method == Method::HEAD {
+        let status = if empty_404 { StatusCode::NOT_FOUND } else { StatusCode::OK };
+        return Ok((
+            status,
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+                (TOTAL_COUNT_HEADER, HeaderValue::from(total)),
+            ],
+        )
+            .into_response());
+    }
 
-    // Begin a read-only transaction
-    let read_txn = state.db.begin_read().unwrap();
-
-    let results: Vec<UrlRecord> = match &params.ref_id {
+    let results: Vec<UrlRecord> = match &ref_id {
         // If ref_id is provided, use the efficient index-based query
         Some(ref_id) => {
-            let table = read_txn.open_table(TABLE_REF_INDEX).unwrap();
+            let table = read_txn.open_table(TABLE_REF_INDEX)?;
+            let table_main = read_txn.open_table(TABLE_URLS)?;
             
             // Define range query boundaries for the ref_id
             // start_key: "user_123:" - matches all entries starting with this ref_id
@@ -240,45 +1533,254 @@ pub async fn list_urls(
             let start_key = format!("{}:", ref_id);
             let end_key = format!("{}:{{", ref_id);
 
-            // Execute range query with pagination
-            table
-                .range(start_key.as_str()..end_key.as_str())
-                .unwrap()
-                .skip(offset)  // Skip items from previous pages
-                .take(limit)   // Take only the requested number of items
-                .filter_map(|res| {
-                    // Handle potential errors and deserialize the JSON records
-                    res.ok()
-                        .and_then(|(_, value)| serde_json::from_str::<UrlRecord>(value.value()).ok())
+            // Stale or duplicated index entries may point at the same record,
+            // so keep only the first occurrence of each id
+            let mut seen_ids = HashSet::new();
+            let mut duplicates = 0usize;
+            
+            // With VALIDATE_INDEX_ON_READ, entries whose slug has no record,
+            // or whose record is indexed under another key, are skipped and
+            // collected for repair
+            let validate = state.config.validate_index_on_read;
+            let mut orphans = Vec::new();
+
+            // Execute range query, newest key (latest creation) first
+            let records = table
+                .range(start_key.as_str()..end_key.as_str())?
+                .rev()
+                .map(|res| -> Result<Option<UrlRecord>, AppError> {
+                    // Resolve each slug to its record; read errors fail the listing
+                    let (key, id) = res?;
+                    let record = load_record(&table_main, id.value())?;
+                    if !validate {
+                        return Ok(record);
+                    }
+                    // Unreadable records were returned as errors above, so they
+                    // never count as orphans
+                    Ok(match record {
+                        Some(record) if ref_index_key(&record).as_deref() == Some(key.value()) => {
+                            // Keys of a ref_id containing ':' share this range
+                            (record.ref_id.as_deref() == Some(ref_id.as_str())).then_some(record)
+                        }
+                        Some(_) | None => {
+                            orphans.push(key.value().to_string());
+                            None
+                        }
+                    })
                 })
-                .collect()
+                .filter_map(Result::transpose)
+                .filter(|record| {
+                    let Ok(record) = record else {
+                        return true;
+                    };
+                    let first_seen = seen_ids.insert(record.id.clone());
+                    if !first_seen {
+                        duplicates += 1;
+                    }
+                    first_seen
+                });
+            
+            let results = match params.sort {
+                Some(sort) => sorted_page(records.collect::<Result<Vec<_>, _>>()?.into_iter(), sort, offset, limit),
+                // Records of earlier pages are still read, so their errors are not skipped
+                None => records
+                    .take(offset + limit)
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter()
+                    .skip(offset)
+                    .collect(),
+            };
+            
+            if duplicates > 0 {
+                tracing::warn!(
+                    "ref_id index for {} contains {} duplicate entries; consider reindexing",
+                    ref_id,
+                    duplicates
+                );
+            }
+            
+            if !orphans.is_empty() {
+                tracing::warn!(
+                    "ref_id index for {} contains {} entries without a matching record; removing them",
+                    ref_id,
+                    orphans.len()
+                );
+                if let Err(err) = remove_index_entries(&state, &orphans) {
+                    tracing::error!("Failed to repair ref_id index for {}: {}", ref_id, err);
+                }
+            }
+            
+            results
         },
         // If ref_id is not provided, return all URLs from the main table
         // WARNING: This can be slow for large databases
         None => {
-            let table = read_txn.open_table(TABLE_URLS).unwrap();
+            let table = read_txn.open_table(TABLE_URLS)?;
             
-            table
-                .iter()
-                .unwrap()
-                .skip(offset)
-                .take(limit)
-                .filter_map(|res| {
-                    res.ok()
-                        .and_then(|(_, value)| serde_json::from_str::<UrlRecord>(value.value()).ok())
+            // The main table is keyed by slug, so newest-first needs every
+            // record loaded and sorted by creation time; a record that cannot
+            // be read fails the whole listing rather than silently vanishing
+            let mut records = table
+                .iter()?
+                .map(|res| {
+                    let (_, value) = res?;
+                    Ok(record_format::decode(value.value())?)
                 })
-                .collect()
+                .collect::<Result<Vec<UrlRecord>, AppError>>()?;
+            records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+            
+            match params.sort {
+                Some(sort) => sorted_page(records.into_iter(), sort, offset, limit),
+                None => records.into_iter().skip(offset).take(limit).collect(),
+            }
         }
     };
 
-    // Return paginated results with metadata
-    Json(serde_json::json!({
+    if empty_404 {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "No links found for this ref_id",
+                "code": "no_links"
+            })),
+        )
+            .into_response());
+    }
+
+    if csv {
+        let mut body = String::from("id,original_url,clicks,created_at\n");
+        for record in &results {
+            let created_at = match params.time_format {
+                TimeFormat::Rfc3339 => record.created_at.to_rfc3339(),
+                TimeFormat::EpochMs => record.created_at.timestamp_millis().to_string(),
+            };
+            body.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(&record.id),
+                csv_field(&record.original_url),
+                record.clicks,
+                created_at,
+            ));
+        }
+        return Ok((
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+                (TOTAL_COUNT_HEADER, HeaderValue::from(total)),
+            ],
+            body,
+        )
+            .into_response());
+    }
+
+    let mut body = serde_json::json!({
         "page": page,
         "limit": limit,
         "total_fetched": results.len(),
+        "total": total,
+        "links": pagination_links(&uri, page, limit, total),
         "data": results
-    }))
-    .into_response()
+    });
+    params.time_format.apply(&mut body);
+    
+    // Return paginated results with metadata
+    Ok(([(TOTAL_COUNT_HEADER, HeaderValue::from(total))], Json(body)).into_response())
+}
+
+/// Returns the `TABLE_REF_INDEX` key a record should be indexed under
+fn ref_index_key(record: &UrlRecord) -> Option<String> {
+    let ref_id = record.ref_id.as_ref()?;
+    Some(format!("{}:{}", ref_id, record.created_at.timestamp_micros()))
+}
+
+/// Deletes orphaned `TABLE_REF_INDEX` entries found while listing
+/// 
+/// Always writes to the primary, even when the listing read from a replica,
+/// and checks each entry again there: one is only deleted when its slug has
+/// no record or the record is indexed under another key.
+fn remove_index_entries(state: &AppState, keys: &[String]) -> Result<(), redb::Error> {
+    let write_txn = state.begin_write()?;
+    {
+        let table_main = write_txn.open_table(TABLE_URLS)?;
+        let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+        for key in keys {
+            let Some(id) = table_index.get(key.as_str())?.map(|id| id.value().to_string()) else {
+                continue;
+            };
+            let orphaned = match load_record(&table_main, &id)? {
+                Some(record) => ref_index_key(&record).as_deref() != Some(key.as_str()),
+                None => true,
+            };
+            if orphaned {
+                table_index.remove(key.as_str())?;
+            }
+        }
+    }
+    write_txn.commit()?;
+    Ok(())
+}
+
+/// Orders the whole filtered set by `sort`, then cuts out one page
+/// 
+/// The sort keys are not indexed, so every matching record is loaded; ties
+/// keep their index order.
+fn sorted_page(records: impl Iterator<Item = UrlRecord>, sort: ListSort, offset: usize, limit: usize) -> Vec<UrlRecord> {
+    let mut records: Vec<UrlRecord> = records.collect();
+    match sort {
+        // `None` orders before any `Some`, so never-accessed links end up last
+        ListSort::LastAccessedDesc => records.sort_by_key(|record| std::cmp::Reverse(record.last_accessed_at)),
+    }
+    records.into_iter().skip(offset).take(limit).collect()
+}
+
+/// Counts the index entries of `ref_id`, or every link when it is `None`
+fn count_links(read_txn: &ReadTransaction, ref_id: Option<&str>) -> Result<usize, AppError> {
+    let count = match ref_id {
+        Some(ref_id) => {
+            let table = read_txn.open_table(TABLE_REF_INDEX)?;
+            let start_key = format!("{}:", ref_id);
+            let end_key = format!("{}:{{", ref_id);
+            table.range(start_key.as_str()..end_key.as_str())?.count()
+        }
+        None => {
+            let table = read_txn.open_table(TABLE_URLS)?;
+            table.len()? as usize
+        }
+    };
+    Ok(count)
+}
+
+/// Builds `first`/`prev`/`next`/`last` navigation URLs for a list response
+/// 
+/// Every query parameter except `page` and `limit` is carried over verbatim
+/// (already percent-encoded), so filters such as `ref_id` survive navigation.
+fn pagination_links(uri: &axum::http::Uri, page: usize, limit: usize, total: usize) -> serde_json::Value {
+    let last_page = total.div_ceil(limit.max(1)).max(1);
+    
+    let preserved: Vec<&str> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or("");
+            !pair.is_empty() && key != "page" && key != "limit"
+        })
+        .collect();
+    
+    let link = |target: usize| {
+        let mut query = preserved.clone();
+        let page_pair = format!("page={}", target);
+        let limit_pair = format!("limit={}", limit);
+        query.push(&page_pair);
+        query.push(&limit_pair);
+        format!("{}?{}", uri.path(), query.join("&"))
+    };
+    
+    json!({
+        "first": link(1),
+        "prev": (page > 1).then(|| link((page - 1).min(last_page))),
+        "next": (page < last_page).then(|| link(page + 1)),
+        "last": link(last_page),
+    })
 }
 
 /// Deletes a short URL with ownership verification
@@ -303,6 +1805,7 @@ pub async fn list_urls(
 /// - **200 OK** - URL successfully deleted
 /// - **404 Not Found** - URL does not exist
 /// - **403 Forbidden** - ref_id does not match (not the owner)
+/// - **500 Internal Server Error** - Storage kept failing after `WRITE_RETRIES` retries
 /// 
 /// # Database Operations
 /// 
@@ -312,75 +1815,98 @@ pub async fn list_urls(
 pub async fn delete_short_url(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
     Query(params): Query<DeleteParams>,
 ) -> impl IntoResponse {
-    // Begin a write transaction
-    let write_txn = state.db.begin_write().unwrap();
-
-    {
-        // Open the main URLs table
-        let mut table_main = write_txn.open_table(TABLE_URLS).unwrap();
-        
-        // Retrieve the existing record to verify ownership
-        let record = match table_main.get(id.as_str()).unwrap() {
-            Some(guard) => serde_json::from_str::<UrlRecord>(guard.value()).unwrap(),
-            None => {
-                // Return 404 if the URL doesn't exist
-                return (
-                    StatusCode::NOT_FOUND,
-                    Json(json!({
-                        "error": "URL not found",
-                        "code": "not_found"
-                    })),
-                )
-                    .into_response()
-            }
-        };
+    let ref_id = effective_ref_id(subject, params.ref_id);
+    
+    // Transient storage errors retry the whole transaction (see `with_write_retry`)
+    let outcome = with_write_retry(state.config.write_retries, || {
+        // Begin a write transaction
+        let write_txn = state.begin_write()?;
         
-        // Verify ownership by comparing ref_id (only if ref_id is provided in the request)
-        if let Some(request_ref_id) = &params.ref_id {
-            // If the record has a ref_id, it must match the request ref_id
-            match &record.ref_id {
-                Some(record_ref_id) => {
-                    if record_ref_id != request_ref_id {
-                        return (
-                            StatusCode::FORBIDDEN,
-                            Json(json!({
-                                "error": "You are not authorized to delete this link",
-                                "code": "forbidden"
-                            })),
-                        )
-                            .into_response();
+        {
+            // Open the main URLs table
+            let mut table_main = write_txn.open_table(TABLE_URLS)?;
+            
+            // Retrieve the existing record to verify ownership
+            let record = match table_main.get(id.as_str())? {
+                Some(guard) => record_format::decode(guard.value())?,
+                None => return Ok(DeleteOutcome::NotFound),
+            };
+            
+            // Verify ownership by comparing ref_id (only if ref_id is provided in the request)
+            if let Some(request_ref_id) = &ref_id {
+                // If the record has a ref_id, it must match the request ref_id
+                match &record.ref_id {
+                    Some(record_ref_id) => {
+                        if record_ref_id != request_ref_id {
+                            return Ok(DeleteOutcome::Forbidden("You are not authorized to delete this link"));
+                        }
+                    },
+                    None => {
+                        // Record has no ref_id, but request is trying to verify ownership
+                        return Ok(DeleteOutcome::Forbidden(
+                            "This URL has no owner and cannot be deleted with ref_id verification",
+                        ));
                     }
-                },
-                None => {
-                    // Record has no ref_id, but request is trying to verify ownership
-                    return (
-                        StatusCode::FORBIDDEN,
-                        Json(json!({
-                            "error": "This URL has no owner and cannot be deleted with ref_id verification",
-                            "code": "forbidden"
-                        })),
-                    )
-                        .into_response();
                 }
             }
+            
+            // Delete from the main table and the creation-time index
+            table_main.remove(id.as_str())?;
+            let mut table_created = write_txn.open_table(TABLE_CREATED_INDEX)?;
+            table_created.remove(created_index_key(record.created_at, &id).as_str())?;
+            
+            // Delete from the ref_id index (only if the record has a ref_id)
+            if let Some(record_ref_id) = &record.ref_id {
+                let index_key = format!("{}:{}", record_ref_id, record.created_at.timestamp_micros());
+                let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+                table_index.remove(index_key.as_str())?;
+            }
+            
+            // Drop aliases left by earlier renames so their slugs become free again
+            let mut table_aliases = write_txn.open_table(TABLE_ALIASES)?;
+            let aliases = aliases_of(&table_aliases, &id)?;
+            for alias in aliases {
+                table_aliases.remove(alias.as_str())?;
+            }
+            
+            // Delete the password hash (only if the link was protected)
+            if record.password_protected {
+                let mut table_passwords = write_txn.open_table(TABLE_LINK_PASSWORDS)?;
+                table_passwords.remove(id.as_str())?;
+            }
+            
+            replace_destination(&write_txn, &record.original_url, &id, None)?;
+            history::delete_history(&write_txn, &id)?;
+            visitors::delete_visitors(&write_txn, &id)?;
+            counters::delete_counters(&write_txn, &id)?;
         }
         
-        // Delete from the main table
-        table_main.remove(id.as_str()).unwrap();
-        
-        // Delete from the ref_id index (only if the record has a ref_id)
-        if let Some(record_ref_id) = &record.ref_id {
-            let index_key = format!("{}:{}", record_ref_id, record.created_at.timestamp_micros());
-            let mut table_index = write_txn.open_table(TABLE_REF_INDEX).unwrap();
-            table_index.remove(index_key.as_str()).unwrap();
+        // Commit the transaction to persist the deletion
+        write_txn.commit()?;
+        Ok(DeleteOutcome::Deleted)
+    })
+    .await;
+    
+    let error = |status: StatusCode, error: &str, code: &str| {
+        (status, Json(json!({ "error": error, "code": code }))).into_response()
+    };
+    match outcome {
+        Ok(DeleteOutcome::Deleted) => {}
+        Ok(DeleteOutcome::NotFound) => return error(StatusCode::NOT_FOUND, "URL not found", "not_found"),
+        Ok(DeleteOutcome::Forbidden(message)) => return error(StatusCode::FORBIDDEN, message, "forbidden"),
+        Err(err) => {
+            tracing::error!("Failed to delete link {}: {}", id, err);
+            return error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The link could not be deleted. Please try again.",
+                "storage_error",
+            );
         }
     }
 
-    // Commit the transaction to persist the deletion
-    write_txn.commit().unwrap();
-
     // Return success response
     (
         StatusCode::OK,
@@ -391,3 +1917,1772 @@ pub async fn delete_short_url(
     )
         .into_response()
 }
+
+/// Result of the delete transaction, turned into a response by `delete_short_url`
+enum DeleteOutcome {
+    Deleted,
+    NotFound,
+    
+    /// Ownership check failed, with the message explaining why
+    Forbidden(&'static str),
+}
+
+/// Looks up a link by its full short URL
+/// 
+/// The host must match the configured `URL`; the port and scheme are not
+/// compared since links are often served through a proxy. The path is the
+/// slug, and old slugs of renamed links resolve to the current record.
+/// 
+/// # Query Parameters
+/// 
+/// - `short_url` (required) - URL-encoded short URL
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/resolve?short_url=http%3A%2F%2Flocalhost%3A8080%2Fabc123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - The `UrlRecord`
+/// - **400 Bad Request** - `short_url` missing or unparseable, or its host is
+///   not this shortener's domain
+/// - **404 Not Found** - No link with that slug
+pub async fn resolve_short_url(
+    State(state): State<AppState>,
+    Query(params): Query<ResolveParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let bad_request = |error: &str, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": error,
+                "code": code
+            })),
+        )
+            .into_response()
+    };
+    
+    let Some(short_url) = params.short_url.filter(|short_url| !short_url.is_empty()) else {
+        return Ok(bad_request("short_url is required", "missing_short_url"));
+    };
+    let Ok(parsed) = Url::parse(short_url.trim()) else {
+        return Ok(bad_request("short_url is not a valid URL", "invalid_short_url"));
+    };
+    
+    let domain = short_url_domain();
+    let expected_host = Url::parse(&domain).ok().and_then(|url| url.host_str().map(String::from));
+    if parsed.host_str().is_none() || parsed.host_str() != expected_host.as_deref() {
+        return Ok(bad_request("short_url does not belong to this shortener", "foreign_domain"));
+    }
+    
+    let slug = percent_decode_str(parsed.path().trim_matches('/'))
+        .decode_utf8()
+        .map(|slug| slug.into_owned())
+        .unwrap_or_default();
+    if slug.is_empty() {
+        return Ok(bad_request("short_url has no slug", "invalid_short_url"));
+    }
+    
+    let read_txn = state.begin_read()?;
+    Ok(match find_record(&read_txn, &slug)? {
+        Some(record) => Json(record).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response(),
+    })
+}
+
+/// Computes the short URL a slug would get, without creating anything
+/// 
+/// Applies the same `SLUG_PREFIX`/`SLUG_SUFFIX` and reserved-word rules as
+/// `custom_id` on create, and never touches the database, so the slug may or
+/// may not be available.
+/// 
+/// # Query Parameters
+/// 
+/// - `id` (required) - Slug as it would be sent in `custom_id`
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/preview-url?id=summer-sale`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"id": "go-summer-sale", "short_url": "http://localhost:8080/go-summer-sale"}`
+/// - **400 Bad Request** - `id` missing, or refused by the slug rules
+pub async fn preview_short_url(
+    State(state): State<AppState>,
+    Query(params): Query<PreviewUrlParams>,
+) -> impl IntoResponse {
+    let Some(requested) = params.id.filter(|id| !id.is_empty()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "id is required",
+                "code": "missing_id"
+            })),
+        )
+            .into_response();
+    };
+    
+    match slug::custom(&state.config, &requested) {
+        Ok(id) => Json(json!({
+            "short_url": format!("{}/{}", short_url_domain(), id),
+            "id": id
+        }))
+        .into_response(),
+        Err(err) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": err.to_string(),
+                "code": "invalid_custom_id"
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Checks a prospective custom slug against every rule in one call
+/// 
+/// Reports each rule separately (see `slug::SlugChecks`) so a form can show
+/// why a slug is refused. Availability covers links, renamed links' aliases
+/// and live reservations held by anyone but `ref_id`; it is a snapshot, so a
+/// later create can still lose a race with `409`.
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/validate-slug?id=summer-sale&ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"valid": false, "checks": {"charset": true, "length": true,
+///   "reserved": true, "numeric": true, "available": false}}`
+/// - **400 Bad Request** - `id` missing
+pub async fn validate_slug(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<ValidateSlugParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(requested) = params.id else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "id is required",
+                "code": "missing_id"
+            })),
+        )
+            .into_response());
+    };
+    let ref_id = effective_ref_id(subject, params.ref_id);
+    
+    let read_txn = state.begin_read()?;
+    // A failed lookup counts as taken here and fails the request below
+    let mut lookup = Ok(());
+    let checks = slug::validate_slug(&state.config, &requested, |stored| {
+        slug_taken(&read_txn, stored, ref_id.as_deref()).unwrap_or_else(|err| {
+            lookup = Err(err);
+            true
+        })
+    });
+    lookup?;
+    
+    Ok(Json(json!({
+        "valid": checks.valid(),
+        "checks": checks,
+    }))
+    .into_response())
+}
+
+/// Whether `stored` is used by a link or alias, or reserved by someone other than `owner`
+fn slug_taken(read_txn: &ReadTransaction, stored: &str, owner: Option<&str>) -> Result<bool, redb::Error> {
+    let table_main = read_txn.open_table(TABLE_URLS)?;
+    let table_aliases = read_txn.open_table(TABLE_ALIASES)?;
+    Ok(table_main.get(stored)?.is_some()
+        || table_aliases.get(stored)?.is_some()
+        || reservations::held_by_other(read_txn, stored, owner)?)
+}
+
+/// Describes a link as schema.org JSON-LD
+/// 
+/// The short link is modelled as a `WebPage` whose `potentialAction` is a
+/// `ViewAction` targeting the destination, so SEO and metadata tools can
+/// consume it without knowing this API.
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/abc123/jsonld`
+/// 
+/// # Response
+/// 
+/// ```json
+/// {
+///   "@context": "https://schema.org",
+///   "@type": "WebPage",
+///   "@id": "http://localhost:8080/abc123",
+///   "url": "http://localhost:8080/abc123",
+///   "identifier": "abc123",
+///   "dateCreated": "2026-01-17T13:40:00Z",
+///   "potentialAction": {
+///     "@type": "ViewAction",
+///     "target": "https://example.com/very/long/url"
+///   }
+/// }
+/// ```
+/// 
+/// - **200 OK** - `application/ld+json` document
+/// - **404 Not Found** - Short URL does not exist
+pub async fn link_jsonld(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let read_txn = state.begin_read()?;
+    let Some(record) = find_record(&read_txn, &id)? else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    };
+    
+    let document = json!({
+        "@context": "https://schema.org",
+        "@type": "WebPage",
+        "@id": record.short_url,
+        "url": record.short_url,
+        "identifier": record.id,
+        "dateCreated": record.created_at,
+        "potentialAction": {
+            "@type": "ViewAction",
+            "target": record.original_url
+        }
+    });
+    
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/ld+json")],
+        document.to_string(),
+    )
+        .into_response())
+}
+
+/// Moves a short URL to a new slug, keeping the old slug as an alias
+/// 
+/// The record, its index entry, password hash and click history move to
+/// `new_id`. The old slug is recorded in `TABLE_ALIASES`, and aliases left by
+/// earlier renames are repointed, so every previous slug keeps resolving.
+/// 
+/// # Path Parameters
+/// 
+/// - `id` - The current short URL identifier/slug
+/// 
+/// # Request Body
+/// 
+/// ```json
+/// { "new_id": "summer-sale", "ref_id": "user_123", "password": "only-if-protected" }
+/// ```
+/// 
+/// # Response
+/// 
+/// - **200 OK** - Renamed; body has the new `id` and `short_url`
+/// - **400 Bad Request** - `new_id` empty, equal to the current slug, reserved,
+///   or using the slug prefix/suffix
+/// - **401 Unauthorized** - Protected link and the password is missing or wrong
+/// - **403 Forbidden** - ref_id does not match (not the owner)
+/// - **404 Not Found** - URL does not exist
+/// - **409 Conflict** - `new_id` is already used by another link or alias, or
+///   reserved by another owner
+pub async fn rename_short_url(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<RenameRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, payload.ref_id);
+    let new_id = payload.new_id.trim().to_string();
+    
+    // A new slug follows the same prefix/suffix and reserved-word rules as a custom id
+    let new_id = match slug::custom(&state.config, &new_id) {
+        Ok(new_id) => new_id,
+        Err(err) => {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": err.to_string(),
+                    "code": "invalid_new_id"
+                })),
+            )
+                .into_response())
+        }
+    };
+    
+    if payload.new_id.trim().is_empty() || new_id == id {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "new_id must be non-empty and differ from the current id",
+                "code": "invalid_new_id"
+            })),
+        )
+            .into_response());
+    }
+    
+    let write_txn = state.begin_write()?;
+    let record = {
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+        let mut table_aliases = write_txn.open_table(TABLE_ALIASES)?;
+        
+        let mut record = match table_main.get(id.as_str())? {
+            Some(guard) => record_format::decode(guard.value())?,
+            None => {
+                return Ok((
+                    StatusCode::NOT_FOUND,
+                    Json(json!({
+                        "error": "URL not found",
+                        "code": "not_found"
+                    })),
+                )
+                    .into_response())
+            }
+        };
+        
+        // Same ownership rule as deletion: only checked when a ref_id is supplied
+        if let Some(request_ref_id) = &ref_id {
+            if record.ref_id.as_ref() != Some(request_ref_id) {
+                return Ok((
+                    StatusCode::FORBIDDEN,
+                    Json(json!({
+                        "error": "You are not authorized to rename this link",
+                        "code": "forbidden"
+                    })),
+                )
+                    .into_response());
+            }
+        }
+        
+        // An alias of this very link may be reclaimed (e.g. undoing a rename)
+        let alias_target = table_aliases
+            .get(new_id.as_str())?
+            .map(|guard| guard.value().to_string());
+        let alias_taken = alias_target.is_some_and(|target| target != id);
+        if table_main.get(new_id.as_str())?.is_some()
+            || alias_taken
+            || !reservations::claim(&write_txn, &new_id, record.ref_id.as_deref())?
+        {
+            return Ok((
+                StatusCode::CONFLICT,
+                Json(json!({
+                    "error": "new_id already taken. Please choose another.",
+                    "code": "id_taken"
+                })),
+            )
+                .into_response());
+        }
+        
+        // Password hashes are salted with the slug, so they must be recomputed
+        let mut table_passwords = write_txn.open_table(TABLE_LINK_PASSWORDS)?;
+        if record.password_protected {
+            // Renaming checks the password too, so it shares the redirect's lockout
+            if let Some(remaining) = state.password_attempts.locked_for(&id) {
+                return Ok(password_locked_response(remaining));
+            }
+            let stored_hash = table_passwords
+                .get(id.as_str())?
+                .map(|guard| guard.value().to_string());
+            let password = payload.password.filter(|password| !password.is_empty());
+            let verified = match (&password, stored_hash) {
+                (Some(password), Some(hash)) => verify_password(&id, password, &hash),
+                _ => false,
+            };
+            if password.is_some() && !verified {
+                state.password_attempts.record_failure(&id);
+            }
+            let Some(password) = password.filter(|_| verified) else {
+                return Ok((
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({
+                        "error": "The current password is required to rename a protected link",
+                        "code": "invalid_password"
+                    })),
+                )
+                    .into_response());
+            };
+            state.password_attempts.reset(&id);
+            table_passwords.remove(id.as_str())?;
+            table_passwords
+                .insert(new_id.as_str(), hash_password(&new_id, &password).as_str())?;
+        }
+        
+        record.short_url = match record.short_url.strip_suffix(id.as_str()) {
+            Some(base) => format!("{}{}", base, new_id),
+            None => record.short_url.clone(),
+        };
+        record.id = new_id.clone();
+        let record_value = state.config.record_format.encode(&record);
+        
+        table_main.remove(id.as_str())?;
+        table_main.insert(new_id.as_str(), record_value.as_str())?;
+        move_created_index_entry(&write_txn, record.created_at, &id, &new_id)?;
+        
+        if let Some(record_ref_id) = &record.ref_id {
+            let index_key = format!("{}:{}", record_ref_id, record.created_at.timestamp_micros());
+            let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+            table_index.insert(index_key.as_str(), new_id.as_str())?;
+        }
+        
+        // Repoint aliases from earlier renames so they never chain
+        let stale_aliases = aliases_of(&table_aliases, &id)?;
+        for alias in stale_aliases {
+            table_aliases.insert(alias.as_str(), new_id.as_str())?;
+        }
+        table_aliases.remove(new_id.as_str())?;
+        table_aliases.insert(id.as_str(), new_id.as_str())?;
+        
+        replace_destination(&write_txn, &record.original_url, &id, Some(&new_id))?;
+        history::rename_history(&write_txn, &id, &new_id)?;
+        visitors::rename_visitors(&write_txn, &id, &new_id)?;
+        counters::rename_counters(&write_txn, &id, &new_id)?;
+        
+        record
+    };
+    
+    #[cfg(feature = "analytics")]
+    rename_link_stats(&write_txn, &id, &new_id)?;
+    write_txn.commit()?;
+    
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": "Short link renamed successfully",
+            "id": record.id,
+            "previous_id": id,
+            "short_url": record.short_url
+        })),
+    )
+        .into_response())
+}
+
+/// Slugs in `TABLE_ALIASES` that currently lead to `id`
+fn aliases_of<T: ReadableTable<&'static str, &'static str>>(table_aliases: &T, id: &str) -> Result<Vec<String>, redb::Error> {
+    let mut aliases = Vec::new();
+    for entry in table_aliases.iter()? {
+        let (alias, target) = entry?;
+        if target.value() == id {
+            aliases.push(alias.value().to_string());
+        }
+    }
+    Ok(aliases)
+}
+
+/// Rekeys a link's `TABLE_CREATED_INDEX` entry after its slug changed
+fn move_created_index_entry(
+    write_txn: &WriteTransaction,
+    created_at: chrono::DateTime<Utc>,
+    id: &str,
+    new_id: &str,
+) -> Result<(), redb::Error> {
+    let mut table_created = write_txn.open_table(TABLE_CREATED_INDEX)?;
+    table_created.remove(created_index_key(created_at, id).as_str())?;
+    table_created.insert(created_index_key(created_at, new_id).as_str(), new_id)?;
+    Ok(())
+}
+
+/// Outcome of rotating one link
+enum Rotation {
+    /// Moved to the contained slug
+    Rotated(String),
+
+    /// Left in place, with the reason's error code
+    Skipped(&'static str),
+}
+
+/// Moves `id` to a freshly generated slug, dropping every way to reach the old one
+fn rotate_link(write_txn: &WriteTransaction, state: &AppState, id: &str) -> Result<Rotation, redb::Error> {
+    let mut table_main = write_txn.open_table(TABLE_URLS)?;
+    let mut table_aliases = write_txn.open_table(TABLE_ALIASES)?;
+    
+    let Some(mut record) = load_record(&table_main, id)? else {
+        return Ok(Rotation::Skipped("not_found"));
+    };
+    
+    // Password hashes are salted with the slug and cannot be recomputed without the password
+    if record.password_protected {
+        return Ok(Rotation::Skipped("password_protected"));
+    }
+    
+    let mut new_id = None;
+    for attempt in 0..=state.config.slug_max_retries {
+        let candidate = generate_slug(state, &record.original_url, record.ref_id.as_deref(), Utc::now(), attempt);
+        let taken = table_main.get(candidate.as_str())?.is_some() || table_aliases.get(candidate.as_str())?.is_some();
+        if !taken && reservations::claim(write_txn, &candidate, record.ref_id.as_deref())? {
+            new_id = Some(candidate);
+            break;
+        }
+        state.metrics.record_slug_collision();
+    }
+    let Some(new_id) = new_id else {
+        return Ok(Rotation::Skipped("id_taken"));
+    };
+    
+    record.short_url = match record.short_url.strip_suffix(id) {
+        Some(base) => format!("{}{}", base, new_id),
+        None => record.short_url.clone(),
+    };
+    record.id = new_id.clone();
+    
+    table_main.remove(id)?;
+    table_main.insert(new_id.as_str(), state.config.record_format.encode(&record).as_str())?;
+    move_created_index_entry(write_txn, record.created_at, id, &new_id)?;
+    
+    // created_at is kept, so the index entry keeps its key and only changes its slug
+    if let Some(record_ref_id) = &record.ref_id {
+        let index_key = format!("{}:{}", record_ref_id, record.created_at.timestamp_micros());
+        let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+        table_index.insert(index_key.as_str(), new_id.as_str())?;
+    }
+    
+    // Aliases from earlier renames leaked along with the slug, so they go too
+    let stale_aliases = aliases_of(&table_aliases, id)?;
+    for alias in stale_aliases {
+        table_aliases.remove(alias.as_str())?;
+    }
+    
+    replace_destination(write_txn, &record.original_url, id, Some(&new_id))?;
+    history::rename_history(write_txn, id, &new_id)?;
+    visitors::rename_visitors(write_txn, id, &new_id)?;
+    counters::rename_counters(write_txn, id, &new_id)?;
+    #[cfg(feature = "analytics")]
+    rename_link_stats(write_txn, id, &new_id)?;
+    
+    Ok(Rotation::Rotated(new_id))
+}
+
+/// Moves every link of a ref_id to a freshly generated slug
+/// 
+/// Meant for a suspected leak of a tenant's links: unlike a rename, the old
+/// slugs (and aliases left by earlier renames) stop resolving at once.
+/// Clicks, `created_at`, destination history and click statistics move with
+/// each link. Links are moved `ROTATE_BATCH_SIZE` at a time, each batch in
+/// its own write transaction, so a failure part-way leaves earlier batches
+/// rotated.
+/// 
+/// Password-protected links are skipped: their password hash is salted with
+/// the slug and cannot be recomputed without the password. Rename them
+/// individually instead.
+/// 
+/// # Query Parameters
+/// 
+/// - `ref_id` (required) - Reference ID whose links are rotated
+/// 
+/// # Example Request
+/// 
+/// `POST /api/urls/rotate-all?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"ref_id", "rotated": {"old-slug": "new-slug", ...}, "skipped": [{"id", "code"}]}`
+/// - **400 Bad Request** - `ref_id` missing
+pub async fn rotate_all(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<RotateParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "ref_id is required",
+                "code": "missing_ref_id"
+            })),
+        )
+            .into_response());
+    };
+    
+    // Read from the primary: the slugs are about to be written
+    let ids: Vec<String> = {
+        let read_txn = state.begin_primary_read()?;
+        let table_index = read_txn.open_table(TABLE_REF_INDEX)?;
+        let table_main = read_txn.open_table(TABLE_URLS)?;
+        let start_key = format!("{}:", ref_id);
+        let end_key = format!("{}:{{", ref_id);
+        
+        // Duplicate index entries must not rotate a link twice
+        let mut seen = HashSet::new();
+        let mut ids = Vec::new();
+        for entry in table_index.range(start_key.as_str()..end_key.as_str())? {
+            let (_, id) = entry?;
+            let Some(record) = load_record(&table_main, id.value())? else {
+                continue;
+            };
+            if record.ref_id.as_deref() == Some(ref_id.as_str()) && seen.insert(record.id.clone()) {
+                ids.push(record.id);
+            }
+        }
+        ids
+    };
+    
+    let mut rotated = serde_json::Map::new();
+    let mut skipped = Vec::new();
+    for batch in ids.chunks(ROTATE_BATCH_SIZE) {
+        let write_txn = state.begin_write()?;
+        for id in batch {
+            match rotate_link(&write_txn, &state, id)? {
+                Rotation::Rotated(new_id) => {
+                    rotated.insert(id.clone(), json!(new_id));
+                }
+                Rotation::Skipped(code) => skipped.push(json!({ "id": id, "code": code })),
+            }
+        }
+        write_txn.commit()?;
+    }
+    
+    Ok(Json(json!({
+        "ref_id": ref_id,
+        "rotated": rotated,
+        "skipped": skipped,
+    }))
+    .into_response())
+}
+
+/// Disables a link immediately (admin kill switch)
+/// 
+/// Intended for support teams handling abusive links, so no ownership check
+/// is made; the route sits behind the API authorization like every other
+/// admin endpoint. The record is kept, marked inactive with `disabled_at`, and
+/// the action is logged with the request id and the caller's JWT subject, or
+/// the fingerprint of the API key used.
+/// 
+/// # Path Parameters
+/// 
+/// - `id` - The short URL identifier/slug
+/// 
+/// # Request Body (optional)
+/// 
+/// ```json
+/// { "message": "This link was removed for violating our terms" }
+/// ```
+/// 
+/// `message` replaces the link's `disabled_message` shown to visitors.
+/// 
+/// # Response
+/// 
+/// - **200 OK** - Link disabled (or already was); redirects now return 410
+/// - **404 Not Found** - URL does not exist
+pub async fn kill_link(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    api_key: Option<Extension<ApiKey>>,
+    request_id: Option<Extension<RequestId>>,
+    payload: Option<Json<KillRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    let message = payload
+        .and_then(|Json(payload)| payload.message)
+        .filter(|message| !message.is_empty());
+    let request_id = request_id.map(|Extension(RequestId(request_id))| request_id);
+    let actor = match (subject, api_key) {
+        (Some(Extension(AuthSubject(subject))), _) => subject,
+        (None, Some(Extension(api_key))) => format!("api-key:{}", api_key.fingerprint()),
+        (None, None) => "api-key".to_string(),
+    };
+    
+    let write_txn = state.begin_write()?;
+    let record = {
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+        
+        let mut record = match table_main.get(id.as_str())? {
+            Some(guard) => record_format::decode(guard.value())?,
+            None => {
+                return Ok((
+                    StatusCode::NOT_FOUND,
+                    Json(json!({
+                        "error": "URL not found",
+                        "code": "not_found"
+                    })),
+                )
+                    .into_response())
+            }
+        };
+        
+        if !record.killed || message.is_some() {
+            if record.is_active {
+                record.is_active = false;
+                record.disabled_at = Some(Utc::now());
+            }
+            record.killed = true;
+            if message.is_some() {
+                record.disabled_message = message;
+            }
+            let record_value = state.config.record_format.encode(&record);
+            
+            table_main.insert(id.as_str(), record_value.as_str())?;
+        }
+        
+        record
+    };
+    write_txn.commit()?;
+    
+    tracing::warn!(
+        request_id = request_id.as_deref().unwrap_or("-"),
+        actor = actor.as_str(),
+        "Link {} disabled by admin kill switch",
+        id
+    );
+    
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": "Link disabled",
+            "id": id,
+            "disabled_at": record.disabled_at,
+            "disabled_message": record.disabled_message,
+            "request_id": request_id
+        })),
+    )
+        .into_response())
+}
+
+/// Merges a duplicate link into another, combining their clicks
+/// 
+/// The source's `clicks` are added to the target's. The source is then
+/// either repointed at the target's destination with its clicks reset, or,
+/// with `delete_source`, removed and kept as an alias so its slug still
+/// reaches the target. Everything happens in one transaction. Click events
+/// and daily stats stay under their original slugs.
+/// 
+/// # Request Body
+/// 
+/// ```json
+/// { "source_id": "old-promo", "target_id": "promo", "ref_id": "user_123", "delete_source": true }
+/// ```
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"source_id", "target_id", "clicks", "source_deleted"}`, where
+///   `clicks` is the target's new total
+/// - **400 Bad Request** - `source_id` and `target_id` are the same link
+/// - **403 Forbidden** - ref_id does not own both links
+/// - **404 Not Found** - either link does not exist
+pub async fn merge_links(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<MergeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, payload.ref_id);
+    let error = |status: StatusCode, error: &str, code: &str| {
+        (status, Json(json!({ "error": error, "code": code }))).into_response()
+    };
+    
+    if payload.source_id == payload.target_id {
+        return Ok(error(StatusCode::BAD_REQUEST, "A link cannot be merged into itself", "same_link"));
+    }
+    
+    let write_txn = state.begin_write()?;
+    let target = {
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+        let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+        
+        let (Some(mut source), Some(mut target)) = (
+            load_record(&table_main, &payload.source_id)?,
+            load_record(&table_main, &payload.target_id)?,
+        ) else {
+            return Ok(error(StatusCode::NOT_FOUND, "URL not found", "not_found"));
+        };
+        
+        // Same ownership rule as deletion, applied to both links
+        if let Some(request_ref_id) = &ref_id {
+            if source.ref_id.as_ref() != Some(request_ref_id) || target.ref_id.as_ref() != Some(request_ref_id) {
+                return Ok(error(
+                    StatusCode::FORBIDDEN,
+                    "You are not authorized to merge these links",
+                    "forbidden",
+                ));
+            }
+        }
+        
+        target.clicks = target.clicks.saturating_add(source.clicks);
+        let target_value = state.config.record_format.encode(&target);
+        table_main.insert(target.id.as_str(), target_value.as_str())?;
+        
+        let source_index_key = source
+            .ref_id
+            .as_ref()
+            .map(|source_ref_id| format!("{}:{}", source_ref_id, source.created_at.timestamp_micros()));
+        replace_destination(&write_txn, &source.original_url, &source.id, None)?;
+        
+        if payload.delete_source {
+            table_main.remove(source.id.as_str())?;
+            let mut table_created = write_txn.open_table(TABLE_CREATED_INDEX)?;
+            table_created.remove(created_index_key(source.created_at, &source.id).as_str())?;
+            if let Some(index_key) = &source_index_key {
+                table_index.remove(index_key.as_str())?;
+            }
+            if source.password_protected {
+                let mut table_passwords = write_txn.open_table(TABLE_LINK_PASSWORDS)?;
+                table_passwords.remove(source.id.as_str())?;
+            }
+            
+            // The source slug and its own aliases now all lead to the target
+            let mut table_aliases = write_txn.open_table(TABLE_ALIASES)?;
+            let stale_aliases = aliases_of(&table_aliases, &source.id)?;
+            for alias in stale_aliases {
+                table_aliases.insert(alias.as_str(), target.id.as_str())?;
+            }
+            table_aliases.insert(source.id.as_str(), target.id.as_str())?;
+            history::delete_history(&write_txn, &source.id)?;
+            visitors::delete_visitors(&write_txn, &source.id)?;
+            counters::delete_counters(&write_txn, &source.id)?;
+        } else {
+            history::record_change(&write_txn, &source.id, &source.original_url, &target.original_url)?;
+            source.original_url = target.original_url.clone();
+            source.clicks = 0;
+            let source_value = state.config.record_format.encode(&source);
+            table_main.insert(source.id.as_str(), source_value.as_str())?;
+        }
+        
+        target
+    };
+    write_txn.commit()?;
+    
+    Ok(Json(json!({
+        "source_id": payload.source_id,
+        "target_id": target.id,
+        "clicks": target.clicks,
+        "source_deleted": payload.delete_source,
+    }))
+    .into_response())
+}
+
+/// Returns a single link's metadata and click count
+/// 
+/// Avoids scanning `list_urls` to read one record. Aliases of renamed links
+/// are not followed; use the current slug.
+/// 
+/// Clicks are not buffered in memory: `redirect_url` commits each counted
+/// visit before responding, so `clicks` already includes every redirect that
+/// has returned. Only a read replica (`READ_REPLICA_DATABASE_URL`) can lag.
+/// 
+/// # Query Parameters
+/// 
+/// - `ref_id` (optional) - Verified against the link's owner, as for deletion
+/// - `token` (optional) - Preview token for this slug, replacing both the API
+///   credentials and the ownership check (see `preview_token`)
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/abc123/stats?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"id", "original_url", "short_url", "ref_id", "created_at", "clicks", "source", "bundle_members"}`
+/// - **401 Unauthorized** - `token` is invalid, expired or for another link
+/// - **403 Forbidden** - ref_id does not match the owner
+/// - **404 Not Found** - URL does not exist
+pub async fn get_url_stats(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+    
+    // A preview token stands in for ownership; an invalid one is refused outright
+    let granted = match preview_token::check(&state, params.token.as_deref(), &id) {
+        TokenCheck::Absent => false,
+        TokenCheck::Granted => true,
+        TokenCheck::Rejected(response) => return Ok(response),
+    };
+    
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_URLS)?;
+    let record = load_record(&table, &id)?;
+    
+    let Some(record) = record else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    };
+    
+    // Same ownership rule as deletion: only checked when a ref_id is supplied
+    if let Some(request_ref_id) = ref_id.as_ref().filter(|_| !granted) {
+        if record.ref_id.as_ref() != Some(request_ref_id) {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "You are not authorized to view this link",
+                    "code": "forbidden"
+                })),
+            )
+                .into_response());
+        }
+    }
+    
+    Ok(Json(json!({
+        "id": record.id,
+        "original_url": record.original_url,
+        "short_url": record.short_url,
+        "ref_id": record.ref_id,
+        "created_at": record.created_at,
+        "clicks": record.clicks,
+        "source": record.source,
+        "bundle_members": record.bundle_members,
+    }))
+    .into_response())
+}
+
+/// Pauses or resumes a link, flipping `is_active`
+/// 
+/// Unlike the admin kill switch this is owner-driven and reversible: a paused
+/// link answers `410 Gone` until it is toggled back on. Links disabled by the
+/// kill switch stay off.
+/// 
+/// # Query Parameters
+/// 
+/// - `ref_id` (optional) - Verified against the link's owner, as for deletion
+/// 
+/// # Example Request
+/// 
+/// `POST /api/urls/abc123/toggle?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"id": "abc123", "is_active": false, "disabled_at": "..."}`
+/// - **403 Forbidden** - ref_id does not match, or the link was killed by an admin
+/// - **404 Not Found** - URL does not exist
+pub async fn toggle_link(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<ToggleParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+    
+    let write_txn = state.begin_write()?;
+    let record = {
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+        
+        let mut record = match table_main.get(id.as_str())? {
+            Some(guard) => record_format::decode(guard.value())?,
+            None => {
+                return Ok((
+                    StatusCode::NOT_FOUND,
+                    Json(json!({
+                        "error": "URL not found",
+                        "code": "not_found"
+                    })),
+                )
+                    .into_response())
+            }
+        };
+        
+        // Same ownership rule as deletion: only checked when a ref_id is supplied
+        if let Some(request_ref_id) = &ref_id {
+            if record.ref_id.as_ref() != Some(request_ref_id) {
+                return Ok((
+                    StatusCode::FORBIDDEN,
+                    Json(json!({
+                        "error": "You are not authorized to toggle this link",
+                        "code": "forbidden"
+                    })),
+                )
+                    .into_response());
+            }
+        }
+        
+        if record.killed {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "This link was disabled by an administrator",
+                    "code": "link_killed"
+                })),
+            )
+                .into_response());
+        }
+        
+        record.is_active = !record.is_active;
+        record.disabled_at = if record.is_active { None } else { Some(Utc::now()) };
+        let record_value = state.config.record_format.encode(&record);
+        
+        table_main.insert(id.as_str(), record_value.as_str())?;
+        
+        record
+    };
+    write_txn.commit()?;
+    
+    Ok(Json(json!({
+        "id": id,
+        "is_active": record.is_active,
+        "disabled_at": record.disabled_at,
+    }))
+    .into_response())
+}
+
+/// Returns the exact string stored in `TABLE_URLS` for a slug (debug)
+/// 
+/// The value is not deserialized, so operators can inspect corrupt or legacy
+/// records that other endpoints skip or fail on. Reads the primary database,
+/// never the replica.
+/// 
+/// # Response
+/// 
+/// - **200 OK** - The stored value verbatim, as `text/plain`
+/// - **404 Not Found** - Nothing is stored under this slug
+pub async fn raw_record(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let read_txn = state.begin_primary_read()?;
+    let table = read_txn.open_table(TABLE_URLS)?;
+    
+    Ok(match table.get(id.as_str())? {
+        Some(value) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            value.value().to_string(),
+        )
+            .into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response(),
+    })
+}
+
+/// Fill ratio at which `slug_capacity` starts warning
+pub const CAPACITY_WARN_RATIO: f64 = 0.5;
+
+/// Estimates how much of the generated-slug space is used
+/// 
+/// `slug_space` is the number of distinct slugs `SLUG_STRATEGY` can generate
+/// (`62^SLUG_LENGTH` for `random` and `hash`, 102,400 for `words`), or `null`
+/// for `base62`, whose counter never runs out. `used` counts every stored
+/// link, custom ids included, so it slightly overstates usage when many
+/// custom ids are in use. Generated slugs that collide with an existing one
+/// are retried only `SLUG_MAX_RETRIES` times (each counted in the
+/// `slug_collisions` metric), so create failures become likely as
+/// `fill_ratio` grows.
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{slug_length, slug_space, used, remaining, fill_ratio, warning}`,
+///   where `warning` is a message once `fill_ratio` reaches `CAPACITY_WARN_RATIO`
+///   and `null` otherwise. `remaining` and `fill_ratio` are `null` too when
+///   `slug_space` is
+pub async fn slug_capacity(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let read_txn = state.begin_primary_read()?;
+    let table = read_txn.open_table(TABLE_URLS)?;
+    let used = table.len()?;
+    
+    let slug_space = state.slug_generator.space(&state.config);
+    let fill_ratio = slug_space.map(|slug_space| used as f64 / slug_space as f64);
+    let warning = fill_ratio.filter(|fill_ratio| *fill_ratio >= CAPACITY_WARN_RATIO).map(|fill_ratio| {
+        format!(
+            "{:.0}% of the slug space is used; increase SLUG_LENGTH or change SLUG_STRATEGY to avoid collisions",
+            fill_ratio * 100.0
+        )
+    });
+    
+    Ok(Json(json!({
+        "slug_length": state.config.slug_length,
+        "slug_space": slug_space,
+        "used": used,
+        "remaining": slug_space.map(|slug_space| slug_space.saturating_sub(used)),
+        "fill_ratio": fill_ratio,
+        "warning": warning,
+    })))
+}
+
+/// Links returned by `leaderboard` when `limit` is not given
+pub const DEFAULT_LEADERBOARD_LIMIT: usize = 10;
+
+/// Most links `leaderboard` returns
+pub const MAX_LEADERBOARD_LIMIT: usize = 100;
+
+/// The `limit` links with the most clicks, most clicked first
+/// 
+/// Takes `(slug, clicks, destination)` entries and keeps only a min-heap of
+/// the best `limit` seen so far, so memory stays bounded however many links
+/// are scanned. Ties are broken by slug, ascending.
+pub fn top_by_clicks<T>(links: impl IntoIterator<Item = (String, u64, T)>, limit: usize) -> Vec<(String, u64, T)>
+where
+    T: Ord,
+{
+    let mut heap = BinaryHeap::with_capacity(limit + 1);
+    for (id, clicks, extra) in links {
+        heap.push(Reverse((clicks, Reverse(id), extra)));
+        if heap.len() > limit {
+            heap.pop();
+        }
+    }
+    
+    // Ascending `Reverse` order is the best entry first
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((clicks, Reverse(id), extra))| (id, clicks, extra))
+        .collect()
+}
+
+/// Lists the most clicked links across all owners
+/// 
+/// Scans every link once, keeping a bounded heap of the leaders (see
+/// `top_by_clicks`) instead of sorting the whole table.
+/// 
+/// # Query Parameters
+/// 
+/// - `limit` (optional) - Number of links, default 10, max 100
+/// 
+/// # Example Request
+/// 
+/// `GET /api/admin/leaderboard?limit=3`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"data": [{"id": "abc123", "destination_host": "example.com", "clicks": 42}]}`
+pub async fn leaderboard(
+    State(state): State<AppState>,
+    Query(params): Query<LeaderboardParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT).clamp(1, MAX_LEADERBOARD_LIMIT);
+    
+    let leaders = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+        // Stop at the first unreadable record and report it after ranking
+        let mut failure = None;
+        let links = table
+            .iter()?
+            .map_while(|entry| {
+                let decoded = entry
+                    .map_err(AppError::from)
+                    .and_then(|(_, value)| Ok(record_format::decode(value.value())?));
+                decoded.map_err(|err| failure = Some(err)).ok()
+            })
+            .map(|record| (record.id, record.clicks, record.original_url));
+        let leaders = top_by_clicks(links, limit);
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        leaders
+    };
+    
+    let data: Vec<serde_json::Value> = leaders
+        .into_iter()
+        .map(|(id, clicks, destination)| {
+            json!({
+                "id": id,
+                "destination_host": redirect_log::destination_host(&destination),
+                "clicks": clicks,
+            })
+        })
+        .collect();
+    
+    Ok(Json(json!({ "data": data })))
+}
+
+/// Links returned by `oldest_links` when `limit` is not given
+pub const DEFAULT_OLDEST_LIMIT: usize = 50;
+
+/// Most links `oldest_links` returns
+pub const MAX_OLDEST_LIMIT: usize = 1000;
+
+/// Lists the oldest links across all owners, for retention cleanup
+/// 
+/// Reads `TABLE_CREATED_INDEX` forward and stops after `limit` links, so no
+/// sort or full scan is needed. Index entries whose link is gone are skipped.
+/// 
+/// # Query Parameters
+/// 
+/// - `limit` (optional) - Number of links, default 50, max 1000
+/// 
+/// # Example Request
+/// 
+/// `GET /api/admin/oldest?limit=2`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"data": [{"id", "original_url", "ref_id", "created_at", "clicks", "last_accessed_at"}]}`,
+///   oldest first
+pub async fn oldest_links(
+    State(state): State<AppState>,
+    Query(params): Query<OldestParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_OLDEST_LIMIT).clamp(1, MAX_OLDEST_LIMIT);
+    
+    let read_txn = state.begin_read()?;
+    let table_created = read_txn.open_table(TABLE_CREATED_INDEX)?;
+    let table_main = read_txn.open_table(TABLE_URLS)?;
+    let mut records = Vec::new();
+    for entry in table_created.iter()? {
+        if records.len() == limit {
+            break;
+        }
+        let (_, id) = entry?;
+        records.extend(load_record(&table_main, id.value())?);
+    }
+    let data: Vec<serde_json::Value> = records
+        .into_iter()
+        .map(|record| {
+            json!({
+                "id": record.id,
+                "original_url": record.original_url,
+                "ref_id": record.ref_id,
+                "created_at": record.created_at,
+                "clicks": record.clicks,
+                "last_accessed_at": record.last_accessed_at,
+            })
+        })
+        .collect();
+    
+    Ok(Json(json!({ "data": data })))
+}
+
+/// Lists the links a ref_id created on one UTC calendar day
+/// 
+/// Reads only the day's slice of the ref_id index: the timestamp half of the
+/// `{ref_id}:{created_at micros}` keys is bounded to the day's microsecond
+/// range. Microsecond timestamps have the same number of digits for every
+/// date between 2001 and 2286, so string order matches time order.
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/by-date?ref_id=user_123&date=2026-01-17`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"ref_id", "date", "total", "data": [...]}`, oldest first
+/// - **400 Bad Request** - `ref_id` missing, or `date` missing or not `YYYY-MM-DD`
+pub async fn list_urls_by_date(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<ByDateParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let bad_request = |error: &str, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": error,
+                "code": code
+            })),
+        )
+            .into_response()
+    };
+    
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok(bad_request("ref_id is required", "missing_ref_id"));
+    };
+    
+    let Some(date) = params
+        .date
+        .as_deref()
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+    else {
+        return Ok(bad_request("date must be formatted as YYYY-MM-DD", "invalid_date"));
+    };
+    
+    // [start of day, start of next day) in microseconds
+    let day_start = date.and_time(NaiveTime::MIN).and_utc().timestamp_micros();
+    let day_end = day_start + Duration::days(1).num_microseconds().unwrap();
+    let start_key = format!("{}:{}", ref_id, day_start);
+    let end_key = format!("{}:{}", ref_id, day_end);
+    
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_REF_INDEX)?;
+    let table_main = read_txn.open_table(TABLE_URLS)?;
+    
+    let mut seen_ids = HashSet::new();
+    let mut links: Vec<UrlRecord> = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (_, id) = entry?;
+        if let Some(record) = load_record(&table_main, id.value())? {
+            if seen_ids.insert(record.id.clone()) {
+                links.push(record);
+            }
+        }
+    }
+    
+    let mut body = json!({
+        "ref_id": ref_id,
+        "date": date.to_string(),
+        "total": links.len(),
+        "data": links,
+    });
+    params.time_format.apply(&mut body);
+    
+    Ok(Json(body).into_response())
+}
+
+/// Lists the distinct destinations a ref_id has shortened, with link counts
+/// 
+/// Scans every index entry of the ref_id and deserializes each record, so the
+/// cost grows linearly with the tenant's link count; there is no per-
+/// destination index. Intended for occasional audits, not hot paths.
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/destinations?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"ref_id", "total_links", "destinations": [{"url", "count"}]}`,
+///   most-linked destinations first, ties ordered by url
+/// - **400 Bad Request** - `ref_id` missing
+pub async fn list_destinations(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<DestinationsParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "ref_id is required",
+                "code": "missing_ref_id"
+            })),
+        )
+            .into_response());
+    };
+    
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_REF_INDEX)?;
+    let table_main = read_txn.open_table(TABLE_URLS)?;
+    let start_key = format!("{}:", ref_id);
+    let end_key = format!("{}:{{", ref_id);
+    
+    // Duplicate index entries must not inflate the counts
+    let mut seen_ids = HashSet::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (_, id) = entry?;
+        let Some(record) = load_record(&table_main, id.value())? else {
+            continue;
+        };
+        if seen_ids.insert(record.id) {
+            *counts.entry(record.original_url).or_default() += 1;
+        }
+    }
+    
+    let mut destinations: Vec<(String, usize)> = counts.into_iter().collect();
+    destinations.sort_by(|(url_a, count_a), (url_b, count_b)| count_b.cmp(count_a).then_with(|| url_a.cmp(url_b)));
+    
+    Ok(Json(json!({
+        "ref_id": ref_id,
+        "total_links": seen_ids.len(),
+        "destinations": destinations
+            .into_iter()
+            .map(|(url, count)| json!({ "url": url, "count": count }))
+            .collect::<Vec<_>>(),
+    }))
+    .into_response())
+}
+
+/// Lists the distinct tags across a ref_id's links, e.g. for tag filters in a UI
+/// 
+/// There is no tag index, so like `list_destinations` this scans and
+/// deserializes every link of the ref_id. Tags are compared exactly; they
+/// are already normalized per link when stored (see `tags`).
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/tags?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"ref_id", "tags": ["email", "spring"]}`, sorted
+/// - **400 Bad Request** - `ref_id` missing
+pub async fn list_tags(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<TagsParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "ref_id is required",
+                "code": "missing_ref_id"
+            })),
+        )
+            .into_response());
+    };
+    
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_REF_INDEX)?;
+    let table_main = read_txn.open_table(TABLE_URLS)?;
+    let start_key = format!("{}:", ref_id);
+    let end_key = format!("{}:{{", ref_id);
+    
+    let records = table
+        .range(start_key.as_str()..end_key.as_str())?
+        .map(|entry| load_record(&table_main, entry?.1.value()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let tags: BTreeSet<String> = records.into_iter().flatten().flat_map(|record| record.tags).collect();
+    
+    Ok(Json(json!({
+        "ref_id": ref_id,
+        "tags": tags,
+    }))
+    .into_response())
+}
+
+/// Renders a link's `short_url` as a PNG QR code, e.g. for printed signage
+/// 
+/// # Query Parameters
+/// 
+/// - `size` (optional) - Pixels per QR module, clamped to 1..=32 (default: 8)
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/abc123/qr?size=12`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `image/png` QR code
+/// - **404 Not Found** - URL does not exist
+pub async fn link_qr(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<QrParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let record = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+        load_record(&table, &id)?
+    };
+    
+    let Some(record) = record else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    };
+    
+    let module_size = params
+        .size
+        .unwrap_or(DEFAULT_MODULE_SIZE)
+        .clamp(MIN_MODULE_SIZE, MAX_MODULE_SIZE);
+    
+    // QR rendering is CPU-bound, so keep it off the async worker threads
+    let png = tokio::task::spawn_blocking(move || render_png(&record.short_url, module_size)).await??;
+    
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+/// Renders a link's `short_url` as an SVG QR code, which scales without blur for print
+/// 
+/// # Query Parameters
+/// 
+/// - `color` (optional) - Color of the dark modules as 3 or 6 hex digits,
+///   with or without `#` (default: `#000000`)
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/abc123/qr.svg?color=336699`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `image/svg+xml` QR code
+/// - **400 Bad Request** - `color` is not a hex color
+/// - **404 Not Found** - URL does not exist
+pub async fn link_qr_svg(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<QrSvgParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let color = match params.color.as_deref() {
+        None => DEFAULT_SVG_COLOR.to_string(),
+        Some(value) => match parse_hex_color(value) {
+            Some(color) => color,
+            None => {
+                return Ok((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "color must be 3 or 6 hex digits, e.g. 336699",
+                        "code": "invalid_color"
+                    })),
+                )
+                    .into_response());
+            }
+        },
+    };
+    
+    let record = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+        load_record(&table, &id)?
+    };
+    
+    let Some(record) = record else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    };
+    
+    let svg = tokio::task::spawn_blocking(move || render_svg(&record.short_url, &color)).await??;
+    
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+/// Exports QR codes for all links of a ref_id as a ZIP archive
+/// 
+/// Each link's `short_url` is rendered as a PNG named `{slug}.png`. Intended
+/// for print campaigns where a tenant needs every code at once.
+/// 
+/// # Query Parameters
+/// 
+/// - `ref_id` (required) - Reference ID whose links are exported
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/qr-bundle?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `application/zip` archive with one PNG per link
+/// - **400 Bad Request** - `ref_id` missing, or the tenant has more links than
+///   `QR_BUNDLE_MAX_LINKS` allows in one archive
+pub async fn qr_bundle(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<QrBundleParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "ref_id is required",
+                "code": "missing_ref_id"
+            })),
+        )
+            .into_response());
+    };
+    
+    let max_links = state.config.qr_bundle_max_links;
+    
+    // Collect (slug, short_url) pairs from the ref_id index
+    let links: Vec<(String, String)> = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_REF_INDEX)?;
+        let table_main = read_txn.open_table(TABLE_URLS)?;
+        let start_key = format!("{}:", ref_id);
+        let end_key = format!("{}:{{", ref_id);
+        
+        let mut links = Vec::new();
+        // Read one past the limit so oversized tenants can be detected
+        for entry in table.range(start_key.as_str()..end_key.as_str())?.take(max_links + 1) {
+            let (_, id) = entry?;
+            if let Some(record) = load_record(&table_main, id.value())? {
+                links.push((record.id, record.short_url));
+            }
+        }
+        links
+    };
+    
+    if links.len() > max_links {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("Too many links to bundle; the maximum is {}", max_links),
+                "code": "bundle_too_large",
+                "max": max_links
+            })),
+        )
+            .into_response());
+    }
+    
+    // QR rendering is CPU-bound, so keep it off the async worker threads
+    let archive = tokio::task::spawn_blocking(move || render_zip_bundle(&links, DEFAULT_MODULE_SIZE)).await??;
+    
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}-qr-codes.zip\"", ref_id),
+            ),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+/// Header row of `export_csv`
+const EXPORT_CSV_HEADER: &str = "id,original_url,short_url,created_at,clicks\n";
+
+/// Quotes a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Reads up to `EXPORT_BATCH_SIZE` of a ref_id's links after `start` as CSV rows
+/// 
+/// Returns the rows and the last index key read, or `None` once the range is exhausted.
+fn export_batch(state: &AppState, start: Bound<&str>, end_key: &str) -> Result<Option<(String, String)>, redb::Error> {
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_REF_INDEX)?;
+    let table_main = read_txn.open_table(TABLE_URLS)?;
+    
+    let mut rows = String::new();
+    let mut last_key = None;
+    for entry in table.range::<&str>((start, Bound::Excluded(end_key)))?.take(EXPORT_BATCH_SIZE) {
+        let (key, id) = entry?;
+        last_key = Some(key.value().to_string());
+        if let Some(record) = load_record(&table_main, id.value())? {
+            rows.push_str(&format!(
+                "{},{},{},{},{}\n",
+                csv_field(&record.id),
+                csv_field(&record.original_url),
+                csv_field(&record.short_url),
+                record.created_at.to_rfc3339(),
+                record.clicks,
+            ));
+        }
+    }
+    
+    Ok(last_key.map(|key| (rows, key)))
+}
+
+/// Exports all links of a ref_id as CSV
+/// 
+/// Rows are in creation order, under the header
+/// `id,original_url,short_url,created_at,clicks`. The body is streamed,
+/// reading `EXPORT_BATCH_SIZE` links per read transaction, so memory stays
+/// flat for large tenants; links created or deleted mid-export may or may
+/// not appear.
+/// 
+/// # Query Parameters
+/// 
+/// - `ref_id` (required) - Reference ID whose links are exported
+/// 
+/// # Example Request
+/// 
+/// `GET /api/urls/export?ref_id=user_123`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `text/csv` with one row per link
+/// - **400 Bad Request** - `ref_id` missing
+pub async fn export_csv(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<ExportParams>,
+) -> impl IntoResponse {
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "ref_id is required",
+                "code": "missing_ref_id"
+            })),
+        )
+            .into_response();
+    };
+    
+    let end_key = format!("{}:{{", ref_id);
+    let start = Bound::Included(format!("{}:", ref_id));
+    let rows = stream::unfold(Some(start), move |start| {
+        let state = state.clone();
+        let end_key = end_key.clone();
+        async move {
+            let start = start?;
+            // The status line is already sent, so a storage error can only cut the body short
+            match export_batch(&state, start.as_ref().map(String::as_str), &end_key) {
+                Ok(Some((rows, last_key))) => Some((Ok(rows), Some(Bound::Excluded(last_key)))),
+                Ok(None) => None,
+                Err(err) => {
+                    tracing::error!("Aborting CSV export: {}", err);
+                    Some((Err(err), None))
+                }
+            }
+        }
+    });
+    let body = stream::once(async { Ok(EXPORT_CSV_HEADER.to_string()) }).chain(rows);
+    
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}-links.csv\"", ref_id),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Reports whether API requests need credentials, and which kind
+/// 
+/// Public, so clients can configure themselves before they have any
+/// credentials. Only the mode is revealed, never a key or secret.
+/// 
+/// # Example Request
+/// 
+/// `GET /api/auth/required`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"auth_required": true, "mode": "secret"}`; `mode` is
+///   `secret` (`AUTHORIZATION` keys), `jwt` (`AUTH_MODE=jwt`) or `none`
+pub async fn auth_required() -> impl IntoResponse {
+    let mode = AuthMode::current();
+    Json(json!({
+        "auth_required": mode != AuthMode::None,
+        "mode": mode,
+    }))
+}
+
+/// Confirms the caller's credentials are accepted
+/// 
+/// Sits behind `auth_middleware` like every other API route, so reaching the
+/// handler at all means the credentials are valid (or auth is disabled).
+/// Lets operators probe their setup without creating a link.
+/// 
+/// # Example Request
+/// 
+/// `GET /api/auth/check`
+/// 
+/// # Response
+/// 
+/// - **200 OK** - `{"authenticated": true}`, plus `subject` in JWT mode
+/// - **401 Unauthorized** - Rejected by `auth_middleware`
+pub async fn auth_check(subject: Option<Extension<AuthSubject>>) -> impl IntoResponse {
+    match subject {
+        Some(Extension(AuthSubject(subject))) => Json(json!({
+            "authenticated": true,
+            "subject": subject,
+        })),
+        None => Json(json!({ "authenticated": true })),
+    }
+}
@@ -0,0 +1,200 @@
+//! Destination change history and the per-link audit trail
+//!
+//! Whenever a link starts pointing somewhere else, the old and new
+//! destinations are appended to `TABLE_LINK_HISTORY` under `{id}:{micros}`,
+//! in the same transaction as the change. The history follows renames and
+//! is dropped with the link.
+//!
+//! `GET /api/urls/{id}/audit` combines it with the record and, with the
+//! `analytics` feature, the link's daily counters and newest click events.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use redb::{ReadTransaction, ReadableTable, WriteTransaction};
+use serde_json::json;
+
+use crate::database::{load_record, AppState, TABLE_LINK_HISTORY, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::effective_ref_id;
+use crate::middleware::{AdminAccess, AuthSubject};
+use crate::model::{AuditParams, DestinationChange};
+
+/// Click events included in an audit when `events_limit` is omitted
+#[cfg(feature = "analytics")]
+pub const DEFAULT_AUDIT_EVENTS: usize = 50;
+
+/// Most click events a single audit may include
+#[cfg(feature = "analytics")]
+pub const MAX_AUDIT_EVENTS: usize = 500;
+
+/// Appends a destination change of link `id` to its history
+pub fn record_change(write_txn: &WriteTransaction, id: &str, from: &str, to: &str) -> Result<(), redb::Error> {
+    let change = DestinationChange {
+        changed_at: Utc::now(),
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    let key = format!("{}:{}", id, change.changed_at.timestamp_micros());
+
+    let mut table = write_txn.open_table(TABLE_LINK_HISTORY)?;
+    table.insert(key.as_str(), serde_json::to_string(&change).unwrap().as_str())?;
+    Ok(())
+}
+
+/// Returns the `(key, change)` pairs of link `id`, oldest first
+///
+/// Slugs may contain ':', so entries of longer slugs sharing the `{id}:`
+/// prefix are skipped by checking the part before the last ':'.
+fn entries<T: ReadableTable<&'static str, &'static str>>(
+    table: &T,
+    id: &str,
+) -> Result<Vec<(String, DestinationChange)>, redb::Error> {
+    let start_key = format!("{}:", id);
+    let end_key = format!("{}:{{", id);
+
+    let mut changes = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (key, value) = entry?;
+        if key.value().rsplit_once(':').is_some_and(|(change_id, _)| change_id == id) {
+            if let Ok(change) = serde_json::from_str(value.value()) {
+                changes.push((key.value().to_string(), change));
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Returns every destination change of link `id`, oldest first
+pub fn destination_history(read_txn: &ReadTransaction, id: &str) -> Result<Vec<DestinationChange>, redb::Error> {
+    let table = read_txn.open_table(TABLE_LINK_HISTORY)?;
+    Ok(entries(&table, id)?.into_iter().map(|(_, change)| change).collect())
+}
+
+/// Moves the history of a renamed link from `from` to `to`
+pub fn rename_history(write_txn: &WriteTransaction, from: &str, to: &str) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_LINK_HISTORY)?;
+    for (key, change) in entries(&table, from)? {
+        table.remove(key.as_str())?;
+        let key = format!("{}:{}", to, change.changed_at.timestamp_micros());
+        table.insert(key.as_str(), serde_json::to_string(&change).unwrap().as_str())?;
+    }
+    Ok(())
+}
+
+/// Drops the history of a deleted link
+pub fn delete_history(write_txn: &WriteTransaction, id: &str) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_LINK_HISTORY)?;
+    for (key, _) in entries(&table, id)? {
+        table.remove(key.as_str())?;
+    }
+    Ok(())
+}
+
+/// Returns everything known about one link, for investigations
+///
+/// # Query Parameters
+///
+/// - `ref_id` (optional) - Verified against the link's owner, as for
+///   deletion; only callers with `AdminAccess` may omit it to audit any link
+/// - `events_limit` (optional) - Newest click events included, max 500
+///   (default: 50)
+///
+/// # Example Request
+///
+/// `GET /api/urls/abc123/audit?ref_id=user_123`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "record": { "id": "abc123", "original_url": "...", ... },
+///   "history": [{ "changed_at": "...", "from": "...", "to": "..." }],
+///   "clicks": { "total": 42, "daily": [{ "date": "2026-01-17", "clicks": 5 }] },
+///   "recent_events": [{ "timestamp": "...", "referrer": null, ... }]
+/// }
+/// ```
+///
+/// `history` is oldest first and `recent_events` newest first. Without the
+/// `analytics` feature, `clicks` only carries `total` and `recent_events` is
+/// absent.
+///
+/// - **200 OK** - The consolidated audit trail
+/// - **403 Forbidden** - ref_id does not match the owner, or is omitted
+///   without admin credentials
+/// - **404 Not Found** - URL does not exist
+pub async fn link_audit(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    admin: Option<Extension<AdminAccess>>,
+    Query(params): Query<AuditParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let ref_id = effective_ref_id(subject, params.ref_id);
+    if ref_id.is_none() && admin.is_none() {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "Admin credentials are required to audit without a ref_id",
+                "code": "admin_required"
+            })),
+        )
+            .into_response());
+    }
+
+    let read_txn = state.begin_read()?;
+    let table = read_txn.open_table(TABLE_URLS)?;
+    let record = load_record(&table, &id)?;
+
+    let Some(record) = record else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "URL not found",
+                "code": "not_found"
+            })),
+        )
+            .into_response());
+    };
+
+    if let Some(request_ref_id) = &ref_id {
+        if record.ref_id.as_ref() != Some(request_ref_id) {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "You are not authorized to audit this link",
+                    "code": "forbidden"
+                })),
+            )
+                .into_response());
+        }
+    }
+
+    let history = destination_history(&read_txn, &id)?;
+    let body = json!({
+        "record": record,
+        "history": history,
+        "clicks": { "total": record.clicks },
+    });
+
+    #[cfg(feature = "analytics")]
+    let body = {
+        use crate::analytics::{daily_clicks, recent_events};
+
+        let mut body = body;
+        let limit = params.events_limit.unwrap_or(DEFAULT_AUDIT_EVENTS).min(MAX_AUDIT_EVENTS);
+        let daily: Vec<_> = daily_clicks(&state, &id)?
+            .into_iter()
+            .map(|(day, clicks)| json!({ "date": day.to_string(), "clicks": clicks }))
+            .collect();
+        body["clicks"]["daily"] = json!(daily);
+        body["recent_events"] = json!(recent_events(&state, &id, limit)?);
+        body
+    };
+
+    Ok(Json(body).into_response())
+}
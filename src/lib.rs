@@ -2,8 +2,34 @@
 //! 
 //! This module exposes internal components for testing and potential library usage.
 
+#[cfg(feature = "analytics")]
+pub mod analytics;
+pub mod config;
+pub mod counters;
+pub mod creation_stats;
 pub mod database;
+pub mod dedup;
+pub mod error;
+pub mod geoip;
 pub mod handler;
+pub mod history;
+pub mod linkcheck;
+pub mod metrics;
 pub mod model;
+pub mod pages;
+pub mod password;
+pub mod preferences;
+pub mod preview_token;
+pub mod qr;
+pub mod ratelimit;
+pub mod record_format;
+pub mod redirect_log;
+pub mod reservations;
 pub mod route;
+pub mod slug;
+pub mod slug_generator;
+pub mod tags;
+pub mod visitors;
+pub mod webhook;
 pub mod middleware;
+pub mod migrations;
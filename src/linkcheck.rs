@@ -0,0 +1,183 @@
+//! Reachability checks for link destinations
+//!
+//! Destinations are probed with `HEAD` requests through the shared
+//! `AppState::http_client`, which applies `VALIDATE_TIMEOUT_SECS` to each one.
+//! Redirects are followed here rather than by the client, up to
+//! `MAX_REDIRECT_HOPS`, stopping early when a URL repeats.
+
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::database::{AppState, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::batch_too_large;
+use crate::model::ValidateRequest;
+use crate::record_format;
+
+/// Outcome of checking a single link's destination
+#[derive(Debug, Serialize)]
+pub struct DestinationStatus {
+    /// HTTP status returned by the destination, if it answered at all
+    pub status: Option<u16>,
+
+    /// Whether the destination answered with a non-error (< 400) status
+    pub reachable: bool,
+
+    /// Why the check failed: `not_found`, `timeout`, `unreachable`,
+    /// `invalid_url` or `redirect_loop`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
+
+    /// Set when the hop limit was reached while still being redirected
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl DestinationStatus {
+    fn failed(error: &'static str) -> Self {
+        DestinationStatus { status: None, reachable: false, error: Some(error), truncated: false }
+    }
+}
+
+/// Sends `HEAD` requests to `url`, following up to `max_hops` redirects
+/// 
+/// The reported status is that of the last response received. A redirect to
+/// an already visited URL stops the chain with `redirect_loop`; running out of
+/// hops while still being redirected marks the result `truncated`. Neither
+/// counts as reachable.
+pub async fn check_destination(client: &reqwest::Client, url: &str, max_hops: usize) -> DestinationStatus {
+    let Ok(mut current) = reqwest::Url::parse(url) else {
+        return DestinationStatus::failed("invalid_url");
+    };
+    let mut visited = HashSet::new();
+    let mut hops = 0;
+
+    loop {
+        visited.insert(current.clone());
+
+        let response = match client.head(current.clone()).send().await {
+            Ok(response) => response,
+            Err(err) if err.is_timeout() => return DestinationStatus::failed("timeout"),
+            Err(_) => return DestinationStatus::failed("unreachable"),
+        };
+        let status = response.status();
+
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|location| current.join(location).ok());
+
+        let next = match location {
+            Some(next) if status.is_redirection() => next,
+            _ => {
+                return DestinationStatus {
+                    status: Some(status.as_u16()),
+                    reachable: !(status.is_client_error() || status.is_server_error()),
+                    error: None,
+                    truncated: false,
+                }
+            }
+        };
+
+        if visited.contains(&next) {
+            return DestinationStatus {
+                status: Some(status.as_u16()),
+                reachable: false,
+                error: Some("redirect_loop"),
+                truncated: false,
+            };
+        }
+        if hops == max_hops {
+            return DestinationStatus {
+                status: Some(status.as_u16()),
+                reachable: false,
+                error: None,
+                truncated: true,
+            };
+        }
+
+        hops += 1;
+        current = next;
+    }
+}
+
+/// Checks whether the destinations of a batch of links are reachable
+/// 
+/// Destinations are probed concurrently, at most `VALIDATE_CONCURRENCY` at a
+/// time, each bounded by `VALIDATE_TIMEOUT_SECS`. Redirects are followed up
+/// to `MAX_REDIRECT_HOPS`.
+/// 
+/// # Request Body
+/// 
+/// ```json
+/// { "ids": ["abc123", "gone42", "missing"] }
+/// ```
+/// 
+/// # Response
+/// 
+/// ```json
+/// {
+///   "abc123": { "status": 200, "reachable": true },
+///   "gone42": { "status": 404, "reachable": false },
+///   "hopper": { "status": 302, "reachable": false, "truncated": true },
+///   "missing": { "status": null, "reachable": false, "error": "not_found" }
+/// }
+/// ```
+/// 
+/// - **200 OK** - One entry per requested id
+/// - **400 Bad Request** - More than `VALIDATE_MAX_BATCH` or `MAX_BATCH_SIZE` ids
+pub async fn validate_destinations(
+    State(state): State<AppState>,
+    Json(payload): Json<ValidateRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if let Some(response) = batch_too_large(&state.config, payload.ids.len(), Some(state.config.validate_max_batch)) {
+        return Ok(response);
+    }
+
+    let mut results = BTreeMap::new();
+    let mut destinations = Vec::new();
+    {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+
+        for id in payload.ids {
+            if results.contains_key(&id) {
+                continue;
+            }
+            let record = table
+                .get(id.as_str())?
+                .and_then(|value| record_format::decode(value.value()).ok());
+
+            if let Some(record) = record {
+                destinations.push((id.clone(), record.original_url));
+            }
+            // Placeholder until the check completes; also dedupes repeated ids
+            results.insert(id, DestinationStatus::failed("not_found"));
+        }
+    }
+
+    let permits = Arc::new(Semaphore::new(state.config.validate_concurrency.max(1)));
+    let max_hops = state.config.max_redirect_hops;
+    let mut checks = JoinSet::new();
+    for (id, url) in destinations {
+        let client = state.http_client.clone();
+        let permits = permits.clone();
+        checks.spawn(async move {
+            let _permit = permits.acquire_owned().await.unwrap();
+            let status = check_destination(&client, &url, max_hops).await;
+            (id, status)
+        });
+    }
+
+    while let Some(joined) = checks.join_next().await {
+        let (id, status) = joined?;
+        results.insert(id, status);
+    }
+
+    Ok(Json(results).into_response())
+}
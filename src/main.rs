@@ -5,19 +5,46 @@
 //! - Initializes the database
 //! - Starts the HTTP server with graceful shutdown support
 
-use std::sync::Arc;
 use tokio::signal;
 use tokio::net::TcpListener;
+use tokio::sync::watch;
 use dotenvy::dotenv;
 use tower_http::trace::TraceLayer;
 use std::env;
+use std::net::SocketAddr;
 
 // Module declarations
+#[cfg(feature = "analytics")]
+mod analytics;
+mod config;
+mod counters;
+mod creation_stats;
 mod database;
+mod dedup;
+mod error;
+mod geoip;
 mod handler;
+mod history;
+mod linkcheck;
+mod metrics;
 mod model;
+mod pages;
+mod password;
+mod preferences;
+mod preview_token;
+mod qr;
+mod ratelimit;
+mod record_format;
+mod redirect_log;
+mod reservations;
 mod route;
+mod slug;
+mod slug_generator;
+mod tags;
+mod visitors;
+mod webhook;
 mod middleware;
+mod migrations;
 
 use database::{init_db, AppState};
 use route::create_app;
@@ -35,6 +62,9 @@ use route::create_app;
 /// 
 /// - `PORT` - Server port number (default: 8080)
 /// - `DATABASE_URL` - Path to database file (default: "data.db")
+/// - `READ_REPLICA_DATABASE_URL` - Optional read replica file (reads may be stale)
+/// 
+/// See `config::Config::from_env` for the remaining runtime settings.
 #[tokio::main]
 async fn main() {
     // Load environment variables from .env file if it exists
@@ -55,9 +85,29 @@ async fn main() {
     let db = init_db(&db_name).expect("Failed to initialize database");
     
     // Create application state with thread-safe database reference
-    let state = AppState {
-        db: Arc::new(db),
-    };
+    let mut state = AppState::new(db);
+    
+    // Re-encode records left in the other format after a RECORD_FORMAT change
+    let converted = record_format::convert_records(&state.db, state.config.record_format)
+        .expect("Failed to convert stored records");
+    if converted > 0 {
+        tracing::info!("Converted {} records to {:?}", converted, state.config.record_format);
+    }
+    
+    // Optionally serve reads from a replica file kept in sync externally
+    let replica_name = env::var("READ_REPLICA_DATABASE_URL").ok().filter(|path| !path.is_empty());
+    if let Some(replica_name) = &replica_name {
+        let replica = init_db(replica_name).expect("Failed to initialize read replica database");
+        state = state.with_read_replica(replica);
+    }
+    
+    // Periodically delete expired slug reservations until shutdown
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let sweeper = reservations::spawn_sweeper(
+        state.db.clone(),
+        state.config.reservation_sweep_interval,
+        shutdown_rx,
+    );
     
     // Create the Axum router with all routes configured
     let app = create_app(state).layer(TraceLayer::new_for_http());
@@ -69,13 +119,20 @@ async fn main() {
     // Print startup information
     println!("🚀 Server running at http://localhost:{}", port);
     println!("📂 Using database: {}", db_name);
+    if let Some(replica_name) = &replica_name {
+        println!("📖 Serving reads from replica: {}", replica_name);
+    }
 
     // Start the server with graceful shutdown support
     // The server will continue running until it receives SIGTERM or SIGINT
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
+    
+    // Stop background tasks before the database is dropped
+    let _ = shutdown_tx.send(true);
+    let _ = sweeper.await;
 }
 
 /// Handles graceful shutdown signals
@@ -0,0 +1,96 @@
+//! In-process operational counters
+//!
+//! Counters live in memory for the lifetime of the process and reset on
+//! restart; they are meant for dashboards polling `GET /api/admin/metrics`,
+//! not for accounting.
+
+use axum::{extract::State, response::IntoResponse, Json};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::database::AppState;
+
+/// Counters shared by every handler through `AppState::metrics`
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Redirects served without recording the click because
+    /// `MAX_CONCURRENT_REDIRECTS` counting slots were all busy
+    dropped_click_counts: AtomicU64,
+
+    /// Generated slugs found already taken, each one retried or failing a create
+    slug_collisions: AtomicU64,
+
+    /// Read transactions opened while serving requests
+    read_transactions: AtomicU64,
+
+    /// Write transactions opened while serving requests, retries included
+    write_transactions: AtomicU64,
+}
+
+impl Metrics {
+    /// Notes a redirect whose click was not counted under load
+    pub fn record_dropped_click(&self) {
+        self.dropped_click_counts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of clicks dropped since startup
+    pub fn dropped_click_counts(&self) -> u64 {
+        self.dropped_click_counts.load(Ordering::Relaxed)
+    }
+
+    /// Notes a generated slug that was already taken
+    pub fn record_slug_collision(&self) {
+        self.slug_collisions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of generated slugs found taken since startup
+    ///
+    /// A rising rate means the slug space is filling (see `/api/admin/capacity`).
+    pub fn slug_collisions(&self) -> u64 {
+        self.slug_collisions.load(Ordering::Relaxed)
+    }
+
+    /// Notes a read transaction opened (see `AppState::begin_read`)
+    pub fn record_read_transaction(&self) {
+        self.read_transactions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of read transactions opened since startup
+    pub fn read_transactions(&self) -> u64 {
+        self.read_transactions.load(Ordering::Relaxed)
+    }
+
+    /// Notes a write transaction opened (see `AppState::begin_write`)
+    pub fn record_write_transaction(&self) {
+        self.write_transactions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of write transactions opened since startup
+    ///
+    /// Every counted redirect writes once, so compare it with the click rate
+    /// to see how much of the write load is click counting.
+    pub fn write_transactions(&self) -> u64 {
+        self.write_transactions.load(Ordering::Relaxed)
+    }
+}
+
+/// Returns the current value of every counter
+///
+/// # Example Request
+///
+/// `GET /api/admin/metrics`
+///
+/// # Response
+///
+/// - **200 OK** - `{"dropped_click_counts": 0, "slug_collisions": 0, "read_transactions": 12, "write_transactions": 3}`
+///
+/// The transaction counts cover request handling only, not startup work such
+/// as migrations or the reservation sweeper.
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    Json(json!({
+        "dropped_click_counts": state.metrics.dropped_click_counts(),
+        "slug_collisions": state.metrics.slug_collisions(),
+        "read_transactions": state.metrics.read_transactions(),
+        "write_transactions": state.metrics.write_transactions(),
+    }))
+}
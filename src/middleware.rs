@@ -1,58 +1,385 @@
 use axum::{
-    extract::Request,
-    http::{HeaderMap, StatusCode},
+    extract::{Extension, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use std::env;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::database::AppState;
+use crate::preview_token;
+use crate::ratelimit::retry_after_with_jitter;
+
+/// Identity extracted from a verified JWT's `sub` claim
+///
+/// Inserted as a request extension in `AUTH_MODE=jwt`. Handlers use it as the
+/// effective `ref_id`, overriding whatever the request body or query claims.
+#[derive(Clone, Debug)]
+pub struct AuthSubject(pub String);
+
+/// The `AUTHORIZATION` key a request authenticated with
+///
+/// Inserted as a request extension in shared-secret mode when auth is
+/// enabled, so handlers can tell integrations apart. Holds the key itself;
+/// log `fingerprint` instead.
+#[derive(Clone, Debug)]
+pub struct ApiKey(pub String);
+
+impl ApiKey {
+    /// Short, stable identifier of the key that is safe to log
+    ///
+    /// The first 8 hex digits of the key's SHA-256.
+    pub fn fingerprint(&self) -> String {
+        Sha256::digest(self.0.as_bytes())
+            .iter()
+            .take(4)
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Marks a request allowed to use the `/api/admin` routes
+///
+/// Inserted by `auth_middleware` for a key listed in `ADMIN_KEYS` or a JWT
+/// whose `role` claim is `admin`. When no auth is configured at all, every
+/// request gets it, since there is nobody to tell admins from.
+#[derive(Clone, Copy, Debug)]
+pub struct AdminAccess;
+
+/// Identifier of the current request, for correlating logs
+///
+/// Inserted as a request extension by `request_id_middleware`.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Default header carrying the request id, both inbound and outbound
+///
+/// Overridden with `REQUEST_ID_HEADER` (see `Config::request_id_header`).
+pub const DEFAULT_REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied request id that is reused as-is
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Claims required in a JWT
+#[derive(Deserialize)]
+struct Claims {
+    /// Subject, used as the caller's ref_id
+    sub: String,
+
+    /// `admin` grants `AdminAccess`; anything else, or no role, does not
+    #[serde(default)]
+    role: Option<String>,
+}
+
+/// How API requests are authenticated, read from the environment per request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// `Authorization` must equal one of the `AUTHORIZATION` keys
+    Secret,
+
+    /// `AUTH_MODE=jwt`: `Authorization` must carry a valid bearer token
+    Jwt,
+
+    /// Neither is configured; every request is let through
+    None,
+}
+
+impl AuthMode {
+    /// The mode currently configured by `AUTH_MODE` and `AUTHORIZATION`
+    pub fn current() -> Self {
+        if env::var("AUTH_MODE").is_ok_and(|mode| mode.eq_ignore_ascii_case("jwt")) {
+            AuthMode::Jwt
+        } else if auth_keys().is_empty() {
+            AuthMode::None
+        } else {
+            AuthMode::Secret
+        }
+    }
+}
+
+/// The comma-separated keys in `AUTHORIZATION`, without blank entries
+fn auth_keys() -> Vec<String> {
+    env::var("AUTHORIZATION")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// The comma-separated keys in `ADMIN_KEYS`, without blank entries
+fn admin_keys() -> Vec<String> {
+    env::var("ADMIN_KEYS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Cached RS256 public key, keyed by the path it was read from
+static PUBLIC_KEY_CACHE: Mutex<Option<(String, Vec<u8>)>> = Mutex::new(None);
 
 /// Middleware to check for Authorization header
-/// 
+///
 /// This middleware checks if the `AUTHORIZATION` environment variable is set.
 /// If it is set, it verifies that the request contains an `Authorization` header
-/// with the matching value.
-/// 
-/// If the environment variable is not set, the check is skipped.
+/// matching one of its comma-separated keys (e.g. `key_alice,key_bob`), so
+/// one integration's access can be revoked without rotating everyone's. The
+/// matched key is exposed to handlers as an `ApiKey` extension.
+///
+/// If the environment variable is not set or empty, the check is skipped.
+///
+/// Requests for a link's stats carrying `?token=` are let through unchecked;
+/// the stats handlers verify the preview token themselves.
+///
+/// A header matching one of the `ADMIN_KEYS` is accepted as well, whether or
+/// not `AUTHORIZATION` is set, and also grants `AdminAccess`.
+///
+/// # JWT Mode
+///
+/// With `AUTH_MODE=jwt`, the header must instead carry `Bearer <token>`, where
+/// the token is signed with HS256 using `JWT_SECRET`, or with RS256 verified
+/// against the PEM public key at `JWT_PUBLIC_KEY_PATH`. Expired, tampered or
+/// malformed tokens are rejected with 401. The `sub` claim is exposed to
+/// handlers as an `AuthSubject` extension, and a `role` claim of `admin`
+/// grants `AdminAccess`.
 pub async fn auth_middleware(
     headers: HeaderMap,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, Response> {
-    // Check if AUTHORIZATION env var is set
-    // We use var instead of var_os to ensure it's a valid unicode string
-    if let Ok(auth_secret) = env::var("AUTHORIZATION") {
-        // If the env var exists but is empty, we might want to skip auth or enforce empty auth?
-        // The requirement says "jika di env di set authorization key maka perlu di cek"
-        // usually implies if it's present.
-        if !auth_secret.is_empty() {
-            let unauthorized_response = || {
-                (
-                    StatusCode::UNAUTHORIZED,
-                    Json(json!({
-                        "error": "Unauthorized",
-                        "message": "Invalid or missing authorization header"
-                    })),
-                ).into_response()
-            };
-
-             match headers.get("Authorization") {
-                Some(header_value) => {
-                    match header_value.to_str() {
-                        Ok(header_str) => {
-                            if header_str != auth_secret {
-                                return Err(unauthorized_response());
-                            }
-                        }
-                        Err(_) => return Err(unauthorized_response()),
-                    }
-                }
-                None => return Err(unauthorized_response()),
-            }
+    let unauthorized_response = || {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "message": "Invalid or missing authorization header"
+            })),
+        ).into_response()
+    };
+
+    if preview_token::is_token_request(&request) {
+        return Ok(next.run(request).await);
+    }
+
+    if AuthMode::current() == AuthMode::Jwt {
+        let token = headers
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(unauthorized_response)?;
+
+        let claims = verify_jwt(token.trim()).ok_or_else(unauthorized_response)?;
+        if claims.role.as_deref() == Some("admin") {
+            request.extensions_mut().insert(AdminAccess);
         }
+        request.extensions_mut().insert(AuthSubject(claims.sub));
+
+        return Ok(next.run(request).await);
     }
-    
+
+    // Any of the comma-separated keys in AUTHORIZATION is accepted;
+    // unset or empty disables the check
+    let keys = auth_keys();
+    let admin_keys = admin_keys();
+    let header_str = headers.get("Authorization").and_then(|value| value.to_str().ok());
+    if let Some(key) = header_str.and_then(|header_str| admin_keys.iter().find(|key| *key == header_str)) {
+        request.extensions_mut().insert(ApiKey(key.clone()));
+        request.extensions_mut().insert(AdminAccess);
+    } else if !keys.is_empty() {
+        let header_str = header_str.ok_or_else(unauthorized_response)?;
+
+        let key = keys.into_iter().find(|key| key == header_str).ok_or_else(unauthorized_response)?;
+        request.extensions_mut().insert(ApiKey(key));
+    } else if admin_keys.is_empty() {
+        request.extensions_mut().insert(AdminAccess);
+    }
+
     // If env var is not set or empty, or auth matches, proceed
     Ok(next.run(request).await)
 }
+
+/// Verifies a JWT's signature and expiry, returning its claims
+///
+/// RS256 is used when `JWT_PUBLIC_KEY_PATH` is set, HS256 with `JWT_SECRET`
+/// otherwise. Returns `None` for any invalid token, missing key material or
+/// an empty `sub`.
+fn verify_jwt(token: &str) -> Option<Claims> {
+    let (key, algorithm) = match env::var("JWT_PUBLIC_KEY_PATH") {
+        Ok(path) if !path.is_empty() => {
+            let pem = read_public_key(&path)?;
+            (DecodingKey::from_rsa_pem(&pem).ok()?, Algorithm::RS256)
+        }
+        _ => {
+            let secret = env::var("JWT_SECRET").ok().filter(|secret| !secret.is_empty())?;
+            (DecodingKey::from_secret(secret.as_bytes()), Algorithm::HS256)
+        }
+    };
+
+    // Validation requires and checks the `exp` claim by default
+    let validation = Validation::new(algorithm);
+    let data = decode::<Claims>(token, &key, &validation).ok()?;
+
+    Some(data.claims).filter(|claims| !claims.sub.is_empty())
+}
+
+/// Middleware restricting the `/api/admin` routes to `AdminAccess`
+///
+/// Runs after `auth_middleware`, so other callers have already proven who
+/// they are and get `403 Forbidden` with `code: admin_required`.
+pub async fn admin_middleware(
+    admin: Option<Extension<AdminAccess>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    if admin.is_none() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "Admin credentials required",
+                "code": "admin_required"
+            })),
+        )
+            .into_response());
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Reads the RS256 public key, caching it so the file is not read per request
+fn read_public_key(path: &str) -> Option<Vec<u8>> {
+    let mut cache = PUBLIC_KEY_CACHE.lock().unwrap();
+
+    if let Some((cached_path, pem)) = cache.as_ref() {
+        if cached_path == path {
+            return Some(pem.clone());
+        }
+    }
+
+    match fs::read(path) {
+        Ok(pem) => {
+            *cache = Some((path.to_string(), pem.clone()));
+            Some(pem)
+        }
+        Err(err) => {
+            tracing::error!("Failed to read JWT_PUBLIC_KEY_PATH {}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Middleware bounding the number of concurrent API writes
+///
+/// redb serializes write transactions, so a burst of creates would otherwise
+/// pile up waiting for the writer lock. Requests with a mutating method must
+/// acquire a permit from `AppState::write_permits`; when none is free they
+/// are rejected immediately with `503 Service Unavailable` and a jittered
+/// `Retry-After` of 1-2 seconds.
+/// Reads (`GET`, `HEAD`, `OPTIONS`) are never throttled.
+pub async fn write_limit_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(next.run(request).await);
+    }
+
+    // The permit is held until the handler has produced its response
+    let _permit = state.write_permits.clone().try_acquire_owned().map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after_with_jitter(Duration::from_secs(1)).to_string())],
+            Json(json!({
+                "error": "Service Unavailable",
+                "message": "Too many concurrent writes, please retry shortly"
+            })),
+        )
+            .into_response()
+    })?;
+
+    Ok(next.run(request).await)
+}
+
+/// Middleware assigning every request an id
+///
+/// A reasonable id sent by the client (or a proxy) in the configured header
+/// (`X-Request-Id` by default) is reused, otherwise a random one is generated.
+/// The id is exposed to handlers as a `RequestId` extension and echoed back
+/// under the same header name.
+pub async fn request_id_middleware(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let header_name = &state.config.request_id_header;
+    let request_id = request
+        .headers()
+        .get(header_name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(String::from)
+        .unwrap_or_else(|| format!("{:016x}", rand::rng().random::<u64>()));
+
+    request.extensions_mut().insert(RequestId(request_id.clone()));
+    let mut response = next.run(request).await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(header_name.clone(), value);
+    }
+    response
+}
+
+/// Paths served over plain http even with `FORCE_HTTPS`, so load balancer
+/// health checks keep working
+const HTTPS_EXEMPT_PATHS: &[&str] = &["/health"];
+
+/// Middleware redirecting plain-http requests to https (`FORCE_HTTPS`)
+///
+/// TLS is expected to terminate at a proxy, so the original scheme is read
+/// from `X-Forwarded-Proto`; requests without it pass through. A request
+/// forwarded as `http` gets a 301 to the same host, path and query over
+/// https, before any credentials or link data are handled. The host comes
+/// from `X-Forwarded-Host`, falling back to `Host`; without either the
+/// request passes through since no target can be built.
+pub async fn https_redirect_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.config.force_https || HTTPS_EXEMPT_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    // Scoped so no borrow of the (non-Sync) request is held across the await below
+    let target = {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                // Proxies chaining the header append values; the first is the client's
+                .and_then(|value| value.split(',').next())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let forwarded_http = header("x-forwarded-proto").is_some_and(|proto| proto.eq_ignore_ascii_case("http"));
+        let host = header("x-forwarded-host").or_else(|| header(header::HOST.as_str()));
+        host.filter(|_| forwarded_http).and_then(|host| {
+            let path = request.uri().path_and_query().map_or("/", |path| path.as_str());
+            HeaderValue::from_str(&format!("https://{}{}", host, path)).ok()
+        })
+    };
+
+    match target {
+        Some(location) => (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, location)]).into_response(),
+        None => next.run(request).await,
+    }
+}
@@ -0,0 +1,290 @@
+//! Schema migrations
+//!
+//! The schema version lives in `TABLE_META` under `schema_version`; a fresh or
+//! pre-migration database is version 0. `MIGRATIONS[i]` upgrades version `i`
+//! to `i + 1`. Each migration runs in its own write transaction together with
+//! the version bump, so a crash mid-upgrade never leaves a half-applied step.
+//!
+//! To change the stored layout, append a migration; never edit or reorder
+//! existing ones, since deployed databases have already applied them.
+
+use chrono::{DateTime, Utc};
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+use serde::Deserialize;
+
+use crate::database::{created_index_key, InitError, TABLE_CREATED_INDEX, TABLE_DESTINATIONS, TABLE_REF_INDEX, TABLE_URLS};
+use crate::model::UrlRecord;
+use crate::record_format::{self, DecodeError, RecordFormat};
+
+/// Bookkeeping values about the database itself
+///
+/// Key: Setting name (e.g. "schema_version")
+/// Value: Setting value
+pub const TABLE_META: TableDefinition<&str, u64> = TableDefinition::new("meta_v1");
+
+/// Key in `TABLE_META` holding the applied schema version
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// First layout of the ref_id index, holding a full record copy per entry
+///
+/// Only read by migrations; `TABLE_REF_INDEX` replaced it in version 3.
+const TABLE_REF_INDEX_V1: TableDefinition<&str, &str> = TableDefinition::new("ref_index_v1");
+
+/// A single upgrade step, run inside the write transaction that records it
+pub type Migration = fn(&WriteTransaction) -> Result<(), redb::Error>;
+
+/// Every migration, in order
+pub const MIGRATIONS: &[Migration] = &[
+    reserialize_records,
+    index_destinations,
+    index_ref_ids_by_slug,
+    index_links_by_creation,
+    reencode_bincode_records,
+];
+
+/// Returns the schema version recorded in `db` (0 when none is recorded)
+pub fn schema_version(db: &Database) -> Result<u64, redb::Error> {
+    let write_txn = db.begin_write()?;
+    let version = read_version(&write_txn)?;
+    write_txn.abort()?;
+    Ok(version)
+}
+
+/// Brings `db` up to the current schema version
+pub fn run_migrations(db: &Database) -> Result<(), InitError> {
+    apply_migrations(db, MIGRATIONS)
+}
+
+/// Applies the not-yet-applied entries of `migrations` to `db`
+///
+/// Fails with `InitError::UnsupportedVersion` when the database was written
+/// by a newer build that knows more migrations than `migrations` holds.
+pub fn apply_migrations(db: &Database, migrations: &[Migration]) -> Result<(), InitError> {
+    let supported = migrations.len() as u64;
+    let found = schema_version(db)?;
+    if found > supported {
+        return Err(InitError::UnsupportedVersion { found, supported });
+    }
+
+    for (version, migration) in migrations.iter().enumerate().skip(found as usize) {
+        let write_txn = db.begin_write().map_err(redb::Error::from)?;
+        migration(&write_txn)?;
+        {
+            let mut meta = write_txn.open_table(TABLE_META).map_err(redb::Error::from)?;
+            meta.insert(SCHEMA_VERSION_KEY, version as u64 + 1)
+                .map_err(redb::Error::from)?;
+        }
+        write_txn.commit().map_err(redb::Error::from)?;
+
+        tracing::info!("Migrated database schema to version {}", version + 1);
+    }
+
+    Ok(())
+}
+
+/// Reads the schema version inside an open write transaction
+fn read_version(write_txn: &WriteTransaction) -> Result<u64, redb::Error> {
+    let meta = write_txn.open_table(TABLE_META)?;
+    let version = meta.get(SCHEMA_VERSION_KEY)?.map(|value| value.value()).unwrap_or(0);
+    Ok(version)
+}
+
+/// Version 0 to 1: rewrites every stored record through `UrlRecord`
+///
+/// Records written before later fields existed gain their defaults
+/// explicitly. Values that fail to parse are left untouched.
+fn reserialize_records(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    for definition in [TABLE_URLS, TABLE_REF_INDEX_V1] {
+        let mut table = write_txn.open_table(definition)?;
+
+        let mut rewritten = Vec::new();
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            if let Ok(record) = serde_json::from_str::<UrlRecord>(value.value()) {
+                let json = serde_json::to_string(&record).unwrap();
+                if json != value.value() {
+                    rewritten.push((key.value().to_string(), json));
+                }
+            }
+        }
+
+        for (key, json) in rewritten {
+            table.insert(key.as_str(), json.as_str())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Version 1 to 2: fills `TABLE_DESTINATIONS` from the existing links
+///
+/// The newest link to each destination is indexed, as creation would have.
+fn index_destinations(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    let table_main = write_txn.open_table(TABLE_URLS)?;
+    let mut table_destinations = write_txn.open_table(TABLE_DESTINATIONS)?;
+
+    let mut newest: std::collections::HashMap<String, UrlRecord> = std::collections::HashMap::new();
+    for entry in table_main.iter()? {
+        let (_, value) = entry?;
+        if let Ok(record) = serde_json::from_str::<UrlRecord>(value.value()) {
+            let is_newer = newest
+                .get(&record.original_url)
+                .is_none_or(|current| record.created_at > current.created_at);
+            if is_newer {
+                newest.insert(record.original_url.clone(), record);
+            }
+        }
+    }
+
+    for (original_url, record) in newest {
+        table_destinations.insert(original_url.as_str(), record.id.as_str())?;
+    }
+
+    Ok(())
+}
+
+/// Version 2 to 3: rebuilds the ref_id index to store slugs only
+///
+/// Entries are derived from `TABLE_URLS`, the source of truth, rather than
+/// copied from `ref_index_v1`, whose record copies may be stale. The old
+/// table is then deleted.
+fn index_ref_ids_by_slug(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    {
+        let table_main = write_txn.open_table(TABLE_URLS)?;
+        let mut table_index = write_txn.open_table(TABLE_REF_INDEX)?;
+
+        for entry in table_main.iter()? {
+            let (key, value) = entry?;
+            if let Ok(record) = serde_json::from_str::<UrlRecord>(value.value()) {
+                if let Some(ref_id) = &record.ref_id {
+                    let index_key = format!("{}:{}", ref_id, record.created_at.timestamp_micros());
+                    table_index.insert(index_key.as_str(), key.value())?;
+                }
+            }
+        }
+    }
+
+    write_txn.delete_table(TABLE_REF_INDEX_V1)?;
+    Ok(())
+}
+
+/// Version 3 to 4: fills `TABLE_CREATED_INDEX` from the existing links
+///
+/// Records may already be bincode by now (see `record_format`), so they
+/// are read with `record_format::decode`.
+fn index_links_by_creation(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    let table_main = write_txn.open_table(TABLE_URLS)?;
+    let mut table_created = write_txn.open_table(TABLE_CREATED_INDEX)?;
+
+    for entry in table_main.iter()? {
+        let (key, value) = entry?;
+        if let Ok(record) = record_format::decode(value.value()) {
+            table_created.insert(created_index_key(record.created_at, key.value()).as_str(), key.value())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `UrlRecord` as `RECORD_FORMAT=bincode` first stored it
+///
+/// Only read by migrations. Bincode stores fields by position, so this
+/// must keep exactly the fields, order and types of that release.
+#[derive(Deserialize)]
+struct BincodeRecordV1 {
+    id: String,
+    original_url: String,
+    short_url: String,
+    ref_id: Option<String>,
+    created_at: DateTime<Utc>,
+    clicks: u64,
+    password_protected: bool,
+    redirect_delay_seconds: Option<u32>,
+    is_active: bool,
+    killed: bool,
+    disabled_at: Option<DateTime<Utc>>,
+    disabled_message: Option<String>,
+    click_webhook: Option<String>,
+    tags: Vec<String>,
+    expires_at: Option<DateTime<Utc>>,
+    last_accessed_at: Option<DateTime<Utc>>,
+    permanent: bool,
+    allowed_referrers: Vec<String>,
+    require_referrer: bool,
+}
+
+/// Decodes a bincode record written by any release since `BincodeRecordV1`
+///
+/// Fields appended to `UrlRecord` since then are read in order for as long
+/// as the value has bytes left; missing ones get their defaults.
+fn decode_legacy_bincode(value: &str) -> Result<UrlRecord, DecodeError> {
+    let bytes = record_format::bincode_payload(value)?;
+    let mut reader = bytes.as_slice();
+    let v1: BincodeRecordV1 = bincode::deserialize_from(&mut reader)?;
+    let mut record = UrlRecord {
+        id: v1.id,
+        original_url: v1.original_url,
+        short_url: v1.short_url,
+        ref_id: v1.ref_id,
+        created_at: v1.created_at,
+        clicks: v1.clicks,
+        password_protected: v1.password_protected,
+        redirect_delay_seconds: v1.redirect_delay_seconds,
+        is_active: v1.is_active,
+        killed: v1.killed,
+        disabled_at: v1.disabled_at,
+        disabled_message: v1.disabled_message,
+        click_webhook: v1.click_webhook,
+        tags: v1.tags,
+        expires_at: v1.expires_at,
+        last_accessed_at: v1.last_accessed_at,
+        permanent: v1.permanent,
+        allowed_referrers: v1.allowed_referrers,
+        require_referrer: v1.require_referrer,
+        source: None,
+        max_unique_visitors: None,
+        bundle_members: Vec::new(),
+    };
+
+    if !reader.is_empty() {
+        record.source = bincode::deserialize_from(&mut reader)?;
+    }
+    if !reader.is_empty() {
+        record.max_unique_visitors = bincode::deserialize_from(&mut reader)?;
+    }
+    if !reader.is_empty() {
+        record.bundle_members = bincode::deserialize_from(&mut reader)?;
+    }
+    Ok(record)
+}
+
+/// Version 4 to 5: re-encodes bincode records missing fields added since
+///
+/// Such records fail `record_format::decode` once `UrlRecord` has grown
+/// (`source`, `max_unique_visitors`, `bundle_members`); they are read with
+/// `decode_legacy_bincode` and written back in the current layout. Values
+/// that still cannot be decoded are left untouched. Appending a field to
+/// `UrlRecord` means reading it in `decode_legacy_bincode` and appending this
+/// migration to `MIGRATIONS` again.
+fn reencode_bincode_records(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    let mut table_main = write_txn.open_table(TABLE_URLS)?;
+
+    let mut rewritten = Vec::new();
+    for entry in table_main.iter()? {
+        let (key, value) = entry?;
+        let value = value.value();
+        if RecordFormat::of(value) != RecordFormat::Bincode || record_format::decode(value).is_ok() {
+            continue;
+        }
+        match decode_legacy_bincode(value) {
+            Ok(record) => rewritten.push((key.value().to_string(), RecordFormat::Bincode.encode(&record))),
+            Err(err) => tracing::warn!("Leaving bincode record {} as it is: {}", key.value(), err),
+        }
+    }
+
+    for (key, value) in rewritten {
+        table_main.insert(key.as_str(), value.as_str())?;
+    }
+
+    Ok(())
+}
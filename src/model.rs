@@ -5,6 +5,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// Represents a URL record stored in the database
 /// 
@@ -37,6 +38,91 @@ pub struct UrlRecord {
     /// Defaults to 0 if not present during deserialization
     #[serde(default)]
     pub clicks: u64,
+    
+    /// Whether a password must be supplied to follow this link
+    /// The password hash itself lives in `TABLE_LINK_PASSWORDS`
+    #[serde(default)]
+    pub password_protected: bool,
+    
+    /// Seconds to show a countdown page before redirecting
+    /// `None` or 0 means an immediate redirect
+    #[serde(default)]
+    pub redirect_delay_seconds: Option<u32>,
+    
+    /// Whether the link still redirects
+    /// Set to `false` by the admin kill switch or the owner's toggle; the
+    /// record is kept either way
+    #[serde(default = "default_active")]
+    pub is_active: bool,
+    
+    /// Whether the link was disabled by the admin kill switch
+    /// Owners cannot re-enable a killed link with the toggle
+    #[serde(default)]
+    pub killed: bool,
+    
+    /// When the link was disabled, if it has been
+    #[serde(default)]
+    pub disabled_at: Option<DateTime<Utc>>,
+    
+    /// Message shown to visitors once the link is disabled
+    /// `None` falls back to a generic "no longer available" text
+    #[serde(default)]
+    pub disabled_message: Option<String>,
+    
+    /// URL that receives a POST for every counted visit (see the `webhook` module)
+    #[serde(default)]
+    pub click_webhook: Option<String>,
+    
+    /// Free-form labels for grouping links (e.g. by campaign)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    
+    /// When the link stops redirecting; `None` means never
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    
+    /// When the link last served a counted redirect; `None` if never
+    #[serde(default)]
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    
+    /// Whether visitors get a 301 Moved Permanently instead of a 307
+    /// 
+    /// Browsers cache 301s, so repeat visits skip the shortener and are not counted.
+    #[serde(default)]
+    pub permanent: bool,
+    
+    /// Hosts whose pages may send visitors to this link; empty allows any
+    /// 
+    /// Each entry also covers its subdomains (see `handler::referrer_allowed`).
+    #[serde(default)]
+    pub allowed_referrers: Vec<String>,
+    
+    /// Whether visits without a `Referer` header are refused while
+    /// `allowed_referrers` is set (direct visits are allowed by default)
+    #[serde(default)]
+    pub require_referrer: bool,
+    
+    /// Client or app that created the link (e.g. "web", "cli"), for attribution
+    #[serde(default)]
+    pub source: Option<String>,
+    
+    /// Distinct visitors (by IP) allowed to follow the link; `None` means any
+    /// 
+    /// Visitors are tracked in `TABLE_VISITORS` (see the `visitors` module).
+    #[serde(default)]
+    pub max_unique_visitors: Option<u32>,
+    
+    /// Destinations of a bundle, one picked at random per visit; empty for plain links
+    /// 
+    /// `original_url` holds the first member, so listings and dedup still see
+    /// a destination.
+    #[serde(default)]
+    pub bundle_members: Vec<String>,
+}
+
+/// Records stored before `is_active` existed are active
+fn default_active() -> bool {
+    true
 }
 
 /// Request payload for creating a new short URL
@@ -46,10 +132,20 @@ pub struct UrlRecord {
 /// {
 ///   "url": "https://example.com/very/long/url",
 ///   "ref_id": "user_123",
-///   "custom_id": "my-link"  // Optional
+///   "custom_id": "my-link",  // Optional
+///   "password": "s3cret",    // Optional
+///   "redirect_delay_seconds": 5,  // Optional
+///   "disabled_message": "This promotion has ended",  // Optional
+///   "click_webhook": "https://hooks.example.com/clicks",  // Optional
+///   "tags": ["spring", "email"],  // Optional
+///   "expires_in_seconds": 86400,  // Optional
+///   "expires_at": "7d",  // Optional - RFC3339 or a duration; instead of expires_in_seconds
+///   "initial_clicks": 100,  // Optional
+///   "dedup": true,  // Optional
+///   "source": "cli"  // Optional
 /// }
 /// ```
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct CreateRequest {
     /// The original URL to be shortened
     pub url: String,
@@ -61,6 +157,155 @@ pub struct CreateRequest {
     /// Optional custom slug/ID for the shortened URL
     /// If not provided, a random 6-character ID will be generated
     pub custom_id: Option<String>,
+    
+    /// Optional password visitors must supply to follow the link
+    pub password: Option<String>,
+    
+    /// Optional countdown, in seconds, shown before redirecting
+    pub redirect_delay_seconds: Option<u32>,
+    
+    /// Optional message shown to visitors if the link is later disabled
+    pub disabled_message: Option<String>,
+    
+    /// Optional http(s) URL notified of every click
+    pub click_webhook: Option<String>,
+    
+    /// Optional labels, at most `MAX_TAGS`; blank entries and case-insensitive
+    /// duplicates are dropped (see `tags`).
+    /// Omitted means `DEFAULT_TAGS`; an explicit list replaces them.
+    pub tags: Option<Vec<String>>,
+    
+    /// Optional lifetime in seconds, after which the link returns 410
+    /// 0 means the link never expires
+    pub expires_in_seconds: Option<u64>,
+    
+    /// Optional deadline after which the link returns 410
+    /// Either an RFC3339 timestamp or a duration from now such as `"24h"` or
+    /// `"7d"` (units `s`, `m`, `h`, `d`, `w`). Cannot be combined with
+    /// `expires_in_seconds`.
+    pub expires_at: Option<String>,
+    
+    /// Optional starting click count, e.g. for links migrated from another service
+    /// Defaults to 0
+    pub initial_clicks: Option<u64>,
+    
+    /// Return an existing link to the same url instead of creating one
+    /// Ignored when `custom_id` or `password` is set
+    #[serde(default)]
+    pub dedup: bool,
+    
+    /// Redirect with 301 Moved Permanently instead of 307, for SEO
+    /// Defaults to false; click counts become unreliable since browsers cache 301s
+    pub permanent: Option<bool>,
+    
+    /// Hosts allowed to refer visitors (e.g. `["example.com"]`); empty or omitted allows any
+    pub allowed_referrers: Option<Vec<String>>,
+    
+    /// Refuse visits without a `Referer` header while `allowed_referrers` is set
+    /// Defaults to false
+    pub require_referrer: Option<bool>,
+    
+    /// Optional client or app creating the link, at most `MAX_SOURCE_LENGTH` characters
+    /// Falls back to the `X-Client-Source` header on `POST /api/urls`
+    pub source: Option<String>,
+    
+    /// Optional number of distinct visitors (by IP) allowed; later new visitors get 410
+    /// 0 means no cap
+    pub max_unique_visitors: Option<u32>,
+}
+
+/// Request payload for creating a bundle, a slug that redirects to a random member
+/// 
+/// # Example
+/// ```json
+/// {
+///   "members": ["https://mirror-a.example.com", "https://mirror-b.example.com"],
+///   "ref_id": "user_123",  // Optional
+///   "custom_id": "mirrors",  // Optional
+///   "tags": ["downloads"]  // Optional
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct BundleRequest {
+    /// Destinations to spread visits across, at least 2 and at most `MAX_BUNDLE_MEMBERS`
+    pub members: Vec<String>,
+    
+    /// Optional reference ID to identify the owner of the bundle
+    pub ref_id: Option<String>,
+    
+    /// Optional custom slug; generated like a link's when omitted
+    pub custom_id: Option<String>,
+    
+    /// Optional labels, as for links
+    pub tags: Option<Vec<String>>,
+}
+
+/// Values applied to every entry of a batch create unless the entry sets its own
+#[derive(Deserialize, Default)]
+pub struct BatchDefaults {
+    /// Owner of every created link
+    pub ref_id: Option<String>,
+    
+    /// Tags given to every created link
+    pub tags: Option<Vec<String>>,
+    
+    /// Lifetime, in seconds, of every created link
+    pub expires_in_seconds: Option<u64>,
+}
+
+/// One entry of a batch create: a bare URL or a full create payload
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum BatchItem {
+    /// Just the destination; everything else comes from the defaults
+    Url(String),
+    
+    /// A full payload whose fields override the defaults
+    Link(Box<CreateRequest>),
+}
+
+/// Request payload for creating many links with shared defaults
+/// 
+/// # Example
+/// ```json
+/// {
+///   "defaults": { "ref_id": "campaign_7", "tags": ["spring"], "expires_in_seconds": 604800 },
+///   "urls": ["https://example.com/a", { "url": "https://example.com/b", "tags": ["vip"] }]
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct BatchCreateRequest {
+    /// Shared values for every entry
+    #[serde(default)]
+    pub defaults: BatchDefaults,
+    
+    /// Links to create
+    pub urls: Vec<BatchItem>,
+}
+
+/// One link of a bulk import
+#[derive(Deserialize)]
+pub struct BulkItem {
+    /// Destination URL
+    pub url: String,
+    
+    /// Owner of the link (ignored in JWT mode)
+    pub ref_id: Option<String>,
+    
+    /// Slug to use instead of a generated one
+    pub custom_id: Option<String>,
+}
+
+/// Request payload for importing many links in one transaction
+/// 
+/// # Example
+/// ```json
+/// { "items": [{ "url": "https://example.com/a", "ref_id": "user_123", "custom_id": "a" }] }
+/// ```
+#[derive(Deserialize)]
+pub struct BulkCreateRequest {
+    /// Links to create
+    pub items: Vec<BulkItem>,
 }
 
 /// Response returned after successfully creating a short URL
@@ -89,6 +334,17 @@ pub struct CreateResponse {
     pub created_at: DateTime<Utc>,
 }
 
+impl From<UrlRecord> for CreateResponse {
+    fn from(record: UrlRecord) -> Self {
+        CreateResponse {
+            id: record.id,
+            short_url: record.short_url,
+            original_url: record.original_url,
+            created_at: record.created_at,
+        }
+    }
+}
+
 /// Query parameters for listing URLs with pagination
 /// 
 /// # Example
@@ -106,6 +362,264 @@ pub struct ListParams {
     /// Number of items per page
     /// Defaults to 10 if not provided, maximum is 100
     pub limit: Option<usize>,
+    
+    /// How `created_at`/`expires_at` are rendered (`rfc3339` or `epoch_ms`)
+    #[serde(default)]
+    pub time_format: TimeFormat,
+    
+    /// Optional ordering of the results; index order when omitted
+    pub sort: Option<ListSort>,
+    
+    /// Optional body format; when omitted, `Accept: text/csv` selects CSV, else JSON
+    pub format: Option<ListFormat>,
+}
+
+/// Orderings accepted by `GET /api/urls?sort=`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSort {
+    /// Most recently accessed first; never-accessed links last
+    LastAccessedDesc,
+}
+
+/// Body formats accepted by `GET /api/urls?format=`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListFormat {
+    /// The paginated JSON object (default)
+    Json,
+    
+    /// `id,original_url,clicks,created_at` rows under a header row, for spreadsheets
+    Csv,
+}
+
+/// Representation of timestamps in API responses
+/// 
+/// Selected with `?time_format=`; stored records always keep RFC3339.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeFormat {
+    /// RFC3339 strings, e.g. `"2026-01-17T10:30:00Z"` (default)
+    #[default]
+    Rfc3339,
+    
+    /// Integer milliseconds since the Unix epoch
+    EpochMs,
+}
+
+impl TimeFormat {
+    /// Rewrites every `created_at`/`expires_at`/`last_accessed_at` field in `value` to this format
+    /// 
+    /// Nested objects and arrays are walked, so whole list responses can be
+    /// converted at once. Null or unparseable values are left untouched.
+    pub fn apply(self, value: &mut Value) {
+        if self == TimeFormat::Rfc3339 {
+            return;
+        }
+        
+        match value {
+            Value::Object(map) => {
+                for (key, field) in map.iter_mut() {
+                    if matches!(key.as_str(), "created_at" | "expires_at" | "last_accessed_at") {
+                        let millis = field
+                            .as_str()
+                            .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+                            .map(|timestamp| timestamp.timestamp_millis());
+                        if let Some(millis) = millis {
+                            *field = Value::from(millis);
+                        }
+                    } else {
+                        self.apply(field);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.apply(item)),
+            _ => {}
+        }
+    }
+}
+
+/// Query parameters selecting the timestamp format of a response
+/// 
+/// # Example
+/// Query string: `?time_format=epoch_ms`
+#[derive(Deserialize, Default)]
+pub struct TimeFormatParams {
+    /// How `created_at`/`expires_at` are rendered
+    #[serde(default)]
+    pub time_format: TimeFormat,
+}
+
+/// Query parameters accepted by the public redirect endpoint
+/// 
+/// # Example
+/// Query string: `?password=s3cret`
+#[derive(Deserialize, Default)]
+pub struct RedirectParams {
+    /// Password for protected links
+    pub password: Option<String>,
+
+    /// Serve the redirect without recording a click (for monitoring tools)
+    #[serde(default)]
+    pub no_count: bool,
+}
+
+/// Query parameters for listing a ref_id's distinct destinations
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123`
+#[derive(Deserialize)]
+pub struct DestinationsParams {
+    /// Reference ID whose links are scanned (required)
+    pub ref_id: Option<String>,
+}
+
+/// Query parameters for listing the tags used by a ref_id
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123`
+#[derive(Deserialize)]
+pub struct TagsParams {
+    /// Reference ID whose links are scanned (required)
+    pub ref_id: Option<String>,
+}
+
+/// A custom slug held for later creation, stored in `TABLE_RESERVATIONS`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reservation {
+    /// Owner allowed to create a link with the slug
+    pub ref_id: Option<String>,
+    
+    /// When the hold lapses and the slug is free again
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request payload for reserving a custom slug
+/// 
+/// # Example
+/// ```json
+/// { "id": "spring-sale", "ref_id": "user_123", "ttl_seconds": 3600 }
+/// ```
+#[derive(Deserialize)]
+pub struct ReserveRequest {
+    /// Custom slug to hold
+    pub id: String,
+    
+    /// Owner of the reservation (ignored in JWT mode)
+    pub ref_id: Option<String>,
+    
+    /// How long to hold the slug (default: 900, max: 86400)
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Query parameters for listing the links a ref_id created on one day
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123&date=2026-01-17`
+#[derive(Deserialize)]
+pub struct ByDateParams {
+    /// Reference ID whose links are listed (required)
+    pub ref_id: Option<String>,
+    
+    /// UTC calendar day, `YYYY-MM-DD` (required)
+    pub date: Option<String>,
+    
+    /// How `created_at`/`expires_at` are rendered
+    #[serde(default)]
+    pub time_format: TimeFormat,
+}
+
+/// Query parameters for looking up a link by its full short URL
+/// 
+/// # Example
+/// Query string: `?short_url=http%3A%2F%2Flocalhost%3A8080%2Fabc123`
+#[derive(Deserialize)]
+pub struct ResolveParams {
+    /// Full short URL as returned by the create endpoint (required)
+    pub short_url: Option<String>,
+}
+
+/// Query parameters for previewing the short URL of a hypothetical slug
+/// 
+/// # Example
+/// Query string: `?id=summer-sale`
+#[derive(Deserialize)]
+pub struct PreviewUrlParams {
+    /// Slug as it would be passed in `custom_id` (required)
+    pub id: Option<String>,
+}
+
+/// Query parameters for validating a prospective custom slug
+/// 
+/// # Example
+/// Query string: `?id=summer-sale&ref_id=user_123`
+#[derive(Deserialize)]
+pub struct ValidateSlugParams {
+    /// Slug as it would be passed in `custom_id` (required)
+    pub id: Option<String>,
+    
+    /// Prospective owner; their own reservations do not count as taken
+    pub ref_id: Option<String>,
+}
+
+/// Query parameters for exporting a ref_id's QR codes
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123`
+#[derive(Deserialize)]
+pub struct QrBundleParams {
+    /// Reference ID whose links are exported (required)
+    pub ref_id: Option<String>,
+}
+
+/// Query parameters for the global click leaderboard
+/// 
+/// # Example
+/// Query string: `?limit=10`
+#[derive(Deserialize)]
+pub struct LeaderboardParams {
+    /// Number of links returned (default 10, max 100)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for the globally oldest links
+/// 
+/// # Example
+/// Query string: `?limit=50`
+#[derive(Deserialize)]
+pub struct OldestParams {
+    /// Number of links returned (default 50, max 1000)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for exporting a ref_id's links as CSV
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123`
+#[derive(Deserialize)]
+pub struct ExportParams {
+    /// Reference ID whose links are exported (required)
+    pub ref_id: Option<String>,
+}
+
+/// Query parameters for rendering a single link's QR code
+/// 
+/// # Example
+/// Query string: `?size=12`
+#[derive(Deserialize)]
+pub struct QrParams {
+    /// Pixels per QR module, clamped to 1..=32 (default: 8)
+    pub size: Option<u32>,
+}
+
+/// Query parameters for a link's SVG QR code
+/// 
+/// # Example
+/// Query string: `?color=%23336699` or `?color=336699`
+#[derive(Deserialize)]
+pub struct QrSvgParams {
+    /// Color of the dark modules, 3 or 6 hex digits with optional `#` (default: black)
+    pub color: Option<String>,
 }
 
 /// Query parameters for deleting a URL
@@ -116,4 +630,334 @@ pub struct DeleteParams {
     /// Optional reference ID to verify that the requester owns this URL
     /// If not provided, deletion is allowed without ownership verification (use with caution)
     pub ref_id: Option<String>,
-}
\ No newline at end of file
+}
+
+/// Query parameters for reading a link's stats
+/// 
+/// Ownership is verified the same way as for deletion
+#[derive(Deserialize)]
+pub struct StatsParams {
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+    
+    /// Preview token granting read access instead (see `preview_token`)
+    pub token: Option<String>,
+}
+
+/// Request payload for minting a stats preview token
+/// 
+/// # Example
+/// ```json
+/// { "ref_id": "user_123", "ttl_seconds": 3600 }
+/// ```
+#[derive(Deserialize)]
+pub struct PreviewTokenRequest {
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+    
+    /// Seconds until the token expires
+    /// Defaults to 3600, maximum is 604800 (seven days)
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Query parameters for a link's audit trail
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123&events_limit=20`
+#[derive(Deserialize)]
+pub struct AuditParams {
+    /// Optional reference ID to verify that the requester owns this URL;
+    /// omitted by admins, who may audit any link
+    pub ref_id: Option<String>,
+    
+    /// Number of most recent click events included
+    /// Defaults to 50, maximum is 500 (analytics)
+    #[cfg(feature = "analytics")]
+    pub events_limit: Option<usize>,
+}
+
+/// One change of a link's destination, kept in `TABLE_LINK_HISTORY`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DestinationChange {
+    /// When the destination changed
+    pub changed_at: DateTime<Utc>,
+    
+    /// Destination before the change
+    pub from: String,
+    
+    /// Destination after the change
+    pub to: String,
+}
+
+/// Query parameters for toggling a link on or off
+/// 
+/// Ownership is verified the same way as for deletion
+#[derive(Deserialize)]
+pub struct ToggleParams {
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+}
+
+/// Request payload for setting or clearing a link's click webhook
+/// 
+/// # Example
+/// ```json
+/// { "click_webhook": "https://hooks.example.com/clicks", "ref_id": "user_123" }
+/// ```
+#[derive(Deserialize)]
+pub struct WebhookRequest {
+    /// New webhook URL; omitted, null or empty clears it
+    pub click_webhook: Option<String>,
+
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+}
+
+/// Query parameters for reading or incrementing a link's named counter
+/// 
+/// Ownership is verified the same way as for deletion
+#[derive(Deserialize)]
+pub struct CounterParams {
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+}
+
+/// Request payload for renaming a short URL
+/// 
+/// # Example
+/// ```json
+/// { "new_id": "summer-sale", "ref_id": "user_123" }
+/// ```
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    /// Slug the link is moved to
+    pub new_id: String,
+
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+
+    /// Current password, required to rename a password-protected link
+    /// (its hash is salted with the slug and must be recomputed)
+    pub password: Option<String>,
+}
+
+/// Query parameters for rotating every slug of a ref_id
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123`
+#[derive(Deserialize)]
+pub struct RotateParams {
+    /// Reference ID whose links are rotated (required)
+    pub ref_id: Option<String>,
+}
+
+/// Request payload for merging one link into another
+/// 
+/// # Example
+/// ```json
+/// { "source_id": "old-promo", "target_id": "promo", "ref_id": "user_123", "delete_source": true }
+/// ```
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    /// Link whose clicks are moved
+    pub source_id: String,
+    
+    /// Link receiving the clicks
+    pub target_id: String,
+    
+    /// Optional reference ID; when given, it must own both links
+    pub ref_id: Option<String>,
+    
+    /// Delete the source and keep its slug as an alias of the target,
+    /// instead of repointing it at the target's destination
+    #[serde(default)]
+    pub delete_source: bool,
+}
+
+/// Optional request payload for the admin kill switch
+/// 
+/// # Example
+/// ```json
+/// { "message": "This link was removed for violating our terms" }
+/// ```
+#[derive(Deserialize, Default)]
+pub struct KillRequest {
+    /// Replaces the link's `disabled_message` shown to visitors
+    pub message: Option<String>,
+}
+
+/// A single visit to a short link, stored in `TABLE_CLICK_EVENTS`
+/// 
+/// # Example
+/// ```json
+/// {
+///   "id": "abc123",
+///   "timestamp": "2026-01-17T13:40:00Z",
+///   "referrer": "https://news.example.com/",
+///   "user_agent": "Mozilla/5.0 ...",
+///   "country": "ID"
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClickEvent {
+    /// Slug of the link that was visited
+    pub id: String,
+    
+    /// Time of the visit
+    pub timestamp: DateTime<Utc>,
+    
+    /// Value of the `Referer` header, if any
+    pub referrer: Option<String>,
+    
+    /// Value of the `User-Agent` header, if any
+    pub user_agent: Option<String>,
+    
+    /// ISO country code of the visitor's IP, when GeoIP is configured
+    #[serde(default)]
+    pub country: Option<String>,
+}
+
+/// Request payload for purging old click events
+/// 
+/// # Example
+/// ```json
+/// { "older_than_days": 90 }
+/// ```
+#[cfg(feature = "analytics")]
+#[derive(Deserialize)]
+pub struct PurgeEventsRequest {
+    /// Events (and daily stats) older than this many days are deleted
+    /// Must be at least 1
+    pub older_than_days: u32,
+}
+
+/// Query parameters for paging through a link's click events
+/// 
+/// # Example
+/// Query string: `?limit=100&before=1768657200000000`
+#[cfg(feature = "analytics")]
+#[derive(Deserialize)]
+pub struct EventsParams {
+    /// Optional reference ID to verify that the requester owns this URL
+    pub ref_id: Option<String>,
+    
+    /// Events per page, max 1000 (default: 100)
+    pub limit: Option<usize>,
+    
+    /// Only events strictly older than this cursor (microseconds since the epoch)
+    pub before: Option<i64>,
+    
+    /// Only events strictly newer than this cursor (microseconds since the epoch)
+    pub after: Option<i64>,
+}
+
+/// Query parameters for the click heatmap
+/// 
+/// # Example
+/// Query string: `?tz=+07:00`
+#[cfg(feature = "analytics")]
+#[derive(Deserialize)]
+pub struct HeatmapParams {
+    /// Timezone offset applied before bucketing (hours or `±HH:MM`)
+    /// Defaults to UTC if not provided
+    pub tz: Option<String>,
+    
+    /// Preview token granting read access (see `preview_token`)
+    pub token: Option<String>,
+}
+
+/// Request payload for validating link destinations
+/// 
+/// # Example
+/// ```json
+/// { "ids": ["abc123", "promo"] }
+/// ```
+#[derive(Deserialize)]
+pub struct ValidateRequest {
+    /// Short URL ids whose destinations are checked
+    pub ids: Vec<String>,
+}
+
+/// Query parameters for a tenant's click summary
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123&from=2026-01-12&to=2026-01-18`
+#[cfg(feature = "analytics")]
+#[derive(Deserialize)]
+pub struct StatsSummaryParams {
+    /// Reference ID whose links are summed (required)
+    pub ref_id: Option<String>,
+
+    /// First UTC day included, `YYYY-MM-DD` (default: six days before `to`)
+    pub from: Option<String>,
+
+    /// Last UTC day included, `YYYY-MM-DD` (default: today)
+    pub to: Option<String>,
+}
+
+/// Width of the buckets returned by `GET /api/admin/creation-rate`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateBucket {
+    /// One bucket per UTC hour (default)
+    #[default]
+    Hour,
+
+    /// One bucket per UTC day
+    Day,
+}
+
+/// Query parameters for the link creation rate
+///
+/// # Example
+/// Query string: `?bucket=day&from=2026-01-01&to=2026-01-31`
+#[derive(Deserialize)]
+pub struct CreationRateParams {
+    /// Bucket width (default: hour)
+    pub bucket: Option<RateBucket>,
+
+    /// Start of the range, RFC3339 or `YYYY-MM-DD` (default: 24 buckets before `to`)
+    pub from: Option<String>,
+
+    /// End of the range, RFC3339 or `YYYY-MM-DD` (default: now)
+    pub to: Option<String>,
+}
+
+/// Preferences stored per ref_id in `TABLE_PREFERENCES`
+/// 
+/// # Example
+/// ```json
+/// { "default_limit": 50 }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Preferences {
+    /// Page size used by `list_urls` when the request has no `limit`
+    /// `None` falls back to the global default of 10
+    #[serde(default)]
+    pub default_limit: Option<usize>,
+}
+
+/// Query parameters for reading a tenant's preferences
+/// 
+/// # Example
+/// Query string: `?ref_id=user_123`
+#[derive(Deserialize)]
+pub struct PreferencesParams {
+    /// Reference ID whose preferences are returned (required)
+    pub ref_id: Option<String>,
+}
+
+/// Request payload for updating a tenant's preferences
+/// 
+/// # Example
+/// ```json
+/// { "ref_id": "user_123", "default_limit": 50 }
+/// ```
+#[derive(Deserialize)]
+pub struct UpdatePreferencesRequest {
+    /// Reference ID whose preferences are updated (required)
+    pub ref_id: Option<String>,
+
+    /// New default page size, 1 to 100; `null` clears it
+    pub default_limit: Option<usize>,
+}
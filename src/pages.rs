@@ -0,0 +1,82 @@
+//! Small HTML pages served by the public redirect endpoint
+//!
+//! Pages are rendered from inline templates; every interpolated value is
+//! HTML-escaped because destinations and messages are user-supplied.
+
+/// Escapes text for safe use inside HTML element content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Renders a countdown page that forwards to `destination` after `delay_seconds`
+///
+/// The meta refresh performs the redirect even with JavaScript disabled; the
+/// script only updates the visible counter. The destination is read from a
+/// data attribute rather than embedded in the script to avoid script injection.
+pub fn redirect_countdown(destination: &str, delay_seconds: u32) -> String {
+    let destination = escape_html(destination);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="{delay};url={destination}">
+<title>Redirecting…</title>
+</head>
+<body>
+<p>You will be redirected in <span id="countdown" data-seconds="{delay}">{delay}</span> seconds.</p>
+<p><a id="destination" href="{destination}">{destination}</a></p>
+<script>
+(function () {{
+  var counter = document.getElementById("countdown");
+  var remaining = parseInt(counter.dataset.seconds, 10);
+  var timer = setInterval(function () {{
+    remaining -= 1;
+    counter.textContent = Math.max(remaining, 0);
+    if (remaining <= 0) {{
+      clearInterval(timer);
+      window.location.href = document.getElementById("destination").href;
+    }}
+  }}, 1000);
+}})();
+</script>
+</body>
+</html>
+"#,
+        delay = delay_seconds,
+        destination = destination,
+    )
+}
+
+/// Renders the page shown when a disabled link is visited
+pub fn link_unavailable(message: &str) -> String {
+    let message = escape_html(message);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Link unavailable</title>
+</head>
+<body>
+<h1>Link unavailable</h1>
+<p>{message}</p>
+</body>
+</html>
+"#,
+        message = message,
+    )
+}
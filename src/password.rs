@@ -0,0 +1,106 @@
+//! Password protection for short links
+//!
+//! Password-protected links store a salted SHA-256 hash of their passphrase in
+//! `TABLE_LINK_PASSWORDS`. This module provides the hashing helpers and an
+//! in-memory limiter that throttles repeated wrong guesses per slug.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Hashes a link password, salting it with the slug
+///
+/// Salting with the slug means two links sharing a passphrase still store
+/// different hashes.
+pub fn hash_password(id: &str, password: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(id.as_bytes());
+    hasher.update(b":");
+    hasher.update(password.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Checks a submitted password against the stored hash for a slug
+pub fn verify_password(id: &str, password: &str, stored_hash: &str) -> bool {
+    hash_password(id, password) == stored_hash
+}
+
+/// Failed-attempt bookkeeping for a single slug
+struct AttemptState {
+    /// Wrong passwords submitted since the last reset
+    failures: u32,
+
+    /// Time of the most recent failure, used to expire stale entries
+    last_failure: Instant,
+
+    /// While set and in the future, every attempt is rejected
+    locked_until: Option<Instant>,
+}
+
+/// Throttles brute-force attempts against password-protected links
+///
+/// After `max_attempts` wrong passwords a slug is locked for `cooldown`,
+/// during which every attempt is rejected regardless of correctness.
+/// Entries expire after `cooldown` without failures, so the map only holds
+/// recently attacked slugs.
+pub struct PasswordAttemptLimiter {
+    max_attempts: u32,
+    cooldown: Duration,
+    attempts: Mutex<HashMap<String, AttemptState>>,
+}
+
+impl PasswordAttemptLimiter {
+    /// Creates a limiter allowing `max_attempts` failures before a `cooldown` lockout
+    pub fn new(max_attempts: u32, cooldown: Duration) -> Self {
+        PasswordAttemptLimiter {
+            max_attempts: max_attempts.max(1),
+            cooldown,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the remaining lockout time if the slug is currently locked
+    pub fn locked_for(&self, id: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().unwrap();
+        self.evict_expired(&mut attempts, now);
+
+        attempts
+            .get(id)
+            .and_then(|state| state.locked_until)
+            .map(|until| until.saturating_duration_since(now))
+    }
+
+    /// Records a wrong password, locking the slug once the limit is reached
+    pub fn record_failure(&self, id: &str) {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().unwrap();
+        self.evict_expired(&mut attempts, now);
+
+        let state = attempts.entry(id.to_string()).or_insert(AttemptState {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        state.failures += 1;
+        state.last_failure = now;
+
+        if state.failures >= self.max_attempts {
+            state.locked_until = Some(now + self.cooldown);
+        }
+    }
+
+    /// Clears the failure count after a successful unlock
+    pub fn reset(&self, id: &str) {
+        self.attempts.lock().unwrap().remove(id);
+    }
+
+    /// Drops lockouts that have elapsed and failure counts older than the cooldown
+    fn evict_expired(&self, attempts: &mut HashMap<String, AttemptState>, now: Instant) {
+        attempts.retain(|_, state| match state.locked_until {
+            Some(until) => until > now,
+            None => now.duration_since(state.last_failure) < self.cooldown,
+        });
+    }
+}
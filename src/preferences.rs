@@ -0,0 +1,127 @@
+//! Per-tenant preferences
+//!
+//! Preferences are keyed by ref_id in `TABLE_PREFERENCES`. Callers can only
+//! read and write their own: in JWT mode the token's subject always wins over
+//! the requested ref_id.
+
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use redb::ReadTransaction;
+use serde_json::json;
+
+use crate::database::{AppState, TABLE_PREFERENCES};
+use crate::error::AppError;
+use crate::handler::effective_ref_id;
+use crate::middleware::AuthSubject;
+use crate::model::{Preferences, PreferencesParams, UpdatePreferencesRequest};
+
+/// Page size used when neither the request nor the tenant sets one
+pub const DEFAULT_PAGE_LIMIT: usize = 10;
+
+/// Largest page size a list request may use
+pub const MAX_PAGE_LIMIT: usize = 100;
+
+/// Reads a tenant's preferences, defaulting when none (or unreadable ones) are stored
+pub fn load(read_txn: &ReadTransaction, ref_id: &str) -> Result<Preferences, redb::Error> {
+    let table = read_txn.open_table(TABLE_PREFERENCES)?;
+    Ok(table
+        .get(ref_id)?
+        .and_then(|value| serde_json::from_str(value.value()).ok())
+        .unwrap_or_default())
+}
+
+/// Builds the 400 response for a request without a usable ref_id
+fn missing_ref_id() -> axum::response::Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "error": "ref_id is required",
+            "code": "missing_ref_id"
+        })),
+    )
+        .into_response()
+}
+
+/// Returns the caller's preferences
+///
+/// # Example Request
+///
+/// `GET /api/preferences?ref_id=user_123`
+///
+/// # Response
+///
+/// - **200 OK** - `{"ref_id": "user_123", "default_limit": 50}` (`null` when unset)
+/// - **400 Bad Request** - `ref_id` missing
+pub async fn get_preferences(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Query(params): Query<PreferencesParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(ref_id) = effective_ref_id(subject, params.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok(missing_ref_id());
+    };
+
+    let read_txn = state.begin_read()?;
+    let preferences = load(&read_txn, &ref_id)?;
+
+    Ok(Json(json!({
+        "ref_id": ref_id,
+        "default_limit": preferences.default_limit,
+    }))
+    .into_response())
+}
+
+/// Replaces the caller's preferences
+///
+/// # Request Body
+///
+/// ```json
+/// { "ref_id": "user_123", "default_limit": 50 }
+/// ```
+///
+/// # Response
+///
+/// - **200 OK** - The stored preferences, in the same shape as `GET`
+/// - **400 Bad Request** - `ref_id` missing, or `default_limit` outside 1 to 100
+pub async fn update_preferences(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<UpdatePreferencesRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let Some(ref_id) = effective_ref_id(subject, payload.ref_id).filter(|ref_id| !ref_id.is_empty()) else {
+        return Ok(missing_ref_id());
+    };
+
+    if payload.default_limit.is_some_and(|limit| !(1..=MAX_PAGE_LIMIT).contains(&limit)) {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("default_limit must be between 1 and {}", MAX_PAGE_LIMIT),
+                "code": "invalid_limit"
+            })),
+        )
+            .into_response());
+    }
+
+    let preferences = Preferences {
+        default_limit: payload.default_limit,
+    };
+    let preferences_json = serde_json::to_string(&preferences)?;
+
+    let write_txn = state.begin_write()?;
+    {
+        let mut table = write_txn.open_table(TABLE_PREFERENCES)?;
+        table.insert(ref_id.as_str(), preferences_json.as_str())?;
+    }
+    write_txn.commit()?;
+
+    Ok(Json(json!({
+        "ref_id": ref_id,
+        "default_limit": preferences.default_limit,
+    }))
+    .into_response())
+}
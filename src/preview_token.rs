@@ -0,0 +1,203 @@
+//! Signed, time-limited tokens for sharing a link's stats
+//!
+//! A preview token is an HS256 JWT signed with `PREVIEW_TOKEN_SECRET`, whose
+//! `sub` is the slug and `scope` is `stats`. Passing it as `?token=` to the
+//! stats endpoints of that slug grants read-only access without the API
+//! credentials or the owner's ref_id. Tokens cannot be revoked individually;
+//! keep their lifetime short or rotate the secret.
+
+use axum::{
+    extract::{Extension, Path, Request, State},
+    http::{Method, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::database::{load_record, AppState, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::effective_ref_id;
+use crate::middleware::AuthSubject;
+use crate::model::PreviewTokenRequest;
+
+/// Lifetime of a token when the request does not set one (one hour)
+pub const DEFAULT_PREVIEW_TOKEN_TTL_SECS: u64 = 3600;
+
+/// Longest lifetime a token may be minted with (seven days)
+pub const MAX_PREVIEW_TOKEN_TTL_SECS: u64 = 7 * 24 * 3600;
+
+/// The only scope tokens are minted with
+const STATS_SCOPE: &str = "stats";
+
+/// Claims carried by a preview token
+#[derive(Serialize, Deserialize)]
+struct PreviewClaims {
+    /// Slug whose stats the token opens
+    sub: String,
+
+    /// What the token grants; always `stats`
+    scope: String,
+
+    /// Expiry, seconds since the Unix epoch
+    exp: i64,
+}
+
+/// Signs a token granting read access to the stats of `id` until `expires_at`
+pub fn mint(secret: &str, id: &str, expires_at: DateTime<Utc>) -> String {
+    let claims = PreviewClaims {
+        sub: id.to_string(),
+        scope: STATS_SCOPE.to_string(),
+        exp: expires_at.timestamp(),
+    };
+
+    // Encoding plain claims with an HMAC key cannot fail
+    encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+}
+
+/// Whether `token` is a valid, unexpired stats token for `id`
+pub fn verify(secret: &str, token: &str, id: &str) -> bool {
+    let mut validation = Validation::new(Algorithm::HS256);
+    // Shared links should stop working exactly when promised
+    validation.leeway = 0;
+
+    decode::<PreviewClaims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)
+        .is_ok_and(|data| data.claims.sub == id && data.claims.scope == STATS_SCOPE)
+}
+
+/// Whether a request targets a stats endpoint with a `token` query parameter
+///
+/// Such requests skip `auth_middleware`; the handler then verifies the token
+/// and rejects the request with 401 if it is invalid.
+pub fn is_token_request(request: &Request) -> bool {
+    if request.method() != Method::GET {
+        return false;
+    }
+
+    let path = request.uri().path();
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    let is_stats_path = matches!(
+        path.trim_start_matches('/').split('/').collect::<Vec<_>>().as_slice(),
+        ["urls", id, "stats" | "heatmap"] if !id.is_empty()
+    );
+
+    is_stats_path
+        && url::form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+            .any(|(key, value)| key == "token" && !value.is_empty())
+}
+
+/// Outcome of checking a stats request's optional `?token=`
+pub enum TokenCheck {
+    /// No token was supplied; the usual ownership rules apply
+    Absent,
+
+    /// A valid token for this slug was supplied
+    Granted,
+
+    /// The token is invalid, expired, for another slug, or tokens are disabled
+    Rejected(Response),
+}
+
+/// Checks the `token` query parameter of a stats request for `id`
+pub fn check(state: &AppState, token: Option<&str>, id: &str) -> TokenCheck {
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return TokenCheck::Absent;
+    };
+
+    let valid = state
+        .config
+        .preview_token_secret
+        .as_deref()
+        .is_some_and(|secret| verify(secret, token, id));
+    if valid {
+        return TokenCheck::Granted;
+    }
+
+    TokenCheck::Rejected(
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "The preview token is invalid or has expired",
+                "code": "invalid_token"
+            })),
+        )
+            .into_response(),
+    )
+}
+
+/// Mints a preview token for sharing a link's stats
+///
+/// # Request Body
+///
+/// ```json
+/// { "ref_id": "user_123", "ttl_seconds": 3600 }
+/// ```
+///
+/// `ttl_seconds` defaults to one hour and may not exceed seven days.
+///
+/// # Response
+///
+/// - **200 OK** - `{"token", "expires_at", "stats_url"}`
+/// - **400 Bad Request** - `ttl_seconds` is 0 or longer than seven days
+/// - **403 Forbidden** - ref_id does not match the owner
+/// - **404 Not Found** - URL does not exist
+/// - **503 Service Unavailable** - `PREVIEW_TOKEN_SECRET` is not configured
+pub async fn mint_preview_token(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<PreviewTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let error = |status: StatusCode, error: &str, code: &str| {
+        (status, Json(json!({ "error": error, "code": code }))).into_response()
+    };
+
+    let Some(secret) = state.config.preview_token_secret.as_deref() else {
+        return Ok(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Preview tokens are disabled; set PREVIEW_TOKEN_SECRET",
+            "preview_tokens_disabled",
+        ));
+    };
+
+    let ttl = payload.ttl_seconds.unwrap_or(DEFAULT_PREVIEW_TOKEN_TTL_SECS);
+    if !(1..=MAX_PREVIEW_TOKEN_TTL_SECS).contains(&ttl) {
+        return Ok(error(
+            StatusCode::BAD_REQUEST,
+            &format!("ttl_seconds must be between 1 and {}", MAX_PREVIEW_TOKEN_TTL_SECS),
+            "invalid_ttl",
+        ));
+    }
+
+    let record = {
+        let read_txn = state.begin_read()?;
+        let table = read_txn.open_table(TABLE_URLS)?;
+        load_record(&table, &id)?
+    };
+    let Some(record) = record else {
+        return Ok(error(StatusCode::NOT_FOUND, "URL not found", "not_found"));
+    };
+
+    let ref_id = effective_ref_id(subject, payload.ref_id);
+    if let Some(request_ref_id) = &ref_id {
+        if record.ref_id.as_ref() != Some(request_ref_id) {
+            return Ok(error(
+                StatusCode::FORBIDDEN,
+                "You are not authorized to share this link's stats",
+                "forbidden",
+            ));
+        }
+    }
+
+    let expires_at = Utc::now() + Duration::seconds(ttl as i64);
+    let token = mint(secret, &id, expires_at);
+
+    Ok(Json(json!({
+        "stats_url": format!("/api/urls/{}/stats?token={}", id, token),
+        "token": token,
+        "expires_at": expires_at,
+    }))
+    .into_response())
+}
@@ -0,0 +1,110 @@
+//! QR code rendering for short links
+//!
+//! Produces PNG images encoding a link's `short_url`, either individually or
+//! packed into a ZIP archive for bulk export, and SVG images for print.
+
+use image::{ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use std::fmt;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Default pixel size of a single QR module
+pub const DEFAULT_MODULE_SIZE: u32 = 8;
+
+/// Smallest module size accepted from `?size=`
+pub const MIN_MODULE_SIZE: u32 = 1;
+
+/// Largest module size accepted from `?size=`, keeping images a few MB at most
+pub const MAX_MODULE_SIZE: u32 = 32;
+
+/// Errors that can occur while rendering QR codes or archives
+#[derive(Debug)]
+pub enum QrError {
+    /// The data could not be encoded (e.g. too long for a QR code)
+    Encode(qrcode::types::QrError),
+
+    /// The rendered image could not be written as PNG
+    Image(image::ImageError),
+
+    /// The ZIP archive could not be written
+    Archive(zip::result::ZipError),
+}
+
+impl fmt::Display for QrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QrError::Encode(err) => write!(f, "failed to encode QR code: {}", err),
+            QrError::Image(err) => write!(f, "failed to render QR image: {}", err),
+            QrError::Archive(err) => write!(f, "failed to build QR archive: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for QrError {}
+
+/// Renders `data` as a PNG QR code with `module_size` pixels per module
+pub fn render_png(data: &str, module_size: u32) -> Result<Vec<u8>, QrError> {
+    let code = QrCode::new(data.as_bytes()).map_err(QrError::Encode)?;
+    let image = code
+        .render::<Luma<u8>>()
+        .module_dimensions(module_size, module_size)
+        .build();
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(QrError::Image)?;
+
+    Ok(png)
+}
+
+/// Default color of dark modules in SVG output
+pub const DEFAULT_SVG_COLOR: &str = "#000000";
+
+/// Parses a `color` of 3 or 6 hex digits, with or without a leading `#`
+///
+/// Returns the color as `#rrggbb` or `#rgb`, safe to embed in SVG markup.
+pub fn parse_hex_color(value: &str) -> Option<String> {
+    let digits = value.strip_prefix('#').unwrap_or(value);
+    let valid = matches!(digits.len(), 3 | 6) && digits.chars().all(|c| c.is_ascii_hexdigit());
+    valid.then(|| format!("#{}", digits.to_ascii_lowercase()))
+}
+
+/// Renders `data` as an SVG QR code with dark modules in `color`
+///
+/// `color` is embedded verbatim, so it must come from `parse_hex_color`.
+/// The background stays white for scanner contrast.
+pub fn render_svg(data: &str, color: &str) -> Result<String, QrError> {
+    let code = QrCode::new(data.as_bytes()).map_err(QrError::Encode)?;
+    Ok(code
+        .render::<svg::Color>()
+        .module_dimensions(DEFAULT_MODULE_SIZE, DEFAULT_MODULE_SIZE)
+        .dark_color(svg::Color(color))
+        .light_color(svg::Color("#ffffff"))
+        .build())
+}
+
+/// Packs one PNG QR code per `(slug, short_url)` pair into a ZIP archive
+///
+/// Each entry is named `{slug}.png`.
+pub fn render_zip_bundle(links: &[(String, String)], module_size: u32) -> Result<Vec<u8>, QrError> {
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    // PNGs are already compressed, so storing them avoids wasted CPU
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    for (slug, short_url) in links {
+        let png = render_png(short_url, module_size)?;
+        archive
+            .start_file(format!("{}.png", slug), options)
+            .map_err(QrError::Archive)?;
+        archive
+            .write_all(&png)
+            .map_err(|err| QrError::Archive(err.into()))?;
+    }
+
+    let cursor = archive.finish().map_err(QrError::Archive)?;
+    Ok(cursor.into_inner())
+}
@@ -0,0 +1,176 @@
+//! Per-caller request rate limiting for the API
+//!
+//! Each caller gets a token bucket holding up to `burst` tokens, refilled at
+//! `RATE_LIMIT_PER_MINUTE`. Every API request consumes one token; callers with
+//! an empty bucket are rejected with 429 until a token is refilled.
+//!
+//! Rejections carry a jittered `Retry-After` (see `retry_after_with_jitter`)
+//! so callers limited at the same moment do not all retry at the same moment.
+
+use axum::{
+    extract::{ConnectInfo, Extension, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rand::Rng;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::database::AppState;
+use crate::geoip::trusted_client_ip;
+use crate::middleware::{ApiKey, AuthSubject};
+
+/// Number of tracked callers above which idle buckets are evicted
+const EVICT_THRESHOLD: usize = 1024;
+
+/// Most extra delay added to a `Retry-After`, as a fraction of the base wait
+pub const RETRY_AFTER_JITTER: f64 = 0.5;
+
+/// Seconds to send in `Retry-After` for a client that must wait `base`
+///
+/// The base is rounded up to whole seconds (at least 1), then a random
+/// spread of up to `RETRY_AFTER_JITTER` of it (at least 1 second) is added,
+/// so the result lies in `base..=base + spread`.
+pub fn retry_after_with_jitter(base: Duration) -> u64 {
+    let base = (base.as_secs_f64().ceil() as u64).max(1);
+    let spread = ((base as f64 * RETRY_AFTER_JITTER).ceil() as u64).max(1);
+    base + rand::rng().random_range(0..=spread)
+}
+
+/// Token state of a single caller
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket limiter keyed by caller identity
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `per_minute` requests with bursts of `burst`
+    ///
+    /// A `per_minute` of 0 disables limiting.
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        RateLimiter {
+            capacity: f64::from(burst.max(1)),
+            refill_per_sec: f64::from(per_minute) / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether requests are limited at all
+    pub fn is_enabled(&self) -> bool {
+        self.refill_per_sec > 0.0
+    }
+
+    /// Takes a token for `key`, or returns how long until one is available
+    pub fn try_acquire(&self, key: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > EVICT_THRESHOLD {
+            // A bucket idle long enough to be full again carries no state
+            let full_after = Duration::from_secs_f64(self.capacity / self.refill_per_sec);
+            buckets.retain(|_, bucket| now.duration_since(bucket.last_refill) < full_after);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec))
+        }
+    }
+}
+
+/// Identifies the caller a request is charged to
+///
+/// The verified JWT subject when present, otherwise the fingerprint of the
+/// `ApiKey` used, otherwise the client address from `trusted_client_ip`, so
+/// `X-Forwarded-For` only counts when it comes from a `TRUSTED_PROXIES` peer.
+/// Requests without any of these (e.g. no socket address) share a single
+/// anonymous bucket. A `ref_id` in the query string is never trusted here,
+/// since any client can claim one.
+fn caller_key(
+    state: &AppState,
+    request: &Request,
+    subject: Option<&AuthSubject>,
+    api_key: Option<&ApiKey>,
+    peer: Option<SocketAddr>,
+) -> (String, Option<String>) {
+    if let Some(AuthSubject(subject)) = subject {
+        return (format!("ref:{}", subject), Some(subject.clone()));
+    }
+
+    if let Some(api_key) = api_key {
+        return (format!("key:{}", api_key.fingerprint()), None);
+    }
+
+    match trusted_client_ip(request.headers(), peer, &state.config.trusted_proxies) {
+        Some(address) => (format!("ip:{}", address), None),
+        None => ("anonymous".to_string(), None),
+    }
+}
+
+/// Middleware enforcing `RATE_LIMIT_PER_MINUTE` on API requests
+///
+/// Runs after authentication so JWT subjects are known. Callers whose JWT
+/// subject is listed in `RATE_LIMIT_EXEMPT_REF_IDS` skip the bucket entirely;
+/// the exemption is checked before any token is consumed. Without a verified
+/// subject no caller is exempt.
+pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    api_key: Option<Extension<ApiKey>>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    if !state.rate_limiter.is_enabled() {
+        return Ok(next.run(request).await);
+    }
+
+    let (key, ref_id) = caller_key(
+        &state,
+        &request,
+        subject.as_ref().map(|Extension(subject)| subject),
+        api_key.as_ref().map(|Extension(api_key)| api_key),
+        connect_info.map(|Extension(ConnectInfo(peer))| peer),
+    );
+    let exempt = ref_id.is_some_and(|ref_id| state.config.rate_limit_exempt_ref_ids.contains(&ref_id));
+
+    if !exempt {
+        if let Err(wait) = state.rate_limiter.try_acquire(&key) {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after_with_jitter(wait).to_string())],
+                Json(json!({
+                    "error": "Too Many Requests",
+                    "message": "Rate limit exceeded, please retry later",
+                    "code": "rate_limited"
+                })),
+            )
+                .into_response());
+        }
+    }
+
+    Ok(next.run(request).await)
+}
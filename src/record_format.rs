@@ -0,0 +1,219 @@
+//! Encodings for records stored in `TABLE_URLS`
+//!
+//! `RECORD_FORMAT` picks how new and updated records are written:
+//!
+//! - `json` (default) - Plain JSON, readable with any redb viewer
+//! - `bincode` - Compact binary, smaller and faster to (de)serialize
+//!
+//! Values are self-describing: bincode values carry a `bincode:` prefix in
+//! front of their base64 encoding (table values are strings), so `decode`
+//! reads either format whatever the setting is. At startup
+//! `convert_records` rewrites every record still in the other format, which
+//! is the migration path in both directions.
+//!
+//! Bincode is not self-describing: fields are stored by position, so a
+//! record written before a field was appended to `UrlRecord` no longer
+//! decodes. A release that appends fields therefore also appends a migration
+//! re-encoding such records (see `migrations::reencode_bincode_records`);
+//! fields must only ever be appended, never reordered or removed.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use redb::{Database, ReadableTable};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::database::TABLE_URLS;
+use crate::migrations::TABLE_META;
+use crate::model::UrlRecord;
+
+/// Prefix marking a bincode value
+const BINCODE_PREFIX: &str = "bincode:";
+
+/// Key in `TABLE_META` holding the format every record was last converted to
+const RECORD_FORMAT_KEY: &str = "record_format";
+
+/// Error for a stored value that cannot be decoded as a `UrlRecord`
+#[derive(Debug)]
+pub struct DecodeError(String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid stored record: {}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<bincode::Error> for DecodeError {
+    fn from(err: bincode::Error) -> Self {
+        DecodeError(err.to_string())
+    }
+}
+
+/// Lets code inside a write transaction treat an unreadable record like any
+/// other non-retryable storage failure
+impl From<DecodeError> for redb::Error {
+    fn from(err: DecodeError) -> Self {
+        redb::Error::Corrupted(err.to_string())
+    }
+}
+
+/// Converts records to and from the strings stored in `TABLE_URLS`
+pub trait RecordCodec: Send + Sync {
+    /// Encodes `record` for storage
+    fn encode(&self, record: &UrlRecord) -> String;
+
+    /// Decodes a value written by `encode`
+    fn decode(&self, value: &str) -> Result<UrlRecord, DecodeError>;
+}
+
+/// Records as JSON objects
+#[derive(Debug, Default)]
+pub struct JsonCodec;
+
+impl RecordCodec for JsonCodec {
+    fn encode(&self, record: &UrlRecord) -> String {
+        serde_json::to_string(record).unwrap()
+    }
+
+    fn decode(&self, value: &str) -> Result<UrlRecord, DecodeError> {
+        serde_json::from_str(value).map_err(|err| DecodeError(err.to_string()))
+    }
+}
+
+/// Records as bincode, base64-encoded behind `bincode:`
+#[derive(Debug, Default)]
+pub struct BincodeCodec;
+
+impl RecordCodec for BincodeCodec {
+    fn encode(&self, record: &UrlRecord) -> String {
+        format!("{}{}", BINCODE_PREFIX, STANDARD.encode(bincode::serialize(record).unwrap()))
+    }
+
+    fn decode(&self, value: &str) -> Result<UrlRecord, DecodeError> {
+        Ok(bincode::deserialize(&bincode_payload(value)?)?)
+    }
+}
+
+/// The raw bincode bytes of a value written by `BincodeCodec`
+pub(crate) fn bincode_payload(value: &str) -> Result<Vec<u8>, DecodeError> {
+    let encoded = value
+        .strip_prefix(BINCODE_PREFIX)
+        .ok_or_else(|| DecodeError("missing bincode prefix".to_string()))?;
+    STANDARD.decode(encoded).map_err(|err| DecodeError(err.to_string()))
+}
+
+/// Storage formats for records, selected by `RECORD_FORMAT`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// See `JsonCodec`
+    #[default]
+    Json,
+
+    /// See `BincodeCodec`
+    Bincode,
+}
+
+impl RecordFormat {
+    /// The codec implementing this format
+    pub fn codec(self) -> &'static dyn RecordCodec {
+        match self {
+            RecordFormat::Json => &JsonCodec,
+            RecordFormat::Bincode => &BincodeCodec,
+        }
+    }
+
+    /// The format a stored value was written in
+    pub fn of(value: &str) -> Self {
+        if value.starts_with(BINCODE_PREFIX) {
+            RecordFormat::Bincode
+        } else {
+            RecordFormat::Json
+        }
+    }
+
+    /// Encodes `record` in this format
+    pub fn encode(self, record: &UrlRecord) -> String {
+        self.codec().encode(record)
+    }
+
+    /// Number stored in `TABLE_META`
+    fn id(self) -> u64 {
+        match self {
+            RecordFormat::Json => 0,
+            RecordFormat::Bincode => 1,
+        }
+    }
+}
+
+/// Error for an unrecognized `RECORD_FORMAT` value
+#[derive(Debug)]
+pub struct ParseRecordFormatError;
+
+impl fmt::Display for ParseRecordFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected `json` or `bincode`")
+    }
+}
+
+impl std::error::Error for ParseRecordFormatError {}
+
+impl FromStr for RecordFormat {
+    type Err = ParseRecordFormatError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(RecordFormat::Json),
+            "bincode" => Ok(RecordFormat::Bincode),
+            _ => Err(ParseRecordFormatError),
+        }
+    }
+}
+
+/// Decodes a stored record in whichever format it was written
+pub fn decode(value: &str) -> Result<UrlRecord, DecodeError> {
+    RecordFormat::of(value).codec().decode(value)
+}
+
+/// Rewrites every record not yet in `format`, returning how many changed
+///
+/// Runs in one write transaction and is skipped when `TABLE_META` shows the
+/// records were already converted to `format`. Values that cannot be
+/// decoded are left untouched.
+pub fn convert_records(db: &Database, format: RecordFormat) -> Result<usize, redb::Error> {
+    let write_txn = db.begin_write()?;
+    let current = {
+        let meta = write_txn.open_table(TABLE_META)?;
+        let current = meta.get(RECORD_FORMAT_KEY)?.map(|value| value.value());
+        current.unwrap_or(RecordFormat::Json.id())
+    };
+    if current == format.id() {
+        write_txn.abort()?;
+        return Ok(0);
+    }
+
+    let converted = {
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+        let mut rewritten = Vec::new();
+        for entry in table_main.iter()? {
+            let (key, value) = entry?;
+            if RecordFormat::of(value.value()) != format {
+                if let Ok(record) = decode(value.value()) {
+                    rewritten.push((key.value().to_string(), format.encode(&record)));
+                }
+            }
+        }
+
+        for (key, value) in &rewritten {
+            table_main.insert(key.as_str(), value.as_str())?;
+        }
+        rewritten.len()
+    };
+    {
+        let mut meta = write_txn.open_table(TABLE_META)?;
+        meta.insert(RECORD_FORMAT_KEY, format.id())?;
+    }
+    write_txn.commit()?;
+
+    Ok(converted)
+}
@@ -0,0 +1,100 @@
+//! Live feed of redirects for operators
+//!
+//! Every served redirect is published on a broadcast channel held in
+//! `AppState::redirect_log`; `GET /api/admin/redirect-log/stream` relays it
+//! as server-sent events. Nothing is stored: subscribers only see redirects
+//! that happen while they are connected, and a subscriber that falls more
+//! than `REDIRECT_LOG_CAPACITY` events behind skips the missed ones.
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, error::RecvError};
+use url::Url;
+
+use crate::database::AppState;
+
+/// Events buffered per subscriber before the slowest ones start skipping
+pub const REDIRECT_LOG_CAPACITY: usize = 256;
+
+/// One served redirect, as pushed to the admin stream
+#[derive(Clone, Debug, Serialize)]
+pub struct RedirectLogEvent {
+    /// Slug of the link that was visited
+    pub id: String,
+
+    /// Host of the destination the visitor was sent to
+    pub destination_host: Option<String>,
+
+    /// Time of the redirect
+    pub timestamp: DateTime<Utc>,
+
+    /// Value of the `Referer` header, if any
+    pub referrer: Option<String>,
+
+    /// Value of the `User-Agent` header, if any
+    pub user_agent: Option<String>,
+
+    /// ISO country code of the visitor's IP, when GeoIP is configured
+    pub country: Option<String>,
+
+    /// Whether the click was counted (false for `no_count` visits and
+    /// clicks dropped under load)
+    pub counted: bool,
+}
+
+/// Creates the sending half shared through `AppState::redirect_log`
+pub fn channel() -> broadcast::Sender<RedirectLogEvent> {
+    broadcast::channel(REDIRECT_LOG_CAPACITY).0
+}
+
+/// Host part of a destination URL, if it parses
+pub fn destination_host(destination: &str) -> Option<String> {
+    Url::parse(destination)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+}
+
+/// Streams redirects as they are served (server-sent events)
+///
+/// Each redirect is sent as a `redirect` event whose data is a JSON
+/// `RedirectLogEvent`. A comment is sent every 15 seconds to keep idle
+/// connections open. When the subscriber falls behind, a `lagged` event
+/// carries the number of skipped redirects.
+///
+/// # Example Request
+///
+/// `GET /api/admin/redirect-log/stream`
+///
+/// # Response
+///
+/// - **200 OK** - `text/event-stream`, e.g.
+///
+/// ```text
+/// event: redirect
+/// data: {"id":"abc123","destination_host":"example.com","timestamp":"...","referrer":null,"user_agent":"curl/8.5.0","country":null,"counted":true}
+/// ```
+pub async fn redirect_log_stream(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.redirect_log.subscribe();
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(redirect) => Event::default()
+                .event("redirect")
+                .json_data(&redirect)
+                .unwrap(),
+            Err(RecvError::Lagged(skipped)) => Event::default().event("lagged").data(skipped.to_string()),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), receiver))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,221 @@
+//! Temporary slug reservations
+//!
+//! A tenant can hold a custom slug for a while (e.g. while a campaign is
+//! being prepared) before creating the link. Reservations live in
+//! `TABLE_RESERVATIONS`; a live one blocks everyone but its owner from
+//! claiming the slug, and is consumed when the owner creates the link.
+//!
+//! Expired reservations no longer block anything, and a background task
+//! (`spawn_sweeper`, every `RESERVATION_SWEEP_SECS`) deletes them so the
+//! table does not grow without bound.
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use redb::{Database, ReadTransaction, ReadableTable, WriteTransaction};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::database::{AppState, TABLE_ALIASES, TABLE_RESERVATIONS, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::effective_ref_id;
+use crate::middleware::AuthSubject;
+use crate::model::{ReserveRequest, Reservation};
+use crate::slug;
+
+/// Reservation lifetime when the request does not set one
+pub const DEFAULT_RESERVATION_TTL_SECS: u64 = 900;
+
+/// Longest reservation a request may ask for (one day)
+pub const MAX_RESERVATION_TTL_SECS: u64 = 86_400;
+
+/// Returns the reservation held on `slug`, if any and not yet expired
+fn live_reservation(
+    write_txn: &WriteTransaction,
+    slug: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<Reservation>, redb::Error> {
+    let table = write_txn.open_table(TABLE_RESERVATIONS)?;
+    let Some(value) = table.get(slug)? else {
+        return Ok(None);
+    };
+    Ok(serde_json::from_str::<Reservation>(value.value())
+        .ok()
+        .filter(|reservation| reservation.expires_at > now))
+}
+
+/// Returns whether someone other than `owner` holds a live reservation on `slug`
+pub fn held_by_other(read_txn: &ReadTransaction, slug: &str, owner: Option<&str>) -> Result<bool, redb::Error> {
+    let table = read_txn.open_table(TABLE_RESERVATIONS)?;
+    let Some(value) = table.get(slug)? else {
+        return Ok(false);
+    };
+    Ok(serde_json::from_str::<Reservation>(value.value())
+        .is_ok_and(|reservation| reservation.expires_at > Utc::now() && reservation.ref_id.as_deref() != owner))
+}
+
+/// Claims `slug` for `owner` inside a write transaction
+///
+/// Returns `false` when someone else holds a live reservation on it.
+/// Otherwise any reservation (the owner's own, or an expired one) is
+/// consumed and `true` is returned.
+pub fn claim(write_txn: &WriteTransaction, slug: &str, owner: Option<&str>) -> Result<bool, redb::Error> {
+    if let Some(reservation) = live_reservation(write_txn, slug, Utc::now())? {
+        if reservation.ref_id.as_deref() != owner {
+            return Ok(false);
+        }
+    }
+
+    let mut table = write_txn.open_table(TABLE_RESERVATIONS)?;
+    table.remove(slug)?;
+    Ok(true)
+}
+
+/// Deletes every reservation that expired before `now`, returning how many
+pub fn sweep_expired(db: &Database, now: DateTime<Utc>) -> Result<usize, redb::Error> {
+    let write_txn = db.begin_write()?;
+    let removed = {
+        let mut table = write_txn.open_table(TABLE_RESERVATIONS)?;
+        let expired: Vec<String> = table
+            .iter()?
+            .filter_map(|entry| entry.ok())
+            .filter(|(_, value)| {
+                // Unreadable entries can never be honoured, so they go too
+                serde_json::from_str::<Reservation>(value.value())
+                    .map_or(true, |reservation| reservation.expires_at <= now)
+            })
+            .map(|(slug, _)| slug.value().to_string())
+            .collect();
+
+        for slug in &expired {
+            table.remove(slug.as_str())?;
+        }
+        expired.len()
+    };
+    write_txn.commit()?;
+
+    Ok(removed)
+}
+
+/// Runs `sweep_expired` every `every` until `shutdown` flips to `true`
+pub fn spawn_sweeper(db: Arc<Database>, every: std::time::Duration, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let db = db.clone();
+                    // redb I/O is blocking, so keep it off the async workers
+                    match tokio::task::spawn_blocking(move || sweep_expired(&db, Utc::now())).await {
+                        Ok(Ok(0)) => {}
+                        Ok(Ok(removed)) => tracing::debug!("Swept {} expired slug reservations", removed),
+                        Ok(Err(err)) => tracing::warn!("Failed to sweep slug reservations: {}", err),
+                        Err(err) => tracing::warn!("Slug reservation sweep panicked: {}", err),
+                    }
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+    })
+}
+
+/// Reserves a custom slug for later creation
+///
+/// # Request Body
+///
+/// ```json
+/// { "id": "spring-sale", "ref_id": "user_123", "ttl_seconds": 3600 }
+/// ```
+///
+/// `ttl_seconds` defaults to 900 and may be at most 86400. Reserving a slug
+/// you already hold extends it.
+///
+/// # Response
+///
+/// - **201 Created** - `{"id", "ref_id", "expires_at"}`
+/// - **400 Bad Request** - The slug breaks the custom id rules, or `ttl_seconds` is out of range
+/// - **409 Conflict** - The slug is used by a link or alias, or reserved by someone else
+pub async fn reserve_slug(
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<ReserveRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let bad_request = |error: String, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": error,
+                "code": code
+            })),
+        )
+            .into_response()
+    };
+
+    let ref_id = effective_ref_id(subject, payload.ref_id);
+
+    let requested = payload.id.trim();
+    if requested.is_empty() {
+        return Ok(bad_request("id is required".to_string(), "invalid_custom_id"));
+    }
+    let id = match slug::custom(&state.config, requested) {
+        Ok(id) => id,
+        Err(err) => return Ok(bad_request(err.to_string(), "invalid_custom_id")),
+    };
+
+    let ttl = payload.ttl_seconds.unwrap_or(DEFAULT_RESERVATION_TTL_SECS);
+    if !(1..=MAX_RESERVATION_TTL_SECS).contains(&ttl) {
+        return Ok(bad_request(
+            format!("ttl_seconds must be between 1 and {}", MAX_RESERVATION_TTL_SECS),
+            "invalid_ttl",
+        ));
+    }
+
+    let now = Utc::now();
+    let reservation = Reservation {
+        ref_id,
+        expires_at: now + Duration::seconds(ttl as i64),
+    };
+
+    let write_txn = state.begin_write()?;
+    {
+        let table_main = write_txn.open_table(TABLE_URLS)?;
+        let table_aliases = write_txn.open_table(TABLE_ALIASES)?;
+        let held_by_other = live_reservation(&write_txn, &id, now)?
+            .is_some_and(|existing| existing.ref_id != reservation.ref_id);
+
+        if table_main.get(id.as_str())?.is_some()
+            || table_aliases.get(id.as_str())?.is_some()
+            || held_by_other
+        {
+            return Ok((
+                StatusCode::CONFLICT,
+                Json(json!({
+                    "error": "This id is already taken or reserved",
+                    "code": "id_taken"
+                })),
+            )
+                .into_response());
+        }
+
+        let mut table = write_txn.open_table(TABLE_RESERVATIONS)?;
+        table
+            .insert(id.as_str(), serde_json::to_string(&reservation)?.as_str())?;
+    }
+    write_txn.commit()?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": id,
+            "ref_id": reservation.ref_id,
+            "expires_at": reservation.expires_at,
+        })),
+    )
+        .into_response())
+}
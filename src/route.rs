@@ -3,24 +3,100 @@
 //! This module configures all HTTP routes and maps them to their respective handlers.
 //! It creates the Axum router with the application state.
 
-use axum::routing::{delete, get};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post};
 use axum::Router;
+use tower_http::timeout::TimeoutLayer;
 
+#[cfg(feature = "analytics")]
+use crate::analytics::{click_heatmap, list_click_events, purge_events, stats_summary};
+use crate::config::Config;
+use crate::counters::{get_counter, increment_counter};
+use crate::creation_stats::creation_rate;
 use crate::database::AppState;
-use crate::handler::{create_short_url, delete_short_url, list_urls, redirect_url};
+use crate::handler::{
+    auth_check, auth_required, batch_create_with_defaults, bulk_create, create_bundle, create_short_url,
+    delete_short_url, export_csv, get_url_stats, kill_link, leaderboard, oldest_links, link_jsonld, link_qr,
+    link_qr_svg, list_destinations, list_tags, list_urls, list_urls_by_date, merge_links, preview_short_url, qr_bundle,
+    raw_record, redirect_url, rename_short_url, resolve_short_url, rotate_all, slug_capacity, toggle_link,
+    validate_slug,
+};
+use crate::history::link_audit;
+use crate::linkcheck::validate_destinations;
+use crate::metrics::get_metrics;
+use crate::preferences::{get_preferences, update_preferences};
+use crate::preview_token::mint_preview_token;
+use crate::ratelimit::rate_limit_middleware;
+use crate::redirect_log::redirect_log_stream;
+use crate::reservations::reserve_slug;
+use crate::webhook::set_click_webhook;
 
 use axum::middleware;
-use crate::middleware::auth_middleware;
+use crate::middleware::{admin_middleware, auth_middleware, https_redirect_middleware, request_id_middleware, write_limit_middleware};
 
 /// Creates and configures the Axum application router with all routes
 /// 
 /// # Route Definitions
 /// 
-/// - `GET /{id}` - Redirects to the original URL (public endpoint)
+/// - `GET /{id}` - Redirects to the original URL (public endpoint; absent with `DISABLE_REDIRECT_ROUTE=true`)
+/// - `GET /{id}/` - The same, with `TRIM_TRAILING_SLASH=true`
 /// - `GET /api/urls` - Lists URLs with pagination (requires ref_id query param)
 /// - `POST /api/urls` - Creates a new short URL
+/// - `POST /api/urls/batch-with-defaults` - Creates many links sharing ref_id, tags and expiry
+/// - `POST /api/bundles` - Creates a bundle slug that redirects to a random member URL
+/// - `POST /api/urls/bulk` - Imports up to `MAX_BATCH_SIZE` links in a single transaction
+/// - `GET /api/urls/preview-url` - Computes the short URL for a slug without creating it
+/// - `GET /api/urls/validate-slug` - Checks a prospective custom slug against every rule
+/// - `POST /api/urls/reserve` - Holds a custom slug for later creation
+/// - `GET /api/urls/resolve` - Looks up a link by its full short URL
+/// - `POST /api/urls/merge` - Adds one link's clicks to another and repoints or aliases it
+/// - `POST /api/urls/rotate-all` - Moves every link of a ref_id to a new slug, retiring the old ones
+/// - `GET /api/urls/by-date` - A ref_id's links created on one UTC day
+/// - `GET /api/urls/destinations` - Distinct destinations of a ref_id's links, with counts
+/// - `GET /api/urls/tags` - Distinct tags across a ref_id's links, sorted
+/// - `GET /api/urls/qr-bundle` - Exports a ref_id's QR codes as a ZIP archive
+/// - `GET /api/urls/export` - Streams a ref_id's links as CSV
+/// - `POST /api/urls/validate` - Checks whether a batch of links' destinations respond
+/// - `GET /api/urls/stats-summary` - Daily click totals across a ref_id's links (analytics)
+/// - `GET /api/urls/{id}/stats` - A link's metadata and click count
+/// - `GET /api/urls/{id}/heatmap` - Click counts bucketed by hour of day (analytics)
+/// - `GET /api/urls/{id}/events` - Pages through a link's click events with cursors (analytics)
+/// - `POST /api/urls/{id}/preview-token` - Mints a time-limited token for sharing a link's stats
+/// - `GET /api/urls/{id}/audit` - A link's record, destination history, clicks and recent events
+/// - `GET /api/urls/{id}/jsonld` - schema.org JSON-LD describing a link
+/// - `GET /api/urls/{id}/qr` - A link's QR code as a PNG image
+/// - `GET /api/urls/{id}/qr.svg` - A link's QR code as an SVG image, for print
+/// - `GET /api/urls/{id}/counter/{name}` - A named app-specific counter of a link
+/// - `POST /api/urls/{id}/counter/{name}/incr` - Atomically increments a named counter
+/// - `POST /api/urls/{id}/toggle` - Pauses or resumes a link (owner-driven, reversible)
+/// - `POST /api/urls/{id}/webhook` - Sets or clears a link's click webhook
+/// - `POST /api/urls/{id}/rename` - Moves a link to a new slug, keeping the old one as an alias
+/// - `GET /api/auth/check` - Confirms the supplied credentials are accepted
+/// - `GET /api/auth/required` - Whether credentials are needed, and which kind (public)
+/// - `GET /api/preferences` / `PUT /api/preferences` - Reads or replaces a ref_id's preferences
 /// - `DELETE /api/{id}` - Deletes a short URL (requires ref_id for authorization)
 /// 
+/// `/api/admin` routes additionally need admin credentials (see `admin_middleware`):
+/// 
+/// - `POST /api/admin/purge-events` - Deletes click events older than a retention window (analytics)
+/// - `POST /api/admin/kill/{id}` - Disables a link immediately, regardless of owner
+/// - `GET /api/admin/raw/{id}` - Returns the stored record string verbatim (debug)
+/// - `GET /api/admin/capacity` - Estimates how much of the generated-slug space is used
+/// - `GET /api/admin/leaderboard` - The most clicked links across all owners
+/// - `GET /api/admin/creation-rate` - Links created per hour or day
+/// - `GET /api/admin/oldest` - The oldest links across all owners, for retention cleanup
+/// - `GET /api/admin/metrics` - In-process counters (e.g. clicks dropped under load)
+/// - `GET /api/admin/redirect-log/stream` - Live server-sent events for every redirect served
+/// 
+/// Routes marked (analytics) only exist when built with the `analytics`
+/// cargo feature, which is enabled by default.
+/// 
+/// With `FORCE_HTTPS=true`, requests forwarded as plain http are redirected
+/// to https first (see `https_redirect_middleware`).
+/// 
+/// Every response carries a request id header, `X-Request-Id` unless
+/// `REQUEST_ID_HEADER` says otherwise (see `request_id_middleware`).
+/// 
 /// # Arguments
 /// 
 /// * `state` - Application state containing the shared database instance
@@ -32,11 +108,10 @@ use crate::middleware::auth_middleware;
 /// # Example Usage
 /// 
 /// ```no_run
-/// # use std::sync::Arc;
 /// # use shortener::database::{init_db, AppState};
 /// # use shortener::route::create_app;
 /// # let db = init_db("data.db").unwrap();
-/// let state = AppState { db: Arc::new(db) };
+/// let state = AppState::new(db);
 /// let app = create_app(state);
 /// // axum::serve(listener, app).await.unwrap();
 /// ```
@@ -44,14 +119,115 @@ pub fn create_app(state: AppState) -> Router {
     // API routes that require authorization check
     let api_routes = Router::new()
         .route("/urls", get(list_urls).post(create_short_url))
-        .route("/{id}", delete(delete_short_url))
+        .route("/urls/batch-with-defaults", post(batch_create_with_defaults))
+        .route("/bundles", post(create_bundle))
+        .route("/urls/bulk", post(bulk_create))
+        .route("/urls/preview-url", get(preview_short_url))
+        .route("/urls/validate-slug", get(validate_slug))
+        .route("/urls/reserve", post(reserve_slug))
+        .route("/urls/resolve", get(resolve_short_url))
+        .route("/urls/merge", post(merge_links))
+        .route("/urls/rotate-all", post(rotate_all))
+        .route("/urls/by-date", get(list_urls_by_date))
+        .route("/urls/destinations", get(list_destinations))
+        .route("/urls/tags", get(list_tags))
+        .route("/urls/{id}/stats", get(get_url_stats))
+        .route("/urls/{id}/audit", get(link_audit))
+        .route("/urls/{id}/preview-token", post(mint_preview_token))
+        .route("/urls/{id}/jsonld", get(link_jsonld))
+        .route("/urls/{id}/qr", get(link_qr))
+        .route("/urls/{id}/qr.svg", get(link_qr_svg))
+        .route("/urls/{id}/counter/{name}", get(get_counter))
+        .route("/urls/{id}/counter/{name}/incr", post(increment_counter))
+        .route("/urls/{id}/toggle", post(toggle_link))
+        .route("/urls/{id}/webhook", post(set_click_webhook))
+        .route("/urls/{id}/rename", post(rename_short_url))
+        .route("/auth/check", get(auth_check))
+        .route("/preferences", get(get_preferences).put(update_preferences))
+        .route("/{id}", delete(delete_short_url));
+
+    #[cfg(feature = "analytics")]
+    let api_routes = api_routes
+        .route("/urls/stats-summary", get(stats_summary))
+        .route("/urls/{id}/heatmap", get(click_heatmap))
+        .route("/urls/{id}/events", get(list_click_events));
+
+    // Endpoints that legitimately outlast REQUEST_TIMEOUT_SECS
+    let long_routes = Router::new()
+        .route("/urls/qr-bundle", get(qr_bundle))
+        .route("/urls/export", get(export_csv))
+        .route("/urls/validate", post(validate_destinations));
+
+    // Operator-only routes spanning every owner's links
+    let admin_routes = Router::new()
+        .route("/kill/{id}", post(kill_link))
+        .route("/raw/{id}", get(raw_record))
+        .route("/capacity", get(slug_capacity))
+        .route("/leaderboard", get(leaderboard))
+        .route("/creation-rate", get(creation_rate))
+        .route("/oldest", get(oldest_links))
+        .route("/metrics", get(get_metrics));
+
+    #[cfg(feature = "analytics")]
+    let admin_routes = admin_routes.route("/purge-events", post(purge_events));
+
+    let admin_long_routes = Router::new().route("/redirect-log/stream", get(redirect_log_stream));
+
+    let admin_routes = with_timeouts(admin_routes, admin_long_routes, &state.config)
+        .route_layer(middleware::from_fn(admin_middleware));
+
+    let api_routes = with_timeouts(api_routes, long_routes, &state.config)
+        .nest("/admin", admin_routes)
+        // Layers run outermost-last: auth is checked first, then the caller's
+        // rate limit, and only then is a write permit taken
+        .layer(middleware::from_fn_with_state(state.clone(), write_limit_middleware))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit_middleware))
         .layer(middleware::from_fn(auth_middleware));
 
-    Router::new()
-        // Public redirect endpoint - converts short URL to original URL
-        .route("/{id}", get(redirect_url))
+    // Public redirect endpoint - converts short URL to original URL. API-only
+    // deployments leave it out (`DISABLE_REDIRECT_ROUTE`), so unknown paths 404
+    let router = if state.config.disable_redirect_route {
+        Router::new()
+    } else {
+        Router::new().route("/{id}", get(redirect_url))
+    };
+
+    // Slugs never contain '/', so `/{id}/` can only be a stray trailing
+    // slash; only a single one is accepted
+    let router = if state.config.trim_trailing_slash && !state.config.disable_redirect_route {
+        router.route("/{id}/", get(redirect_url))
+    } else {
+        router
+    };
+
+    router
+        // Public so clients can discover whether they need credentials
+        .route("/api/auth/required", get(auth_required))
         // Mount API routes under /api
         .nest("/api", api_routes)
+        // Send plain-http visitors to https before anything is handled (`FORCE_HTTPS`)
+        .layer(middleware::from_fn_with_state(state.clone(), https_redirect_middleware))
+        // Tag every request with an id for log correlation
+        .layer(middleware::from_fn_with_state(state.clone(), request_id_middleware))
         // Inject the application state into all handlers
         .with_state(state)
 }
+
+/// Merges `routes` and `long_routes`, each under its own handler timeout
+/// 
+/// `routes` get `REQUEST_TIMEOUT_SECS` and `long_routes` the longer
+/// `LONG_REQUEST_TIMEOUT_SECS`; a handler still running at its deadline is
+/// dropped and answered with `408 Request Timeout`. Only producing the
+/// response is timed, so streamed bodies (CSV export, the live log) may run
+/// past it once their headers are sent.
+pub fn with_timeouts<S>(routes: Router<S>, long_routes: Router<S>, config: &Config) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    routes
+        .layer(TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, config.request_timeout))
+        .merge(long_routes.layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            config.long_request_timeout,
+        )))
+}
@@ -0,0 +1,185 @@
+//! Slug generation and the brand prefix/suffix rules
+//!
+//! Every generated slug is wrapped as `{SLUG_PREFIX}{core}{SLUG_SUFFIX}`, where
+//! the core comes from the `SLUG_STRATEGY` generator (see `slug_generator`).
+//! Custom slugs are wrapped the same way when `SLUG_AFFIX_CUSTOM` is set;
+//! otherwise they must stay out of the prefix/suffix space so they can never
+//! be mistaken for (or collide with) generated ones.
+//!
+//! Custom slugs must also be 1 to `MAX_CUSTOM_SLUG_LENGTH` characters of
+//! `[A-Za-z0-9_-]`, so they always form a single, unescaped path segment.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::config::Config;
+use crate::slug_generator::{SlugContext, SlugGenerator};
+
+/// Default length of the random part of a generated slug (see `SLUG_LENGTH`)
+pub const RANDOM_SLUG_LENGTH: usize = 6;
+
+/// Number of distinct characters a generated slug is drawn from (`[A-Za-z0-9]`)
+pub const SLUG_ALPHABET_SIZE: u64 = 62;
+
+/// Default for `SLUG_MAX_RETRIES`: slugs generated for one link after the
+/// first is already taken, before giving up
+pub const DEFAULT_SLUG_MAX_RETRIES: u32 = 4;
+
+/// Longest custom slug accepted, before affixes
+pub const MAX_CUSTOM_SLUG_LENGTH: usize = 64;
+
+/// Top-level path segments owned by the application rather than by links
+///
+/// The public redirect shares the root with these, so a link named after one
+/// would shadow (or be shadowed by) a route. Add new top-level routes here;
+/// both creation and redirects consult this list through `is_reserved_path`.
+pub const RESERVED_PATHS: &[&str] = &["api", "health", "metrics", "static", "favicon.ico", "robots.txt"];
+
+/// Reasons a custom slug is refused
+#[derive(Debug, PartialEq, Eq)]
+pub enum SlugError {
+    /// The slug is empty or only whitespace
+    Empty,
+
+    /// The slug is longer than `MAX_CUSTOM_SLUG_LENGTH` characters
+    TooLong,
+
+    /// The slug contains a character outside `[A-Za-z0-9_-]` (e.g. `/`)
+    InvalidCharacters,
+
+    /// The slug (with or without affixes) is a reserved word
+    Reserved,
+
+    /// The slug uses the configured prefix or suffix, which is reserved for
+    /// generated slugs
+    ReservedAffix,
+}
+
+impl fmt::Display for SlugError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlugError::Empty => write!(f, "id must not be empty"),
+            SlugError::TooLong => write!(f, "ids are limited to {} characters", MAX_CUSTOM_SLUG_LENGTH),
+            SlugError::InvalidCharacters => write!(f, "ids may only contain letters, digits, '-' and '_'"),
+            SlugError::Reserved => write!(f, "this id is reserved"),
+            SlugError::ReservedAffix => {
+                write!(f, "ids starting with the slug prefix or ending with the slug suffix are reserved")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SlugError {}
+
+/// Outcome of every custom slug rule; `true` means the rule passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SlugChecks {
+    /// Only ASCII letters, digits, `-` and `_`
+    pub charset: bool,
+
+    /// Between 1 and `MAX_CUSTOM_SLUG_LENGTH` characters
+    pub length: bool,
+
+    /// Not a reserved path, and not using the prefix/suffix of generated slugs
+    pub reserved: bool,
+
+    /// Not made of digits only, which would look like an enumerable numeric id
+    pub numeric: bool,
+
+    /// Not taken by a link, an alias or someone else's reservation
+    pub available: bool,
+}
+
+impl SlugChecks {
+    /// Whether every rule passed
+    pub fn valid(&self) -> bool {
+        self.charset && self.length && self.reserved && self.numeric && self.available
+    }
+}
+
+/// Checks a prospective custom slug against every rule at once
+///
+/// `is_taken` receives the slug as it would be stored (with affixes applied
+/// under `SLUG_AFFIX_CUSTOM`), so callers decide where availability is looked
+/// up. It is called even when other rules fail, so every check is reported.
+pub fn validate_slug(config: &Config, requested: &str, is_taken: impl FnOnce(&str) -> bool) -> SlugChecks {
+    let stored = custom(config, requested);
+    let length = requested.chars().count();
+
+    SlugChecks {
+        charset: is_slug_charset(requested),
+        length: (1..=MAX_CUSTOM_SLUG_LENGTH).contains(&length),
+        reserved: !matches!(stored, Err(SlugError::Reserved | SlugError::ReservedAffix)),
+        numeric: !requested.chars().all(|ch| ch.is_ascii_digit()),
+        available: !is_taken(stored.as_deref().unwrap_or(requested)),
+    }
+}
+
+/// Returns whether `slug` only uses ASCII letters, digits, `-` and `_`
+fn is_slug_charset(slug: &str) -> bool {
+    slug.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+/// Returns whether the first segment of `path` is reserved (case-insensitive)
+///
+/// Accepts a bare slug (`api`) as well as a request path (`/api/urls`).
+pub fn is_reserved_path(path: &str) -> bool {
+    let segment = path.trim_start_matches('/').split('/').next().unwrap_or_default();
+    RESERVED_PATHS.iter().any(|reserved| reserved.eq_ignore_ascii_case(segment))
+}
+
+/// Wraps `core` in the configured prefix and suffix
+fn affix(config: &Config, core: &str) -> String {
+    format!("{}{}{}", config.slug_prefix, core, config.slug_suffix)
+}
+
+/// Generates a slug with `generator`, carrying the configured prefix and suffix
+pub fn generate(generator: &dyn SlugGenerator, ctx: &SlugContext) -> String {
+    affix(ctx.config, &generator.generate(ctx))
+}
+
+/// Number of distinct random parts of `length` characters, saturating at `u64::MAX`
+pub fn slug_space(length: usize) -> u64 {
+    u32::try_from(length)
+        .ok()
+        .and_then(|length| SLUG_ALPHABET_SIZE.checked_pow(length))
+        .unwrap_or(u64::MAX)
+}
+
+/// Turns a requested custom id into the slug that will be stored
+///
+/// Ids that are blank, too long or use characters outside `[A-Za-z0-9_-]`
+/// are refused first. With `SLUG_AFFIX_CUSTOM` the id is then wrapped like a
+/// generated slug. Without it, ids that already use the prefix or suffix are
+/// refused. Reserved words are refused in both their bare and wrapped forms.
+pub fn custom(config: &Config, requested: &str) -> Result<String, SlugError> {
+    if requested.trim().is_empty() {
+        return Err(SlugError::Empty);
+    }
+    if requested.chars().count() > MAX_CUSTOM_SLUG_LENGTH {
+        return Err(SlugError::TooLong);
+    }
+    if !is_slug_charset(requested) {
+        return Err(SlugError::InvalidCharacters);
+    }
+
+    if is_reserved_path(requested) {
+        return Err(SlugError::Reserved);
+    }
+
+    if config.slug_affix_custom {
+        let slug = affix(config, requested);
+        if is_reserved_path(&slug) {
+            return Err(SlugError::Reserved);
+        }
+        return Ok(slug);
+    }
+
+    let uses_prefix = !config.slug_prefix.is_empty() && requested.starts_with(&config.slug_prefix);
+    let uses_suffix = !config.slug_suffix.is_empty() && requested.ends_with(&config.slug_suffix);
+    if uses_prefix || uses_suffix {
+        return Err(SlugError::ReservedAffix);
+    }
+
+    Ok(requested.to_string())
+}
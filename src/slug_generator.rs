@@ -0,0 +1,237 @@
+//! Pluggable schemes for the core of generated slugs
+//!
+//! `SLUG_STRATEGY` picks one of the built-in `SlugGenerator`s, which
+//! `AppState::slug_generator` holds for the lifetime of the process. The
+//! generator only produces the core of a slug; `slug::generate` wraps it in
+//! `SLUG_PREFIX` / `SLUG_SUFFIX`.
+//!
+//! - `random` (default) - `SLUG_LENGTH` random characters of `[A-Za-z0-9]`
+//! - `base62` - An increasing counter in base62, so slugs sort by creation
+//! - `words` - `adjective-noun-NN`, easy to read out loud
+//! - `hash` - `SLUG_LENGTH` base62 characters of a SHA-256 of the request
+
+use chrono::{DateTime, Utc};
+use rand::{distr::Alphanumeric, seq::IndexedRandom, Rng};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::slug;
+
+/// Digits used by base62 encodings, in ascending order
+const BASE62_DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// First words of `words` slugs
+const ADJECTIVES: &[&str] = &[
+    "amber", "bold", "brave", "bright", "calm", "clever", "cosmic", "crisp", "eager", "fancy", "gentle", "golden",
+    "happy", "jolly", "keen", "lively", "lucky", "mellow", "misty", "noble", "proud", "quick", "quiet", "rapid",
+    "shiny", "silent", "sunny", "swift", "tidy", "vivid", "warm", "witty",
+];
+
+/// Second words of `words` slugs
+const NOUNS: &[&str] = &[
+    "badger", "breeze", "brook", "cactus", "canyon", "comet", "coral", "falcon", "fern", "forest", "harbor", "heron",
+    "island", "lagoon", "lantern", "maple", "meadow", "meteor", "orchid", "otter", "panda", "pebble", "pine",
+    "planet", "raven", "river", "sparrow", "summit", "tiger", "tulip", "valley", "willow",
+];
+
+/// What a generator may base a slug on
+pub struct SlugContext<'a> {
+    /// Configuration in effect (e.g. `slug_length`)
+    pub config: &'a Config,
+
+    /// Destination of the link being created
+    pub url: &'a str,
+
+    /// Owner of the link being created, if any
+    pub ref_id: Option<&'a str>,
+
+    /// Creation time of the link
+    pub created_at: DateTime<Utc>,
+
+    /// Zero-based attempt number; retries after a collision count up, so
+    /// deterministic schemes can produce a different slug
+    pub attempt: u32,
+}
+
+/// A scheme producing the core of generated slugs
+///
+/// Implementations must only emit `[A-Za-z0-9_-]`, so slugs stay a single
+/// unescaped path segment. Collisions are caught when the link is stored,
+/// which asks for another slug up to `SLUG_MAX_RETRIES` times.
+pub trait SlugGenerator: Send + Sync {
+    /// Returns the core of a new slug, without prefix or suffix
+    fn generate(&self, ctx: &SlugContext) -> String;
+
+    /// Number of distinct cores this scheme can produce under `config`
+    ///
+    /// `None` for schemes that never run out, such as an ever-growing counter.
+    fn space(&self, config: &Config) -> Option<u64>;
+}
+
+/// `SLUG_LENGTH` random alphanumeric characters
+#[derive(Debug, Default)]
+pub struct RandomSlug;
+
+impl SlugGenerator for RandomSlug {
+    fn generate(&self, ctx: &SlugContext) -> String {
+        rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(ctx.config.slug_length)
+            .map(char::from)
+            .collect()
+    }
+
+    fn space(&self, config: &Config) -> Option<u64> {
+        Some(slug::slug_space(config.slug_length))
+    }
+}
+
+/// A counter in base62 that never repeats or decreases
+///
+/// The counter starts from the creation time in microseconds and is bumped
+/// for links created within the same microsecond, so it also keeps
+/// increasing across restarts (as long as the clock does).
+#[derive(Debug, Default)]
+pub struct Base62Slug {
+    last: AtomicU64,
+}
+
+impl SlugGenerator for Base62Slug {
+    fn generate(&self, ctx: &SlugContext) -> String {
+        let now = u64::try_from(ctx.created_at.timestamp_micros()).unwrap_or_default();
+        let previous = self
+            .last
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| Some(now.max(last + 1)))
+            .unwrap();
+        base62(now.max(previous + 1))
+    }
+
+    fn space(&self, _config: &Config) -> Option<u64> {
+        // The counter just grows another digit
+        None
+    }
+}
+
+/// Two words and two digits, e.g. `brave-otter-42`
+#[derive(Debug, Default)]
+pub struct WordsSlug;
+
+impl SlugGenerator for WordsSlug {
+    fn generate(&self, _ctx: &SlugContext) -> String {
+        let mut rng = rand::rng();
+        format!(
+            "{}-{}-{:02}",
+            ADJECTIVES.choose(&mut rng).unwrap(),
+            NOUNS.choose(&mut rng).unwrap(),
+            rng.random_range(0..100),
+        )
+    }
+
+    fn space(&self, _config: &Config) -> Option<u64> {
+        Some((ADJECTIVES.len() * NOUNS.len() * 100) as u64)
+    }
+}
+
+/// `SLUG_LENGTH` base62 characters of a hash of the destination, owner and time
+///
+/// Unlike `random`, the same request at the same microsecond always yields
+/// the same slug, which makes imports reproducible. The attempt number is
+/// hashed too, so a retry after a collision gets a different slug.
+#[derive(Debug, Default)]
+pub struct HashSlug;
+
+impl SlugGenerator for HashSlug {
+    fn generate(&self, ctx: &SlugContext) -> String {
+        let digest = Sha256::new()
+            .chain_update(ctx.url.as_bytes())
+            .chain_update([0])
+            .chain_update(ctx.ref_id.unwrap_or_default().as_bytes())
+            .chain_update([0])
+            .chain_update(ctx.created_at.timestamp_micros().to_be_bytes())
+            .chain_update(ctx.attempt.to_be_bytes())
+            .finalize();
+        digest
+            .iter()
+            .cycle()
+            .take(ctx.config.slug_length)
+            .map(|byte| BASE62_DIGITS[usize::from(*byte) % BASE62_DIGITS.len()] as char)
+            .collect()
+    }
+
+    fn space(&self, config: &Config) -> Option<u64> {
+        Some(slug::slug_space(config.slug_length))
+    }
+}
+
+/// Encodes `value` in base62, most significant digit first
+fn base62(mut value: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(BASE62_DIGITS[(value % 62) as usize]);
+        value /= 62;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Built-in slug schemes, selected by `SLUG_STRATEGY`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlugStrategy {
+    /// See `RandomSlug`
+    #[default]
+    Random,
+
+    /// See `Base62Slug`
+    Base62,
+
+    /// See `WordsSlug`
+    Words,
+
+    /// See `HashSlug`
+    Hash,
+}
+
+impl SlugStrategy {
+    /// Creates the generator implementing this strategy
+    pub fn generator(self) -> Arc<dyn SlugGenerator> {
+        match self {
+            SlugStrategy::Random => Arc::new(RandomSlug),
+            SlugStrategy::Base62 => Arc::new(Base62Slug::default()),
+            SlugStrategy::Words => Arc::new(WordsSlug),
+            SlugStrategy::Hash => Arc::new(HashSlug),
+        }
+    }
+}
+
+/// Error for an unrecognized `SLUG_STRATEGY` value
+#[derive(Debug)]
+pub struct ParseSlugStrategyError;
+
+impl fmt::Display for ParseSlugStrategyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected `random`, `base62`, `words` or `hash`")
+    }
+}
+
+impl std::error::Error for ParseSlugStrategyError {}
+
+impl FromStr for SlugStrategy {
+    type Err = ParseSlugStrategyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "random" => Ok(SlugStrategy::Random),
+            "base62" => Ok(SlugStrategy::Base62),
+            "words" => Ok(SlugStrategy::Words),
+            "hash" => Ok(SlugStrategy::Hash),
+            _ => Err(ParseSlugStrategyError),
+        }
+    }
+}
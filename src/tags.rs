@@ -0,0 +1,61 @@
+//! Normalization of link tags
+//!
+//! Tags are trimmed, blank ones dropped and duplicates removed before a
+//! link is stored. Duplicates are found case-insensitively; `TAG_CASE`
+//! decides which spelling survives:
+//!
+//! - `preserve` (default) - The first spelling given, e.g. `["A", "a", "b"]` becomes `["A", "b"]`
+//! - `lowercase` - Every tag lowercased, e.g. `["A", "a", "b"]` becomes `["a", "b"]`
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the case of tags is normalized, selected by `TAG_CASE`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagCase {
+    /// Keep the first-seen spelling of each tag
+    #[default]
+    Preserve,
+
+    /// Store every tag in lowercase
+    Lowercase,
+}
+
+/// Error for an unrecognized `TAG_CASE` value
+#[derive(Debug)]
+pub struct ParseTagCaseError;
+
+impl fmt::Display for ParseTagCaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected `preserve` or `lowercase`")
+    }
+}
+
+impl std::error::Error for ParseTagCaseError {}
+
+impl FromStr for TagCase {
+    type Err = ParseTagCaseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "preserve" => Ok(TagCase::Preserve),
+            "lowercase" => Ok(TagCase::Lowercase),
+            _ => Err(ParseTagCaseError),
+        }
+    }
+}
+
+/// Trims `tags`, drops blank ones and removes case-insensitive duplicates
+///
+/// The first occurrence of each tag keeps its position.
+pub fn normalize_tags(tags: impl IntoIterator<Item = String>, case: TagCase) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|tag| match case {
+            TagCase::Preserve => tag.trim().to_string(),
+            TagCase::Lowercase => tag.trim().to_lowercase(),
+        })
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .collect()
+}
@@ -0,0 +1,133 @@
+//! Per-link caps on distinct visitors
+//!
+//! Links created with `max_unique_visitors` remember every visitor in
+//! `TABLE_VISITORS` under `{id}:{ip_hash}`. Only a hash of the IP is kept,
+//! salted with the link's creation time (which, unlike the slug, survives
+//! renames) so the same visitor cannot be matched across links.
+//! Once the cap is reached, new visitors get 410 while those already seen
+//! keep access. Visitors follow renames and are dropped with the link.
+//!
+//! The IP is the connection's peer, or the `X-Forwarded-For` client when the
+//! peer is one of `TRUSTED_PROXIES` (see `geoip::trusted_client_ip`), so a
+//! visitor cannot claim a fresh place by sending a made-up header. Visits
+//! with `no_count` are checked against the cap but never take a place.
+
+use chrono::{DateTime, Utc};
+use redb::{ReadableTable, WriteTransaction};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+use crate::database::{AppState, TABLE_VISITORS};
+
+/// Whether a visitor may follow a capped link
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// Seen before; does not count against the cap again
+    Returning,
+
+    /// Not seen before and, by `admit`, recorded now
+    New,
+
+    /// Not seen before, and the cap is already reached
+    LimitReached,
+}
+
+/// Hash of a visitor's IP for the link created at `created_at`, as stored in keys
+///
+/// The first 16 bytes of a SHA-256 over the creation time and the address,
+/// in hex. Visitors without a known address share one hash.
+pub fn visitor_hash(created_at: DateTime<Utc>, ip: Option<IpAddr>) -> String {
+    let address = ip.map(|ip| ip.to_string()).unwrap_or_default();
+    Sha256::new()
+        .chain_update(created_at.timestamp_micros().to_be_bytes())
+        .chain_update(address.as_bytes())
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Returns the `TABLE_VISITORS` keys of link `id`
+///
+/// Entries of longer slugs sharing the `{id}:` prefix are skipped by
+/// checking the part before the last ':'.
+fn visitor_keys<T: ReadableTable<&'static str, u64>>(table: &T, id: &str) -> Result<Vec<String>, redb::Error> {
+    let start_key = format!("{}:", id);
+    let end_key = format!("{}:{{", id);
+
+    let mut keys = Vec::new();
+    for entry in table.range(start_key.as_str()..end_key.as_str())? {
+        let (key, _) = entry?;
+        if key.value().rsplit_once(':').is_some_and(|(visitor_id, _)| visitor_id == id) {
+            keys.push(key.value().to_string());
+        }
+    }
+    Ok(keys)
+}
+
+/// Whether the visitor `hash` is known to link `id`, or would fit under `max`
+fn admission<T: ReadableTable<&'static str, u64>>(table: &T, id: &str, hash: &str, max: u32) -> Result<Admission, redb::Error> {
+    let key = format!("{}:{}", id, hash);
+    if table.get(key.as_str())?.is_some() {
+        Ok(Admission::Returning)
+    } else if visitor_keys(table, id)?.len() >= max as usize {
+        Ok(Admission::LimitReached)
+    } else {
+        Ok(Admission::New)
+    }
+}
+
+/// Admits or refuses the visitor `hash` to link `id`, capped at `max` visitors
+///
+/// Runs in its own write transaction so two first visits cannot both take
+/// the last place.
+pub fn admit(state: &AppState, id: &str, hash: &str, max: u32) -> Result<Admission, redb::Error> {
+    let write_txn = state.begin_write()?;
+    let admission = {
+        let mut table = write_txn.open_table(TABLE_VISITORS)?;
+        let admission = admission(&table, id, hash, max)?;
+        if admission == Admission::New {
+            let key = format!("{}:{}", id, hash);
+            table.insert(key.as_str(), u64::try_from(Utc::now().timestamp_micros()).unwrap_or_default())?;
+        }
+        admission
+    };
+
+    if admission == Admission::New {
+        write_txn.commit()?;
+    } else {
+        write_txn.abort()?;
+    }
+    Ok(admission)
+}
+
+/// Like `admit`, but never records the visitor
+///
+/// For visits that must not count, e.g. monitoring with `no_count`: a new
+/// visitor is still refused once the cap is reached, but takes no place.
+pub fn check(state: &AppState, id: &str, hash: &str, max: u32) -> Result<Admission, redb::Error> {
+    let read_txn = state.begin_primary_read()?;
+    let table = read_txn.open_table(TABLE_VISITORS)?;
+    admission(&table, id, hash, max)
+}
+
+/// Moves the visitors of a renamed link from `from` to `to`
+pub fn rename_visitors(write_txn: &WriteTransaction, from: &str, to: &str) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_VISITORS)?;
+    for key in visitor_keys(&table, from)? {
+        let first_seen = table.remove(key.as_str())?.map(|value| value.value()).unwrap_or_default();
+        let (_, hash) = key.rsplit_once(':').unwrap();
+        table.insert(format!("{}:{}", to, hash).as_str(), first_seen)?;
+    }
+    Ok(())
+}
+
+/// Drops the visitors of a deleted link
+pub fn delete_visitors(write_txn: &WriteTransaction, id: &str) -> Result<(), redb::Error> {
+    let mut table = write_txn.open_table(TABLE_VISITORS)?;
+    for key in visitor_keys(&table, id)? {
+        table.remove(key.as_str())?;
+    }
+    Ok(())
+}
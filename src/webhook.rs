@@ -0,0 +1,114 @@
+//! Per-link click webhooks
+//!
+//! Links created with a `click_webhook` have every counted visit reported to
+//! that URL. Delivery is fire-and-forget: the POST runs on the redirect's
+//! background counting task (see `MAX_CONCURRENT_REDIRECTS`), so a slow or
+//! failing receiver never delays the redirect, and failures are only logged.
+//! The webhook can be changed later with `POST /api/urls/{id}/webhook`.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::database::{load_record, AppState, TABLE_URLS};
+use crate::error::AppError;
+use crate::handler::{effective_ref_id, is_http_url};
+use crate::middleware::AuthSubject;
+use crate::model::{ClickEvent, WebhookRequest};
+
+/// Returns whether `webhook` is an absolute http(s) URL
+pub fn is_valid_webhook(webhook: &str) -> bool {
+    is_http_url(webhook)
+}
+
+/// POSTs `{id, timestamp, referrer, user_agent}` for `event` to `webhook`
+///
+/// Failures are only logged; the request is bounded by the client's timeout.
+pub async fn send_click_webhook(client: &reqwest::Client, webhook: &str, event: &ClickEvent) {
+    let request = client.post(webhook).json(&json!({
+        "id": event.id,
+        "timestamp": event.timestamp,
+        "referrer": event.referrer,
+        "user_agent": event.user_agent,
+    }));
+
+    match request.send().await {
+        Ok(response) if !response.status().is_success() => {
+            tracing::warn!("Click webhook {} responded with {}", webhook, response.status());
+        }
+        Ok(_) => {}
+        Err(err) => tracing::warn!("Click webhook {} failed: {}", webhook, err),
+    }
+}
+
+/// Sets or clears the click webhook of an existing link
+///
+/// # Request Body
+///
+/// ```json
+/// { "click_webhook": "https://hooks.example.com/clicks", "ref_id": "user_123" }
+/// ```
+///
+/// Omitting `click_webhook` (or passing null or `""`) clears it.
+///
+/// # Response
+///
+/// - **200 OK** - `{"id": "abc123", "click_webhook": "https://..."}`
+/// - **400 Bad Request** - `click_webhook` is not an absolute http(s) URL
+/// - **403 Forbidden** - ref_id does not match the owner
+/// - **404 Not Found** - URL does not exist
+pub async fn set_click_webhook(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    subject: Option<Extension<AuthSubject>>,
+    Json(payload): Json<WebhookRequest>,
+) -> Result<Response, AppError> {
+    let error = |status: StatusCode, error: &str, code: &str| {
+        (status, Json(json!({ "error": error, "code": code }))).into_response()
+    };
+
+    let click_webhook = payload.click_webhook.filter(|webhook| !webhook.is_empty());
+    if click_webhook.as_deref().is_some_and(|webhook| !is_valid_webhook(webhook)) {
+        return Ok(error(
+            StatusCode::BAD_REQUEST,
+            "click_webhook must be an absolute http(s) URL",
+            "invalid_click_webhook",
+        ));
+    }
+
+    let ref_id = effective_ref_id(subject, payload.ref_id);
+
+    let write_txn = state.begin_write()?;
+    {
+        let mut table_main = write_txn.open_table(TABLE_URLS)?;
+
+        let Some(mut record) = load_record(&table_main, &id)? else {
+            return Ok(error(StatusCode::NOT_FOUND, "URL not found", "not_found"));
+        };
+
+        // Same ownership rule as deletion: only checked when a ref_id is supplied
+        if let Some(request_ref_id) = &ref_id {
+            if record.ref_id.as_ref() != Some(request_ref_id) {
+                return Ok(error(
+                    StatusCode::FORBIDDEN,
+                    "You are not authorized to modify this link",
+                    "forbidden",
+                ));
+            }
+        }
+
+        record.click_webhook = click_webhook.clone();
+        table_main.insert(id.as_str(), state.config.record_format.encode(&record).as_str())?;
+    }
+    write_txn.commit()?;
+
+    Ok(Json(json!({
+        "id": id,
+        "click_webhook": click_webhook,
+    }))
+    .into_response())
+}
@@ -0,0 +1,373 @@
+//! Integration tests for click analytics
+//! 
+//! These tests seed the event and daily stats tables directly through the
+//! `analytics` module and then exercise the HTTP endpoints built on them.
+
+#![cfg(feature = "analytics")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use chrono::{Duration, TimeZone, Utc};
+use http_body_util::BodyExt;
+use redb::{Database, ReadableDatabase, ReadableTableMetadata};
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+use tower::ServiceExt;
+
+use shortener::analytics::record_click;
+use shortener::database::{init_db, AppState, TABLE_CLICK_EVENTS, TABLE_DAILY_STATS};
+use shortener::model::ClickEvent;
+use shortener::route::create_app;
+
+/// Helper function to create a test application that also exposes its state
+fn setup_test_app() -> (axum::Router, AppState, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::new(db);
+    
+    (create_app(state.clone()), state, temp_db)
+}
+
+/// Helper function to issue a JSON request against the app
+async fn send(app: &axum::Router, method: &str, uri: &str, payload: Option<Value>) -> axum::response::Response {
+    let body = match payload {
+        Some(payload) => Body::from(payload.to_string()),
+        None => Body::empty(),
+    };
+    
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(body)
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Helper function to parse response body as JSON
+async fn response_json(body: Body) -> Value {
+    let bytes = body
+        .collect()
+        .await
+        .expect("Failed to read response body")
+        .to_bytes();
+    
+    serde_json::from_slice(&bytes).expect("Failed to parse JSON")
+}
+
+/// Helper function to build a click event at a given time
+fn click(id: &str, timestamp: chrono::DateTime<Utc>) -> ClickEvent {
+    ClickEvent {
+        id: id.to_string(),
+        timestamp,
+        referrer: None,
+        user_agent: None,
+        country: None,
+    }
+}
+
+/// Helper function to count entries in the event and daily stats tables
+fn table_counts(db: &Database) -> (u64, u64) {
+    let read_txn = db.begin_read().unwrap();
+    let events = read_txn.open_table(TABLE_CLICK_EVENTS).unwrap().len().unwrap();
+    let daily = read_txn.open_table(TABLE_DAILY_STATS).unwrap().len().unwrap();
+    (events, daily)
+}
+
+#[tokio::test]
+async fn test_purge_events_removes_only_old_entries() {
+    let (app, state, _temp_db) = setup_test_app();
+    
+    let now = Utc::now();
+    record_click(&state, &click("purge1", now - Duration::days(40))).unwrap();
+    record_click(&state, &click("purge1", now - Duration::days(35))).unwrap();
+    record_click(&state, &click("purge1", now - Duration::days(1))).unwrap();
+    record_click(&state, &click("purge2", now)).unwrap();
+    assert_eq!(table_counts(&state.db), (4, 4));
+    
+    let response = send(&app, "POST", "/api/admin/purge-events", Some(json!({ "older_than_days": 30 }))).await;
+    
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["purged_events"], 2);
+    assert_eq!(body["purged_daily_stats"], 2);
+    
+    assert_eq!(table_counts(&state.db), (2, 2));
+}
+
+#[tokio::test]
+async fn test_purge_events_rejects_zero_retention() {
+    let (app, _state, _temp_db) = setup_test_app();
+    
+    let response = send(&app, "POST", "/api/admin/purge-events", Some(json!({ "older_than_days": 0 }))).await;
+    
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_click_heatmap_buckets_by_hour() {
+    let (app, state, _temp_db) = setup_test_app();
+    
+    let response = send(&app, "POST", "/api/urls", Some(json!({
+        "url": "https://example.com/heat",
+        "custom_id": "heat"
+    })))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let day = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+    for (hour, minute) in [(9, 5), (9, 40), (14, 0), (23, 59)] {
+        let at = day + Duration::hours(hour) + Duration::minutes(minute);
+        record_click(&state, &click("heat", at)).unwrap();
+    }
+    // A different slug sharing the prefix must not be counted
+    record_click(&state, &click("heat:other", day + Duration::hours(9))).unwrap();
+    
+    let response = send(&app, "GET", "/api/urls/heat/heatmap", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total"], 4);
+    let hours = body["hours"].as_array().unwrap();
+    assert_eq!(hours.len(), 24);
+    assert_eq!(hours[9], 2);
+    assert_eq!(hours[14], 1);
+    assert_eq!(hours[23], 1);
+    
+    // Shifting by +02:00 moves 23:59 UTC into hour 1 and 9:xx into hour 11
+    let response = send(&app, "GET", "/api/urls/heat/heatmap?tz=2", None).await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["tz"], "+02:00");
+    assert_eq!(body["hours"][11], 2);
+    assert_eq!(body["hours"][16], 1);
+    assert_eq!(body["hours"][1], 1);
+    assert_eq!(body["hours"][23], 0);
+}
+
+#[tokio::test]
+async fn test_click_heatmap_rejects_bad_tz_and_missing_link() {
+    let (app, _state, _temp_db) = setup_test_app();
+    
+    let response = send(&app, "GET", "/api/urls/missing/heatmap", None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    
+    send(&app, "POST", "/api/urls", Some(json!({
+        "url": "https://example.com/heat2",
+        "custom_id": "heat2"
+    })))
+    .await;
+    
+    let response = send(&app, "GET", "/api/urls/heat2/heatmap?tz=abc", None).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    let response = send(&app, "GET", "/api/urls/heat2/heatmap?tz=20", None).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_no_count_redirects_are_not_recorded() {
+    let (app, state, _temp_db) = setup_test_app();
+    
+    let response = send(
+        &app,
+        "POST",
+        "/api/urls",
+        Some(json!({ "url": "https://example.com", "custom_id": "monitored" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // One counted visit, then one uncounted via query and one via header
+    let response = send(&app, "GET", "/monitored", None).await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    let response = send(&app, "GET", "/monitored?no_count=true", None).await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/monitored")
+                .header("x-no-count", "1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    assert_eq!(table_counts(&state.db), (1, 1));
+    let response = send(&app, "GET", "/api/urls/monitored/heatmap", None).await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total"], 1);
+}
+
+#[tokio::test]
+async fn test_stats_summary_sums_tenant_links() {
+    let (app, state, _temp_db) = setup_test_app();
+    
+    for (slug, owner) in [("sum-a", "tenant"), ("sum-b", "tenant"), ("sum-other", "someone_else")] {
+        let response = send(
+            &app,
+            "POST",
+            "/api/urls",
+            Some(json!({ "url": "https://example.com", "ref_id": owner, "custom_id": slug })),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let day = |d: u32, h: u32| Utc.with_ymd_and_hms(2026, 1, d, h, 0, 0).unwrap();
+    for event in [
+        click("sum-a", day(12, 9)),
+        click("sum-a", day(12, 10)),
+        click("sum-b", day(12, 11)),
+        click("sum-b", day(14, 8)),
+        click("sum-a", day(20, 8)),      // outside the range
+        click("sum-other", day(12, 8)),  // another tenant
+    ] {
+        record_click(&state, &event).unwrap();
+    }
+    
+    let response = send(
+        &app,
+        "GET",
+        "/api/urls/stats-summary?ref_id=tenant&from=2026-01-12&to=2026-01-14",
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    
+    assert_eq!(body["total_clicks"], 4);
+    assert_eq!(
+        body["by_day"],
+        json!([
+            { "date": "2026-01-12", "clicks": 3 },
+            { "date": "2026-01-13", "clicks": 0 },
+            { "date": "2026-01-14", "clicks": 1 }
+        ])
+    );
+    
+    let response = send(
+        &app,
+        "GET",
+        "/api/urls/stats-summary?ref_id=tenant&from=2026-01-14&to=2026-01-12",
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_audit_trail_combines_history_clicks_and_events() {
+    let (app, _state, _temp_db) = setup_test_app();
+    
+    for (id, url) in [("audited", "https://old.example"), ("target", "https://new.example")] {
+        let response = send(&app, "POST", "/api/urls", Some(json!({ "url": url, "custom_id": id, "ref_id": "auditor" }))).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    // Clicks before and after the destination changes
+    send(&app, "GET", "/audited", None).await;
+    let response = send(
+        &app,
+        "POST",
+        "/api/urls/merge",
+        Some(json!({ "source_id": "audited", "target_id": "target", "ref_id": "auditor" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    for _ in 0..3 {
+        send(&app, "GET", "/audited", None).await;
+    }
+    
+    let response = send(&app, "GET", "/api/urls/audited/audit?ref_id=auditor&events_limit=2", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    
+    assert_eq!(body["record"]["original_url"], "https://new.example");
+    assert_eq!(body["history"].as_array().unwrap().len(), 1);
+    assert_eq!(body["history"][0]["from"], "https://old.example");
+    assert_eq!(body["history"][0]["to"], "https://new.example");
+    // Merging reset the source's total; the daily counters keep every click
+    assert_eq!(body["clicks"]["total"], 3);
+    assert_eq!(body["clicks"]["daily"][0]["clicks"], 4);
+    assert_eq!(body["recent_events"].as_array().unwrap().len(), 2);
+    
+    // The history follows a rename
+    let response = send(&app, "POST", "/api/urls/audited/rename", Some(json!({ "new_id": "renamed", "ref_id": "auditor" }))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send(&app, "GET", "/api/urls/renamed/audit", None).await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["history"][0]["from"], "https://old.example");
+    
+    let response = send(&app, "GET", "/api/urls/renamed/audit?ref_id=someone_else", None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = send(&app, "GET", "/api/urls/missing/audit", None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_click_events_page_with_cursors() {
+    let (app, state, _temp_db) = setup_test_app();
+    
+    let response = send(&app, "POST", "/api/urls", Some(json!({
+        "url": "https://example.com/busy",
+        "custom_id": "busy",
+        "ref_id": "pager"
+    })))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let start = Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap();
+    for minute in 0..250 {
+        record_click(&state, &click("busy", start + Duration::minutes(minute))).unwrap();
+    }
+    // A different slug sharing the prefix must not leak into the pages
+    record_click(&state, &click("busy:other", start + Duration::minutes(10))).unwrap();
+    
+    let mut seen = Vec::new();
+    let mut uri = "/api/urls/busy/events?limit=100".to_string();
+    let mut statuses = Vec::new();
+    loop {
+        let response = send(&app, "GET", &uri, None).await;
+        statuses.push(response.status());
+        let body = response_json(response.into_body()).await;
+        for event in body["events"].as_array().unwrap() {
+            assert_eq!(event["id"], "busy");
+            seen.push(event["cursor"].as_i64().unwrap());
+        }
+        match body["next_before"].as_i64() {
+            Some(cursor) => uri = format!("/api/urls/busy/events?limit=100&before={}", cursor),
+            None => break,
+        }
+    }
+    
+    assert_eq!(statuses, [StatusCode::PARTIAL_CONTENT, StatusCode::PARTIAL_CONTENT, StatusCode::OK]);
+    assert_eq!(seen.len(), 250);
+    assert!(seen.windows(2).all(|pair| pair[0] > pair[1]), "events must be newest first without repeats");
+    assert_eq!(seen[0], (start + Duration::minutes(249)).timestamp_micros());
+    
+    // `after` returns only newer events, still newest first
+    let after = (start + Duration::minutes(246)).timestamp_micros();
+    let response = send(&app, "GET", &format!("/api/urls/busy/events?after={}", after), None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["events"].as_array().unwrap().len(), 3);
+    assert_eq!(body["has_more"], false);
+    assert!(body["next_before"].is_null());
+    
+    let response = send(&app, "GET", "/api/urls/busy/events?ref_id=someone_else", None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = send(&app, "GET", "/api/urls/missing/events", None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
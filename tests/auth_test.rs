@@ -3,28 +3,56 @@ use axum::{
     http::{Request, StatusCode},
 };
 use http_body_util::BodyExt;
+use jsonwebtoken::{encode, EncodingKey, Header};
 use serde_json::{json, Value};
 use std::env;
-use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
 use tower::ServiceExt;
 
+use shortener::config::Config;
 use shortener::database::{init_db, AppState};
+use shortener::preview_token;
 use shortener::route::create_app;
 
 // Mutex to ensure tests that modify env vars don't run in parallel
-static ENV_MUTEX: Mutex<()> = Mutex::new(());
+static ENV_MUTEX: Mutex<()> = Mutex::const_new(());
 
 fn setup_test_app() -> (axum::Router, NamedTempFile) {
     let temp_db = NamedTempFile::new().expect("Failed to create temp file");
     let db_path = temp_db.path().to_str().unwrap();
     let db = init_db(db_path).expect("Failed to initialize test database");
-    let state = AppState {
-        db: Arc::new(db),
-    };
+    let state = AppState::new(db);
     (create_app(state), temp_db)
 }
 
+/// Helper function to mint an HS256 token for `sub` expiring `exp_offset` seconds from now
+fn make_jwt(secret: &str, sub: &str, exp_offset: i64) -> String {
+    let exp = chrono::Utc::now().timestamp() + exp_offset;
+    encode(
+        &Header::default(),
+        &json!({ "sub": sub, "exp": exp }),
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .unwrap()
+}
+
+/// Helper function to create a URL with the given Authorization header value
+async fn create_with_auth(app: &axum::Router, authorization: &str, payload: Value) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .header("Authorization", authorization)
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
 /// Helper function to parse response body as JSON
 async fn response_json(body: Body) -> Value {
     let bytes = body
@@ -38,7 +66,7 @@ async fn response_json(body: Body) -> Value {
 
 #[tokio::test]
 async fn test_auth_middleware_enabled_valid_token() {
-    let _guard = ENV_MUTEX.lock().unwrap();
+    let _guard = ENV_MUTEX.lock().await;
     env::set_var("AUTHORIZATION", "secret_token");
     
     let (app, _temp_db) = setup_test_app();
@@ -67,7 +95,7 @@ async fn test_auth_middleware_enabled_valid_token() {
 
 #[tokio::test]
 async fn test_auth_middleware_enabled_invalid_token() {
-    let _guard = ENV_MUTEX.lock().unwrap();
+    let _guard = ENV_MUTEX.lock().await;
     env::set_var("AUTHORIZATION", "secret_token");
     
     let (app, _temp_db) = setup_test_app();
@@ -100,7 +128,7 @@ async fn test_auth_middleware_enabled_invalid_token() {
 
 #[tokio::test]
 async fn test_auth_middleware_enabled_no_token() {
-    let _guard = ENV_MUTEX.lock().unwrap();
+    let _guard = ENV_MUTEX.lock().await;
     env::set_var("AUTHORIZATION", "secret_token");
     
     let (app, _temp_db) = setup_test_app();
@@ -132,7 +160,7 @@ async fn test_auth_middleware_enabled_no_token() {
 
 #[tokio::test]
 async fn test_auth_middleware_disabled() {
-    let _guard = ENV_MUTEX.lock().unwrap();
+    let _guard = ENV_MUTEX.lock().await;
     env::remove_var("AUTHORIZATION");
     
     let (app, _temp_db) = setup_test_app();
@@ -155,3 +183,485 @@ async fn test_auth_middleware_disabled() {
     
     assert_eq!(response.status(), StatusCode::CREATED);
 }
+
+#[tokio::test]
+async fn test_jwt_mode_valid_token_sets_owner() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let (app, _temp_db) = setup_test_app();
+    let token = make_jwt("jwt_test_secret", "tenant_42", 3600);
+    
+    // The body claims another owner, but the token subject wins
+    let response = create_with_auth(&app, &format!("Bearer {}", token), json!({
+        "url": "https://example.com/jwt-valid",
+        "ref_id": "someone_else",
+        "custom_id": "jwtvalid"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/urls")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total_fetched"], 1);
+    assert_eq!(body["data"][0]["ref_id"], "tenant_42");
+    
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+#[tokio::test]
+async fn test_jwt_mode_expired_token_rejected() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let (app, _temp_db) = setup_test_app();
+    let token = make_jwt("jwt_test_secret", "tenant_42", -3600);
+    
+    let response = create_with_auth(&app, &format!("Bearer {}", token), json!({
+        "url": "https://example.com/jwt-expired"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+#[tokio::test]
+async fn test_jwt_mode_tampered_token_rejected() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    // Signed with the wrong secret
+    let forged = make_jwt("not_the_secret", "tenant_42", 3600);
+    let response = create_with_auth(&app, &format!("Bearer {}", forged), json!({
+        "url": "https://example.com/jwt-forged"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    // Valid signature, but the payload was modified afterwards
+    let token = make_jwt("jwt_test_secret", "tenant_42", 3600);
+    let mut parts: Vec<String> = token.split('.').map(String::from).collect();
+    let other = make_jwt("jwt_test_secret", "tenant_99", 3600);
+    parts[1] = other.split('.').nth(1).unwrap().to_string();
+    let response = create_with_auth(&app, &format!("Bearer {}", parts.join(".")), json!({
+        "url": "https://example.com/jwt-tampered"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    // The shared-secret style header is not accepted in JWT mode
+    let response = create_with_auth(&app, "jwt_test_secret", json!({
+        "url": "https://example.com/jwt-plain"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+/// Helper function to call the auth check endpoint, optionally with an Authorization header
+async fn auth_check(app: &axum::Router, authorization: Option<&str>) -> axum::response::Response {
+    let mut request = Request::builder().method("GET").uri("/api/auth/check");
+    if let Some(authorization) = authorization {
+        request = request.header("Authorization", authorization);
+    }
+
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_auth_check_valid_token() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "secret_token");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = auth_check(&app, Some("secret_token")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body, json!({ "authenticated": true }));
+    
+    env::remove_var("AUTHORIZATION");
+    
+    // In JWT mode the verified subject is echoed back
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let token = make_jwt("jwt_test_secret", "tenant_42", 3600);
+    let response = auth_check(&app, Some(&format!("Bearer {}", token))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["authenticated"], true);
+    assert_eq!(body["subject"], "tenant_42");
+    
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+#[tokio::test]
+async fn test_auth_check_invalid_token() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "secret_token");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = auth_check(&app, Some("wrong_token")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    let response = auth_check(&app, None).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    env::remove_var("AUTHORIZATION");
+}
+
+#[tokio::test]
+async fn test_auth_check_auth_disabled() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::remove_var("AUTHORIZATION");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = auth_check(&app, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body, json!({ "authenticated": true }));
+}
+
+#[tokio::test]
+async fn test_auth_required_reports_enabled_modes_without_credentials() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "secret_token");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = get_anonymous(&app, "/api/auth/required").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body, json!({ "auth_required": true, "mode": "secret" }));
+    
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let response = get_anonymous(&app, "/api/auth/required").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body, json!({ "auth_required": true, "mode": "jwt" }));
+    
+    env::remove_var("AUTHORIZATION");
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+#[tokio::test]
+async fn test_auth_required_reports_disabled_auth() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", " , ");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = get_anonymous(&app, "/api/auth/required").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body, json!({ "auth_required": false, "mode": "none" }));
+    
+    env::remove_var("AUTHORIZATION");
+}
+
+#[tokio::test]
+async fn test_auth_middleware_accepts_any_of_several_keys() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "key_alice, key_bob");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = auth_check(&app, Some("key_alice")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let response = auth_check(&app, Some("key_bob")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let response = create_with_auth(&app, "key_bob", json!({ "url": "https://example.com/multi-key" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // Neither an unknown key nor the whole list is accepted
+    let response = auth_check(&app, Some("key_carol")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    let response = auth_check(&app, Some("key_alice, key_bob")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    env::remove_var("AUTHORIZATION");
+}
+
+/// Helper function to issue a GET without credentials
+async fn get_anonymous(app: &axum::Router, uri: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_preview_token_grants_stats_access_without_credentials() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "secret_token");
+    
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let app = create_app(AppState::with_config(db, Config {
+        preview_token_secret: Some("preview-secret".to_string()),
+        ..Config::default()
+    }));
+    
+    for id in ["shared", "private"] {
+        let response = create_with_auth(&app, "secret_token", json!({ "url": "https://example.com", "custom_id": id, "ref_id": "owner" })).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls/shared/preview-token")
+                .header("content-type", "application/json")
+                .header("Authorization", "secret_token")
+                .body(Body::from(json!({ "ref_id": "owner", "ttl_seconds": 600 }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    let token = body["token"].as_str().unwrap().to_string();
+    assert!(body["expires_at"].is_string());
+    
+    // A valid token opens the stats, but nothing else
+    let response = get_anonymous(&app, body["stats_url"].as_str().unwrap()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], "shared");
+    
+    let response = get_anonymous(&app, "/api/urls/shared/stats").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = get_anonymous(&app, &format!("/api/urls/shared/audit?token={}", token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = get_anonymous(&app, &format!("/api/urls/private/stats?token={}", token)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    env::remove_var("AUTHORIZATION");
+}
+
+#[tokio::test]
+async fn test_preview_token_rejects_expired_and_tampered_tokens() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "secret_token");
+    
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let app = create_app(AppState::with_config(db, Config {
+        preview_token_secret: Some("preview-secret".to_string()),
+        ..Config::default()
+    }));
+    create_with_auth(&app, "secret_token", json!({ "url": "https://example.com", "custom_id": "shared" })).await;
+    
+    let expired = preview_token::mint("preview-secret", "shared", chrono::Utc::now() - chrono::Duration::seconds(1));
+    let response = get_anonymous(&app, &format!("/api/urls/shared/stats?token={}", expired)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "invalid_token");
+    
+    let valid = preview_token::mint("preview-secret", "shared", chrono::Utc::now() + chrono::Duration::minutes(5));
+    let response = get_anonymous(&app, &format!("/api/urls/shared/stats?token={}", valid)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    // Flipping a signature character or signing with another secret both fail
+    let position = valid.len() - 5;
+    let flipped = if &valid[position..=position] == "A" { "B" } else { "A" };
+    let tampered = format!("{}{}{}", &valid[..position], flipped, &valid[position + 1..]);
+    let forged = preview_token::mint("wrong-secret", "shared", chrono::Utc::now() + chrono::Duration::minutes(5));
+    for token in [tampered, forged] {
+        let response = get_anonymous(&app, &format!("/api/urls/shared/stats?token={}", token)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    
+    env::remove_var("AUTHORIZATION");
+}
+
+#[tokio::test]
+async fn test_rate_limit_exempts_only_verified_subjects() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let app = create_app(AppState::with_config(db, Config {
+        rate_limit_per_minute: 1,
+        rate_limit_burst: 2,
+        rate_limit_exempt_ref_ids: ["internal".to_string()].into_iter().collect(),
+        ..Config::default()
+    }));
+    
+    // A normal tenant exhausts its burst, even when it claims the exempt ref_id
+    let tenant = format!("Bearer {}", make_jwt("jwt_test_secret", "tenant", 3600));
+    for _ in 0..2 {
+        let response = auth_check(&app, Some(&tenant)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/urls?ref_id=internal")
+                .header("Authorization", &tenant)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    
+    // The exempt service's own token is never limited
+    let internal = format!("Bearer {}", make_jwt("jwt_test_secret", "internal", 3600));
+    for _ in 0..10 {
+        let response = auth_check(&app, Some(&internal)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+#[tokio::test]
+async fn test_admin_routes_need_admin_key() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "tenant_key");
+    env::set_var("ADMIN_KEYS", "ops_key");
+    
+    let (app, _temp_db) = setup_test_app();
+    let response = create_with_auth(&app, "tenant_key", json!({
+        "url": "https://example.com/admin-gate",
+        "custom_id": "gated",
+        "ref_id": "tenant"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let admin_get = |authorization: &'static str, uri: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header("Authorization", authorization)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+    
+    // A tenant key passes auth but not the admin gate
+    let response = admin_get("tenant_key", "/api/admin/raw/gated").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "admin_required");
+    let response = admin_get("tenant_key", "/api/urls/gated/audit").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    
+    // The admin key works there, and for tenant routes too
+    let response = admin_get("ops_key", "/api/admin/raw/gated").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = admin_get("ops_key", "/api/urls/gated/audit").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = admin_get("ops_key", "/api/auth/check").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    env::remove_var("AUTHORIZATION");
+    env::remove_var("ADMIN_KEYS");
+}
+
+#[tokio::test]
+async fn test_jwt_admin_routes_need_admin_role() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTH_MODE", "jwt");
+    env::set_var("JWT_SECRET", "jwt_test_secret");
+    
+    let (app, _temp_db) = setup_test_app();
+    
+    let tenant = make_jwt("jwt_test_secret", "tenant_42", 3600);
+    let admin = encode(
+        &Header::default(),
+        &json!({ "sub": "ops", "role": "admin", "exp": chrono::Utc::now().timestamp() + 3600 }),
+        &EncodingKey::from_secret("jwt_test_secret".as_bytes()),
+    )
+    .unwrap();
+    
+    for (token, status) in [(tenant, StatusCode::FORBIDDEN), (admin, StatusCode::OK)] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/metrics")
+                    .header("Authorization", format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status);
+    }
+    
+    env::remove_var("AUTH_MODE");
+    env::remove_var("JWT_SECRET");
+}
+
+#[tokio::test]
+async fn test_rate_limit_buckets_per_api_key() {
+    let _guard = ENV_MUTEX.lock().await;
+    env::set_var("AUTHORIZATION", "key_alice,key_bob");
+    
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let app = create_app(AppState::with_config(db, Config {
+        rate_limit_per_minute: 1,
+        rate_limit_burst: 2,
+        ..Config::default()
+    }));
+    
+    for _ in 0..2 {
+        assert_eq!(auth_check(&app, Some("key_alice")).await.status(), StatusCode::OK);
+    }
+    assert_eq!(auth_check(&app, Some("key_alice")).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    
+    // One noisy integration does not throttle another
+    assert_eq!(auth_check(&app, Some("key_bob")).await.status(), StatusCode::OK);
+    
+    env::remove_var("AUTHORIZATION");
+}
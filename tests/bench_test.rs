@@ -7,11 +7,13 @@ use std::time::Instant;
 use tempfile::NamedTempFile;
 
 use shortener::database::{init_db, AppState};
-use shortener::model::{CreateRequest, ListParams};
+use shortener::model::{CreateRequest, ListParams, TimeFormat, TimeFormatParams};
 use shortener::handler::{create_short_url, list_urls};
+use shortener::record_format::{self, RecordFormat};
 
 use axum::{
-    extract::{Query, State},
+    extract::{OriginalUri, Query, State},
+    http::{HeaderMap, Method, Uri},
     Json,
 };
 
@@ -36,6 +38,11 @@ where
     println!("    Throughput: {} ops/sec\n", ops_per_sec);
 }
 
+/// Request URI handed to `list_urls` when it is called directly
+fn list_uri() -> OriginalUri {
+    OriginalUri(Uri::from_static("/api/urls"))
+}
+
 #[tokio::test]
 #[ignore] // Run explicitly with: cargo test bench --release -- --ignored --nocapture
 async fn bench_create_urls() {
@@ -43,7 +50,7 @@ async fn bench_create_urls() {
     
     let temp_db = NamedTempFile::new().unwrap();
     let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
-    let state = AppState { db: Arc::new(db) };
+    let state = AppState::new(db);
     
     // Benchmark with ref_id
     let iterations = 1000;
@@ -53,10 +60,11 @@ async fn bench_create_urls() {
             url: "https://example.com/bench".to_string(),
             ref_id: Some("bench_user".to_string()),
             custom_id: None,
+            ..Default::default()
         };
         
         tokio::runtime::Handle::current().block_on(async {
-            let _ = create_short_url(State(state_clone), Json(req)).await;
+            let _ = create_short_url(State(state_clone), None, HeaderMap::new(), Query(TimeFormatParams::default()), Json(req)).await;
         });
     });
     
@@ -67,10 +75,11 @@ async fn bench_create_urls() {
             url: "https://example.com/public".to_string(),
             ref_id: None,
             custom_id: None,
+            ..Default::default()
         };
         
         tokio::runtime::Handle::current().block_on(async {
-            let _ = create_short_url(State(state_clone), Json(req)).await;
+            let _ = create_short_url(State(state_clone), None, HeaderMap::new(), Query(TimeFormatParams::default()), Json(req)).await;
         });
     });
 }
@@ -82,7 +91,7 @@ async fn bench_list_urls() {
     
     let temp_db = NamedTempFile::new().unwrap();
     let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
-    let state = AppState { db: Arc::new(db) };
+    let state = AppState::new(db);
     
     // Create 1000 URLs first
     println!("  Preparing: Creating 1000 URLs...");
@@ -91,8 +100,9 @@ async fn bench_list_urls() {
             url: format!("https://example.com/list{}", i),
             ref_id: Some("list_bench_user".to_string()),
             custom_id: None,
+            ..Default::default()
         };
-        create_short_url(State(state.clone()), Json(req)).await;
+        create_short_url(State(state.clone()), None, HeaderMap::new(), Query(TimeFormatParams::default()), Json(req)).await;
     }
     println!("  Done!\n");
     
@@ -104,10 +114,13 @@ async fn bench_list_urls() {
             ref_id: Some("list_bench_user".to_string()),
             page: Some(1),
             limit: Some(10),
+            time_format: TimeFormat::default(),
+            sort: None,
+            format: None,
         };
         
         tokio::runtime::Handle::current().block_on(async {
-            let _ = list_urls(State(state_clone), Query(params)).await;
+            let _ = list_urls(Method::GET, State(state_clone), list_uri(), None, HeaderMap::new(), Query(params)).await;
         });
     });
     
@@ -118,10 +131,13 @@ async fn bench_list_urls() {
             ref_id: None,
             page: Some(1),
             limit: Some(10),
+            time_format: TimeFormat::default(),
+            sort: None,
+            format: None,
         };
         
         tokio::runtime::Handle::current().block_on(async {
-            let _ = list_urls(State(state_clone), Query(params)).await;
+            let _ = list_urls(Method::GET, State(state_clone), list_uri(), None, HeaderMap::new(), Query(params)).await;
         });
     });
 }
@@ -133,7 +149,7 @@ async fn bench_database_scaling() {
     
     let temp_db = NamedTempFile::new().unwrap();
     let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
-    let state = AppState { db: Arc::new(db) };
+    let state = AppState::new(db);
     
     // Test performance at different database sizes
     let sizes = [100, 1000, 10000, 50000];
@@ -148,8 +164,9 @@ async fn bench_database_scaling() {
                 url: format!("https://example.com/scale{}", i),
                 ref_id: Some("scale_user".to_string()),
                 custom_id: None,
+                ..Default::default()
             };
-            create_short_url(State(state.clone()), Json(req)).await;
+            create_short_url(State(state.clone()), None, HeaderMap::new(), Query(TimeFormatParams::default()), Json(req)).await;
         }
         let fill_time = start.elapsed();
         println!("    Fill time: {:?}", fill_time);
@@ -160,8 +177,11 @@ async fn bench_database_scaling() {
             ref_id: Some("scale_user".to_string()),
             page: Some(1),
             limit: Some(10),
+            time_format: TimeFormat::default(),
+            sort: None,
+            format: None,
         };
-        list_urls(State(state.clone()), Query(params)).await;
+        let _ = list_urls(Method::GET, State(state.clone()), list_uri(), None, HeaderMap::new(), Query(params)).await;
         let query_time = start.elapsed();
         println!("    Query time: {:?}", query_time);
         println!();
@@ -175,7 +195,7 @@ async fn bench_concurrent_operations() {
     
     let temp_db = NamedTempFile::new().unwrap();
     let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
-    let state = Arc::new(AppState { db: Arc::new(db) });
+    let state = Arc::new(AppState::new(db));
     
     let num_tasks = 100;
     let ops_per_task = 10;
@@ -195,8 +215,9 @@ async fn bench_concurrent_operations() {
                     url: format!("https://example.com/concurrent-{}-{}", task_id, op_id),
                     ref_id: Some(format!("user_{}", task_id)),
                     custom_id: None,
+                    ..Default::default()
                 };
-                create_short_url(State(state_clone.as_ref().clone()), Json(req)).await;
+                create_short_url(State(state_clone.as_ref().clone()), None, HeaderMap::new(), Query(TimeFormatParams::default()), Json(req)).await;
             }
         });
         
@@ -217,6 +238,36 @@ async fn bench_concurrent_operations() {
     println!("  Throughput: {:.0} ops/sec\n", ops_per_sec);
 }
 
+#[test]
+#[ignore]
+fn bench_record_formats() {
+    println!("\n=== Benchmark: Record Formats ===\n");
+    
+    let record = serde_json::from_value(serde_json::json!({
+        "id": "abc123",
+        "original_url": "https://example.com/some/fairly/long/path?utm_source=newsletter&utm_medium=email",
+        "short_url": "http://localhost:8080/abc123",
+        "ref_id": "bench_user",
+        "created_at": "2026-01-17T13:40:00.123456Z",
+        "clicks": 1234,
+        "tags": ["spring", "email"]
+    }))
+    .unwrap();
+    
+    let iterations = 100_000;
+    for format in [RecordFormat::Json, RecordFormat::Bincode] {
+        let encoded = format.encode(&record);
+        println!("  {:?}: {} bytes stored\n", format, encoded.len());
+        
+        benchmark(&format!("{:?} encode", format), iterations, || {
+            std::hint::black_box(format.encode(std::hint::black_box(&record)));
+        });
+        benchmark(&format!("{:?} decode", format), iterations, || {
+            std::hint::black_box(record_format::decode(std::hint::black_box(&encoded)).unwrap());
+        });
+    }
+}
+
 #[test]
 fn bench_summary() {
     println!("\n{}", "=".repeat(60));
@@ -229,5 +280,6 @@ fn bench_summary() {
     println!("  • bench_list_urls           - Query performance with/without index");
     println!("  • bench_database_scaling    - Performance at different DB sizes");
     println!("  • bench_concurrent_operations - Concurrent access patterns");
+    println!("  • bench_record_formats      - JSON vs bincode record size and speed");
     println!("\n{}\n", "=".repeat(60));
 }
@@ -0,0 +1,109 @@
+//! Tests for behavior driven by environment configuration
+//! 
+//! Configuration is read from process-wide environment variables, so every
+//! test here holds `ENV_MUTEX` while it mutates them.
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use http_body_util::BodyExt;
+use std::env;
+use std::io::Write;
+use tempfile::NamedTempFile;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+use shortener::database::{init_db, AppState};
+use shortener::route::create_app;
+
+// Mutex to ensure tests that modify env vars don't run in parallel
+static ENV_MUTEX: Mutex<()> = Mutex::const_new(());
+
+fn setup_test_app() -> (axum::Router, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::new(db);
+    (create_app(state), temp_db)
+}
+
+/// Helper function to read the response body as a UTF-8 string
+async fn response_text(body: Body) -> String {
+    let bytes = body
+        .collect()
+        .await
+        .expect("Failed to read response body")
+        .to_bytes();
+
+    String::from_utf8(bytes.to_vec()).expect("Response body is not UTF-8")
+}
+
+#[tokio::test]
+async fn test_not_found_template_served_to_browsers() {
+    let _guard = ENV_MUTEX.lock().await;
+
+    let mut template = NamedTempFile::new().expect("Failed to create template file");
+    write!(template, "<html><body><h1>Branded missing page</h1></body></html>").unwrap();
+    env::set_var("NOT_FOUND_TEMPLATE_PATH", template.path());
+
+    let (app, _temp_db) = setup_test_app();
+    env::remove_var("NOT_FOUND_TEMPLATE_PATH");
+
+    // Browser request gets the custom page
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/does-not-exist")
+                .header("accept", "text/html,application/xhtml+xml")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response_text(response.into_body()).await;
+    assert!(body.contains("Branded missing page"));
+
+    // Non-browser request keeps the default body
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/does-not-exist")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response_text(response.into_body()).await, "URL not found");
+}
+
+#[tokio::test]
+async fn test_not_found_template_unreadable_falls_back() {
+    let _guard = ENV_MUTEX.lock().await;
+
+    env::set_var("NOT_FOUND_TEMPLATE_PATH", "/nonexistent/dir/404.html");
+    let (app, _temp_db) = setup_test_app();
+    env::remove_var("NOT_FOUND_TEMPLATE_PATH");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/does-not-exist")
+                .header("accept", "text/html")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response_text(response.into_body()).await, "URL not found");
+}
@@ -0,0 +1,299 @@
+//! Integration tests for database initialization
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use redb::{Database, ReadableDatabase, TableDefinition, TableHandle};
+use std::io::{self, ErrorKind};
+use tempfile::NamedTempFile;
+
+use shortener::database::{init_db, is_retryable, with_write_retry, InitError, TABLE_CREATED_INDEX, TABLE_REF_INDEX, TABLE_URLS};
+use shortener::migrations::{apply_migrations, schema_version, MIGRATIONS};
+use shortener::model::UrlRecord;
+use shortener::record_format::{self, convert_records, RecordFormat};
+
+#[test]
+fn test_init_db_is_idempotent() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("First initialization failed");
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        table.insert("kept", r#"{"id":"kept"}"#).unwrap();
+    }
+    write_txn.commit().unwrap();
+    drop(db);
+    
+    // Reopening an initialized file succeeds and leaves existing data alone
+    let db = init_db(db_path).expect("Second initialization failed");
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    assert_eq!(table.get("kept").unwrap().unwrap().value(), r#"{"id":"kept"}"#);
+}
+
+#[test]
+fn test_init_db_reports_schema_mismatch() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    // Simulate a file written by a release that stored `urls_v1` with other types
+    {
+        let incompatible: TableDefinition<&str, u64> = TableDefinition::new("urls_v1");
+        let db = Database::create(db_path).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn.open_table(incompatible).unwrap().insert("abc123", 7).unwrap();
+        write_txn.commit().unwrap();
+    }
+    
+    match init_db(db_path) {
+        Err(InitError::SchemaMismatch { table, .. }) => assert_eq!(table, "urls_v1"),
+        Err(err) => panic!("expected a schema mismatch, got: {}", err),
+        Ok(_) => panic!("expected a schema mismatch, got a database"),
+    }
+}
+
+#[test]
+fn test_migrations_preserve_v1_records() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    // A record as written before any of the optional fields existed
+    let v1_record = r#"{"id":"legacy","original_url":"https://example.com/legacy","short_url":"http://localhost:8080/legacy","ref_id":"user_1","created_at":"2026-01-17T13:40:00Z","clicks":3}"#;
+    {
+        let db = Database::create(db_path).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn.open_table(TABLE_URLS).unwrap().insert("legacy", v1_record).unwrap();
+        write_txn.commit().unwrap();
+    }
+    
+    let db = init_db(db_path).expect("Initialization failed");
+    assert_eq!(schema_version(&db).unwrap(), MIGRATIONS.len() as u64);
+    
+    let stored = {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE_URLS).unwrap();
+        let value = table.get("legacy").unwrap().unwrap();
+        value.value().to_string()
+    };
+    let record: UrlRecord = serde_json::from_str(&stored).unwrap();
+    assert_eq!(record.original_url, "https://example.com/legacy");
+    assert_eq!(record.ref_id.as_deref(), Some("user_1"));
+    assert_eq!(record.clicks, 3);
+    assert!(record.is_active);
+    
+    // A further no-op step bumps the version and leaves the data untouched
+    let mut migrations = MIGRATIONS.to_vec();
+    migrations.push(|_| Ok(()));
+    apply_migrations(&db, &migrations).unwrap();
+    assert_eq!(schema_version(&db).unwrap(), migrations.len() as u64);
+    
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    assert_eq!(table.get("legacy").unwrap().unwrap().value(), stored);
+    drop(read_txn);
+    
+    // Reopening with the shorter list refuses the newer database
+    drop(db);
+    assert!(matches!(
+        init_db(db_path),
+        Err(InitError::UnsupportedVersion { .. })
+    ));
+}
+
+#[test]
+fn test_migrations_rebuild_ref_index_with_slugs() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    // A version 2 database whose ref_id index still holds (stale) record copies
+    let record = r#"{"id":"indexed","original_url":"https://example.com","short_url":"http://localhost:8080/indexed","ref_id":"user_1","created_at":"2026-01-17T13:40:00Z","clicks":7}"#;
+    let stale = r#"{"id":"indexed","original_url":"https://example.com","short_url":"http://localhost:8080/indexed","ref_id":"user_1","created_at":"2026-01-17T13:40:00Z","clicks":0}"#;
+    let v1_index: TableDefinition<&str, &str> = TableDefinition::new("ref_index_v1");
+    {
+        let db = Database::create(db_path).unwrap();
+        apply_migrations(&db, &MIGRATIONS[..2]).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn.open_table(TABLE_URLS).unwrap().insert("indexed", record).unwrap();
+        write_txn.open_table(v1_index).unwrap().insert("user_1:1768657200000000", stale).unwrap();
+        write_txn.commit().unwrap();
+    }
+    
+    let db = init_db(db_path).expect("Initialization failed");
+    let read_txn = db.begin_read().unwrap();
+    let index = read_txn.open_table(TABLE_REF_INDEX).unwrap();
+    assert_eq!(index.get("user_1:1768657200000000").unwrap().unwrap().value(), "indexed");
+    
+    // Version 4 indexes existing links by creation time
+    let created = read_txn.open_table(TABLE_CREATED_INDEX).unwrap();
+    assert_eq!(created.get("00001768657200000000:indexed").unwrap().unwrap().value(), "indexed");
+    
+    let tables: Vec<String> = read_txn.list_tables().unwrap().map(|table| table.name().to_string()).collect();
+    assert!(!tables.contains(&"ref_index_v1".to_string()));
+}
+
+#[tokio::test]
+async fn test_write_retry_recovers_from_transient_failure() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    
+    let mut attempts = 0;
+    let result = with_write_retry(3, || {
+        attempts += 1;
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(TABLE_URLS)?;
+            table.insert("retried", r#"{"id":"retried"}"#)?;
+        }
+        // The first attempt fails before committing, so its insert is discarded
+        if attempts == 1 {
+            return Err(redb::Error::Io(io::Error::from(ErrorKind::Interrupted)));
+        }
+        write_txn.commit()?;
+        Ok(attempts)
+    })
+    .await;
+    
+    assert_eq!(result.unwrap(), 2);
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    assert!(table.get("retried").unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_write_retry_gives_up_and_skips_permanent_errors() {
+    // Transient errors are retried `retries` times, then returned
+    let mut attempts = 0;
+    let result: Result<(), _> = with_write_retry(2, || {
+        attempts += 1;
+        Err(redb::Error::Io(io::Error::from(ErrorKind::TimedOut)))
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(attempts, 3);
+    
+    // Permanent errors propagate immediately
+    let mut attempts = 0;
+    let result: Result<(), _> = with_write_retry(5, || {
+        attempts += 1;
+        Err(redb::Error::Corrupted("bad page".to_string()))
+    })
+    .await;
+    assert!(matches!(result, Err(redb::Error::Corrupted(_))));
+    assert_eq!(attempts, 1);
+    
+    assert!(is_retryable(&redb::Error::TransactionInProgress));
+    assert!(!is_retryable(&redb::Error::PreviousIo));
+    assert!(!is_retryable(&redb::Error::Io(io::Error::from(ErrorKind::PermissionDenied))));
+}
+
+/// A record using every kind of field, for round-trip tests
+fn sample_record() -> UrlRecord {
+    let v1_record = r#"{"id":"fmt","original_url":"https://example.com/fmt?a=1,b","short_url":"http://localhost:8080/fmt","ref_id":"user_1","created_at":"2026-01-17T13:40:00.123456Z","clicks":7}"#;
+    let mut record: UrlRecord = serde_json::from_str(v1_record).unwrap();
+    record.tags = vec!["spring".to_string(), "email".to_string()];
+    record.expires_at = Some(record.created_at + chrono::Duration::days(7));
+    record.allowed_referrers = vec!["partner.example".to_string()];
+    record.source = Some("cli".to_string());
+    record
+}
+
+/// Reads the raw value stored for `id`
+fn stored_value(db: &Database, id: &str) -> String {
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    let value = table.get(id).unwrap().unwrap();
+    value.value().to_string()
+}
+
+#[test]
+fn test_record_formats_round_trip() {
+    let record = sample_record();
+    
+    for format in [RecordFormat::Json, RecordFormat::Bincode] {
+        let encoded = format.encode(&record);
+        assert_eq!(RecordFormat::of(&encoded), format);
+        
+        let decoded = record_format::decode(&encoded).unwrap();
+        assert_eq!(format.encode(&decoded), encoded, "{:?}", format);
+        assert_eq!(decoded.original_url, record.original_url);
+        assert_eq!(decoded.created_at, record.created_at);
+        assert_eq!(decoded.tags, record.tags);
+        assert_eq!(decoded.expires_at, record.expires_at);
+    }
+    
+    // Bincode is the smaller of the two
+    assert!(RecordFormat::Bincode.encode(&record).len() < RecordFormat::Json.encode(&record).len());
+    
+    assert!(record_format::decode("bincode:not base64!").is_err());
+    assert_eq!("BINCODE".parse::<RecordFormat>().unwrap(), RecordFormat::Bincode);
+    assert!("msgpack".parse::<RecordFormat>().is_err());
+}
+
+#[test]
+fn test_migrations_reencode_bincode_records_of_older_layouts() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    // Older layouts are the current one without its trailing fields:
+    // `bundle_members` (8-byte length), `max_unique_visitors` (1-byte tag)
+    // and, for the first layout, `source` (1-byte tag)
+    let legacy_value = |record: &UrlRecord, trailing: usize| {
+        let bytes = bincode::serialize(record).unwrap();
+        format!("bincode:{}", STANDARD.encode(&bytes[..bytes.len() - trailing]))
+    };
+    let mut first = sample_record();
+    first.id = "first".to_string();
+    first.source = None;
+    let mut with_source = sample_record();
+    with_source.id = "with-source".to_string();
+    {
+        let db = Database::create(db_path).unwrap();
+        apply_migrations(&db, &MIGRATIONS[..4]).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+            table.insert("first", legacy_value(&first, 10).as_str()).unwrap();
+            table.insert("with-source", legacy_value(&with_source, 9).as_str()).unwrap();
+            table.insert("garbled", "bincode:AAAA").unwrap();
+        }
+        write_txn.commit().unwrap();
+    }
+    assert!(record_format::decode(&legacy_value(&first, 10)).is_err());
+    
+    let db = init_db(db_path).expect("Initialization failed");
+    for record in [&first, &with_source] {
+        let stored = stored_value(&db, &record.id);
+        assert_eq!(stored, RecordFormat::Bincode.encode(record));
+        let decoded = record_format::decode(&stored).unwrap();
+        assert_eq!(decoded.tags, record.tags);
+        assert_eq!(decoded.source, record.source);
+        assert!(decoded.bundle_members.is_empty());
+    }
+    assert_eq!(stored_value(&db, "garbled"), "bincode:AAAA");
+}
+
+#[test]
+fn test_convert_records_migrates_both_ways() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let record = sample_record();
+    {
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(TABLE_URLS)
+            .unwrap()
+            .insert("fmt", RecordFormat::Json.encode(&record).as_str())
+            .unwrap();
+        write_txn.commit().unwrap();
+    }
+    
+    // JSON is the starting format, so there is nothing to do
+    assert_eq!(convert_records(&db, RecordFormat::Json).unwrap(), 0);
+    
+    assert_eq!(convert_records(&db, RecordFormat::Bincode).unwrap(), 1);
+    assert_eq!(stored_value(&db, "fmt"), RecordFormat::Bincode.encode(&record));
+    assert_eq!(convert_records(&db, RecordFormat::Bincode).unwrap(), 0);
+    
+    assert_eq!(convert_records(&db, RecordFormat::Json).unwrap(), 1);
+    assert_eq!(stored_value(&db, "fmt"), RecordFormat::Json.encode(&record));
+}
@@ -0,0 +1,190 @@
+//! Integration tests for GeoIP country lookup on click events
+//! 
+//! A tiny MaxMind DB is written on the fly: a one-node IPv4 search tree whose
+//! left branch (addresses 0.0.0.0 - 127.255.255.255) maps to `{"country":
+//! {"iso_code": "GB"}}` and whose right branch has no data.
+//! 
+//! Countries are only stored on click events, so these need the `analytics` feature.
+
+#![cfg(feature = "analytics")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use redb::{Database, ReadableDatabase, ReadableTable};
+use serde_json::json;
+use std::io::Write;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tower::ServiceExt;
+
+use shortener::config::Config;
+use shortener::database::{init_db, AppState, TABLE_CLICK_EVENTS};
+use shortener::geoip::GeoIp;
+use shortener::model::ClickEvent;
+use shortener::route::create_app;
+
+/// Encodes a MaxMind DB control byte (plus extended type byte) for `size`
+fn control(type_id: u8, size: usize, out: &mut Vec<u8>) {
+    assert!(size < 29);
+    if type_id <= 7 {
+        out.push((type_id << 5) | size as u8);
+    } else {
+        out.push(size as u8);
+        out.push(type_id - 7);
+    }
+}
+
+/// Encodes an unsigned integer of MaxMind type `type_id` using minimal bytes
+fn uint(type_id: u8, value: u64, out: &mut Vec<u8>) {
+    let bytes: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+    control(type_id, bytes.len(), out);
+    out.extend(bytes);
+}
+
+/// Encodes a UTF-8 string
+fn string(value: &str, out: &mut Vec<u8>) {
+    control(2, value.len(), out);
+    out.extend(value.as_bytes());
+}
+
+/// Writes the test database and returns its file
+fn write_test_mmdb() -> NamedTempFile {
+    let mut db = Vec::new();
+    
+    // Search tree: one node with 24-bit records. Left -> data at offset 0
+    // (node_count + 16 + 0 = 17), right -> "no data" (node_count = 1)
+    db.extend([0x00, 0x00, 0x11, 0x00, 0x00, 0x01]);
+    db.extend([0u8; 16]);
+    
+    // Data section: {"country": {"iso_code": "GB"}}
+    control(7, 1, &mut db);
+    string("country", &mut db);
+    control(7, 1, &mut db);
+    string("iso_code", &mut db);
+    string("GB", &mut db);
+    
+    // Metadata
+    db.extend(b"\xAB\xCD\xEFMaxMind.com");
+    control(7, 9, &mut db);
+    string("binary_format_major_version", &mut db);
+    uint(5, 2, &mut db);
+    string("binary_format_minor_version", &mut db);
+    uint(5, 0, &mut db);
+    string("build_epoch", &mut db);
+    uint(9, 1_700_000_000, &mut db);
+    string("database_type", &mut db);
+    string("Test-Country", &mut db);
+    string("description", &mut db);
+    control(7, 0, &mut db);
+    string("ip_version", &mut db);
+    uint(5, 4, &mut db);
+    string("languages", &mut db);
+    control(11, 1, &mut db);
+    string("en", &mut db);
+    string("node_count", &mut db);
+    uint(6, 1, &mut db);
+    string("record_size", &mut db);
+    uint(5, 24, &mut db);
+    
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&db).unwrap();
+    file
+}
+
+/// Helper function to create a test application using `geoip_db`
+fn setup_test_app(geoip_db: Option<String>) -> (axum::Router, Arc<Database>, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, Config { geoip_db, ..Config::default() });
+    let db = state.db.clone();
+    
+    (create_app(state), db, temp_db)
+}
+
+/// Helper function to visit a short link from `ip`
+async fn visit(app: &axum::Router, uri: &str, ip: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("x-forwarded-for", ip)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Helper function to read every stored click event
+fn stored_events(db: &Database) -> Vec<ClickEvent> {
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_CLICK_EVENTS).unwrap();
+    table
+        .iter()
+        .unwrap()
+        .map(|entry| serde_json::from_str(entry.unwrap().1.value()).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_lookup_in_test_database() {
+    let mmdb = write_test_mmdb();
+    let geoip = GeoIp::open(mmdb.path().to_str().unwrap()).unwrap();
+    
+    assert_eq!(geoip.country_code("81.2.69.142".parse().unwrap()).as_deref(), Some("GB"));
+    assert_eq!(geoip.country_code("203.0.113.7".parse().unwrap()), None);
+}
+
+#[tokio::test]
+async fn test_click_events_record_country() {
+    let mmdb = write_test_mmdb();
+    let (app, db, _temp_db) = setup_test_app(Some(mmdb.path().to_str().unwrap().to_string()));
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "url": "https://example.com", "custom_id": "geo" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    assert_eq!(visit(&app, "/geo", "81.2.69.142").await, StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(visit(&app, "/geo", "203.0.113.7").await, StatusCode::TEMPORARY_REDIRECT);
+    
+    let mut countries: Vec<Option<String>> = stored_events(&db).into_iter().map(|event| event.country).collect();
+    countries.sort();
+    assert_eq!(countries, vec![None, Some("GB".to_string())]);
+}
+
+#[tokio::test]
+async fn test_missing_geoip_database_is_a_no_op() {
+    let (app, db, _temp_db) = setup_test_app(Some("/nonexistent/GeoLite2-Country.mmdb".to_string()));
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "url": "https://example.com", "custom_id": "nogeo" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    assert_eq!(visit(&app, "/nogeo", "81.2.69.142").await, StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(stored_events(&db)[0].country, None);
+}
@@ -8,17 +8,30 @@
 
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    extract::ConnectInfo,
+    http::{HeaderName, Request, StatusCode},
 };
 use http_body_util::BodyExt;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use redb::{Database, ReadableDatabase, ReadableTable, ReadableTableMetadata};
 use serde_json::{json, Value};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tempfile::NamedTempFile;
 use tower::ServiceExt;
 
 // Import from the main crate
-use shortener::database::{init_db, AppState};
+use shortener::config::Config;
+use shortener::creation_stats::record_creation;
+#[cfg(not(feature = "analytics"))]
+use shortener::database::TABLE_CLICK_EVENTS;
+use shortener::database::{init_db, AppState, TABLE_REF_INDEX, TABLE_URLS, TABLE_VISITORS};
+use shortener::dedup::DedupScope;
+use shortener::handler::top_by_clicks;
+use shortener::ratelimit::retry_after_with_jitter;
+use shortener::record_format::RecordFormat;
 use shortener::route::create_app;
+use shortener::tags::{normalize_tags, TagCase};
 
 /// Helper function to create a test application with a temporary database
 fn setup_test_app() -> (axum::Router, NamedTempFile) {
@@ -28,9 +41,7 @@ fn setup_test_app() -> (axum::Router, NamedTempFile) {
     
     // Initialize database
     let db = init_db(db_path).expect("Failed to initialize test database");
-    let state = AppState {
-        db: Arc::new(db),
-    };
+    let state = AppState::new(db);
     
     // Create the app
     let app = create_app(state);
@@ -38,6 +49,65 @@ fn setup_test_app() -> (axum::Router, NamedTempFile) {
     (app, temp_db)
 }
 
+/// Helper function to create a test application with an explicit configuration
+fn setup_test_app_with_config(config: Config) -> (axum::Router, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, config);
+    
+    (create_app(state), temp_db)
+}
+
+/// Helper function to create a test application that also exposes its database
+/// 
+/// Lets tests seed or inspect tables directly alongside HTTP requests.
+fn setup_test_app_with_db() -> (axum::Router, Arc<Database>, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::new(db);
+    let db = state.db.clone();
+    
+    (create_app(state), db, temp_db)
+}
+
+/// Helper function to issue a GET request against the app
+async fn get(app: &axum::Router, uri: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Helper function to POST a JSON payload to the app
+async fn post_json(app: &axum::Router, uri: &str, payload: Value) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Helper function to create a short URL from a JSON payload
+async fn create_url(app: &axum::Router, payload: Value) -> axum::response::Response {
+    post_json(app, "/api/urls", payload).await
+}
+
 /// Helper function to parse response body as JSON
 async fn response_json(body: Body) -> Value {
     let bytes = body
@@ -82,6 +152,32 @@ async fn test_create_short_url_success() {
     assert!(body["short_url"].as_str().unwrap().contains("test123"));
 }
 
+#[tokio::test]
+async fn test_create_rejects_non_http_urls() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/ok", "custom_id": "valid" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    for (url, custom_id) in [
+        ("not a url at all", "bare"),
+        ("javascript:alert(1)", "script"),
+        ("ftp://example.com/file", "ftp"),
+        ("file:///etc/passwd", "file"),
+        ("https://", "nohost"),
+    ] {
+        let response = create_url(&app, json!({ "url": url, "custom_id": custom_id })).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{} should be rejected", url);
+        let body = response_json(response.into_body()).await;
+        assert_eq!(body["code"], "invalid_url");
+    }
+    
+    // Nothing was written for the rejected urls
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    assert_eq!(table.len().unwrap(), 1);
+}
+
 #[tokio::test]
 async fn test_create_short_url_without_ref_id() {
     let (app, _temp_db) = setup_test_app();
@@ -335,10 +431,12 @@ async fn test_list_urls_pagination() {
     
     let body = response_json(response.into_body()).await;
     assert_eq!(body["total_fetched"], 10);
+    assert_eq!(body["total"], 15);
     assert_eq!(body["page"], 1);
     
     // Get second page
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
                 .method("GET")
@@ -351,7 +449,34 @@ async fn test_list_urls_pagination() {
     
     let body = response_json(response.into_body()).await;
     assert_eq!(body["total_fetched"], 5);
+    assert_eq!(body["total"], 15);
     assert_eq!(body["page"], 2);
+    
+    // Without a ref_id the whole table is counted
+    let response = get(&app, "/api/urls?limit=1").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total_fetched"], 1);
+    assert_eq!(body["total"], 15);
+}
+
+#[tokio::test]
+async fn test_list_urls_newest_first() {
+    let (app, _temp_db) = setup_test_app();
+    
+    for id in ["first", "second", "third"] {
+        create_url(&app, json!({ "url": "https://example.com", "custom_id": id, "ref_id": "chrono_user" })).await;
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    }
+    
+    let response = get(&app, "/api/urls?ref_id=chrono_user").await;
+    let body = response_json(response.into_body()).await;
+    let ids: Vec<&str> = body["data"].as_array().unwrap().iter().map(|link| link["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["third", "second", "first"]);
+    
+    // The full scan orders by creation time too, not by slug
+    let response = get(&app, "/api/urls?limit=1").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["id"], "third");
 }
 
 #[tokio::test]
@@ -487,3 +612,2506 @@ async fn test_delete_url_not_found() {
     
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn test_password_attempts_lock_out_then_recover() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        password_max_attempts: 3,
+        password_lockout: std::time::Duration::from_millis(300),
+        ..Config::default()
+    });
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/protected",
+        "custom_id": "locked1",
+        "password": "correct-horse"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // Missing password is rejected but not counted as a guess
+    let response = get(&app, "/locked1").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    
+    // Three wrong guesses exhaust the allowance
+    for _ in 0..3 {
+        let response = get(&app, "/locked1?password=wrong").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = response_json(response.into_body()).await;
+        assert_eq!(body["code"], "invalid_password");
+    }
+    
+    // Even the correct password is refused while locked
+    let response = get(&app, "/locked1?password=correct-horse").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().get("retry-after").is_some());
+    
+    // After the cooldown the correct password unlocks the link
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+    let response = get(&app, "/locked1?password=correct-horse").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://example.com/protected"
+    );
+}
+
+#[tokio::test]
+async fn test_list_urls_pagination_links() {
+    let (app, _temp_db) = setup_test_app();
+    
+    // 3 pages of 2 items each
+    for i in 1..=6 {
+        let response = create_url(&app, json!({
+            "url": format!("https://example.com/links{}", i),
+            "ref_id": "links_user"
+        }))
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = get(&app, "/api/urls?ref_id=links_user&page=2&limit=2").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let body = response_json(response.into_body()).await;
+    let links = &body["links"];
+    assert_eq!(links["first"], "/api/urls?ref_id=links_user&page=1&limit=2");
+    assert_eq!(links["prev"], "/api/urls?ref_id=links_user&page=1&limit=2");
+    assert_eq!(links["next"], "/api/urls?ref_id=links_user&page=3&limit=2");
+    assert_eq!(links["last"], "/api/urls?ref_id=links_user&page=3&limit=2");
+    
+    // No next link on the last page, no prev link on the first
+    let response = get(&app, "/api/urls?ref_id=links_user&page=3&limit=2").await;
+    let body = response_json(response.into_body()).await;
+    assert!(body["links"]["next"].is_null());
+    
+    let response = get(&app, "/api/urls?ref_id=links_user&limit=2").await;
+    let body = response_json(response.into_body()).await;
+    assert!(body["links"]["prev"].is_null());
+}
+
+#[tokio::test]
+async fn test_qr_bundle_contains_one_png_per_link() {
+    let (app, _temp_db) = setup_test_app();
+    
+    for slug in ["qrb1", "qrb2", "qrb3"] {
+        let response = create_url(&app, json!({
+            "url": format!("https://example.com/{}", slug),
+            "ref_id": "qr_tenant",
+            "custom_id": slug
+        }))
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = get(&app, "/api/urls/qr-bundle?ref_id=qr_tenant").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/zip");
+    
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+    assert_eq!(archive.len(), 3);
+    
+    let mut names: Vec<String> = archive.file_names().map(String::from).collect();
+    names.sort();
+    assert_eq!(names, vec!["qrb1.png", "qrb2.png", "qrb3.png"]);
+    
+    // Entries are real PNG files
+    let mut entry = archive.by_name("qrb1.png").unwrap();
+    let mut png = Vec::new();
+    std::io::Read::read_to_end(&mut entry, &mut png).unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+}
+
+#[tokio::test]
+async fn test_export_csv_quotes_fields_in_creation_order() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = get(&app, "/api/urls/export").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    for (slug, url) in [("csv1", "https://example.com/a,b?q=\"x\""), ("csv2", "https://example.com/plain")] {
+        let response = create_url(&app, json!({ "url": url, "ref_id": "csv_tenant", "custom_id": slug })).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = get(&app, "/api/urls/export?ref_id=csv_tenant").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/csv"));
+    
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let csv = String::from_utf8(bytes.to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "id,original_url,short_url,created_at,clicks");
+    assert!(lines[1].starts_with("csv1,\"https://example.com/a,b?q=\"\"x\"\"\","), "unexpected row {}", lines[1]);
+    assert!(lines[1].ends_with(",0"));
+    assert!(lines[2].starts_with("csv2,https://example.com/plain,"));
+}
+
+#[tokio::test]
+async fn test_qr_bundle_requires_ref_id_and_respects_limit() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        qr_bundle_max_links: 1,
+        ..Config::default()
+    });
+    
+    let response = get(&app, "/api/urls/qr-bundle").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    for i in 1..=2 {
+        create_url(&app, json!({
+            "url": format!("https://example.com/big{}", i),
+            "ref_id": "big_tenant"
+        }))
+        .await;
+    }
+    
+    let response = get(&app, "/api/urls/qr-bundle?ref_id=big_tenant").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "bundle_too_large");
+}
+
+#[tokio::test]
+async fn test_link_qr_renders_png() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/sign", "custom_id": "signage" })).await;
+    
+    let response = get(&app, "/api/urls/signage/qr").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+    let default_png = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(default_png.starts_with(b"\x89PNG"));
+    
+    // Oversized modules are clamped rather than rejected, and sizes change the image
+    let response = get(&app, "/api/urls/signage/qr?size=1000").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let large_png = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(large_png.starts_with(b"\x89PNG"));
+    assert_ne!(large_png, default_png);
+    
+    let response = get(&app, "/api/urls/missing-slug/qr").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "not_found");
+}
+
+#[tokio::test]
+async fn test_link_qr_renders_svg() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/print", "custom_id": "poster" })).await;
+    
+    let response = get(&app, "/api/urls/poster/qr.svg").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-type").unwrap(), "image/svg+xml");
+    let svg = String::from_utf8(response.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap();
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("#000000"));
+    
+    let response = get(&app, "/api/urls/poster/qr.svg?color=%23336699").await;
+    let svg = String::from_utf8(response.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap();
+    assert!(svg.contains("#336699"));
+    
+    // Anything but hex digits could break out of the SVG attribute
+    let response = get(&app, "/api/urls/poster/qr.svg?color=red%22%3E").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "invalid_color");
+    
+    let response = get(&app, "/api/urls/missing-slug/qr.svg").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_urls_dedupes_duplicate_index_entries() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    create_url(&app, json!({
+        "url": "https://example.com/dup",
+        "ref_id": "dup_user",
+        "custom_id": "dup1"
+    }))
+    .await;
+    
+    // Copy the existing index entry's slug under a second, stale key
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_REF_INDEX).unwrap();
+        let value = table
+            .range("dup_user:".."dup_user:{")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .1
+            .value()
+            .to_string();
+        table.insert("dup_user:1", value.as_str()).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let response = get(&app, "/api/urls?ref_id=dup_user").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total_fetched"], 1);
+    assert_eq!(body["data"][0]["id"], "dup1");
+}
+
+#[tokio::test]
+async fn test_validate_index_on_read_skips_and_repairs_orphans() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let state = AppState::with_config(db, Config {
+        validate_index_on_read: true,
+        ..Config::default()
+    });
+    let db = state.db.clone();
+    let app = create_app(state);
+    
+    create_url(&app, json!({ "url": "https://example.com/kept", "ref_id": "audited", "custom_id": "kept" })).await;
+    create_url(&app, json!({ "url": "https://example.com/other", "ref_id": "someone", "custom_id": "foreign" })).await;
+    
+    // One entry for a slug without a record, one for another owner's link
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_REF_INDEX).unwrap();
+        table.insert("audited:1", "vanished").unwrap();
+        table.insert("audited:2", "foreign").unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let response = get(&app, "/api/urls?ref_id=audited").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total_fetched"], 1);
+    assert_eq!(body["data"][0]["id"], "kept");
+    
+    // The orphaned entries were deleted; the valid ones remain
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_REF_INDEX).unwrap();
+    assert!(table.get("audited:1").unwrap().is_none());
+    assert!(table.get("audited:2").unwrap().is_none());
+    assert_eq!(table.len().unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_validate_index_on_read_keeps_entries_of_unreadable_records() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let state = AppState::with_config(db, Config {
+        validate_index_on_read: true,
+        ..Config::default()
+    });
+    let db = state.db.clone();
+    let app = create_app(state);
+    
+    create_url(&app, json!({ "url": "https://example.com/garbled", "ref_id": "audited", "custom_id": "garbled" })).await;
+    
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        table.insert("garbled", r#"{"id":"garbled","original_url":42,"#).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    // The read error is reported instead of treating the entry as an orphan
+    let response = get(&app, "/api/urls?ref_id=audited").await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response_json(response.into_body()).await["code"], "storage_error");
+    
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_REF_INDEX).unwrap();
+    assert_eq!(table.len().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_redirect_delay_serves_countdown_page() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({
+        "url": "https://example.com/offer?a=1&b=2",
+        "custom_id": "delayed",
+        "redirect_delay_seconds": 7
+    }))
+    .await;
+    
+    let response = get(&app, "/delayed").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(html.contains(r#"content="7;url=https://example.com/offer?a=1&amp;b=2""#));
+    assert!(html.contains(r#"data-seconds="7""#));
+    
+    // A zero delay keeps the immediate redirect
+    create_url(&app, json!({
+        "url": "https://example.com/instant",
+        "custom_id": "instant",
+        "redirect_delay_seconds": 0
+    }))
+    .await;
+    
+    let response = get(&app, "/instant").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+}
+
+#[tokio::test]
+async fn test_write_concurrency_limit_rejects_excess_writes() {
+    let temp_db = NamedTempFile::new().unwrap();
+    let db = init_db(temp_db.path().to_str().unwrap()).unwrap();
+    let state = AppState::with_config(db, Config {
+        max_concurrent_writes: 1,
+        ..Config::default()
+    });
+    let permits = state.write_permits.clone();
+    let app = create_app(state);
+    
+    // Simulate an in-flight write holding the only permit
+    let in_flight = permits.try_acquire_owned().unwrap();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/saturated" })).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let retry_after: u64 = response.headers().get("retry-after").unwrap().to_str().unwrap().parse().unwrap();
+    assert!((1..=2).contains(&retry_after));
+    
+    // Reads are not throttled
+    let response = get(&app, "/api/urls").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    // Once the write completes, new writes go through again
+    drop(in_flight);
+    let response = create_url(&app, json!({ "url": "https://example.com/saturated" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_reads_are_served_from_read_replica() {
+    let primary_file = NamedTempFile::new().unwrap();
+    let replica_file = NamedTempFile::new().unwrap();
+    let primary = init_db(primary_file.path().to_str().unwrap()).unwrap();
+    let replica = init_db(replica_file.path().to_str().unwrap()).unwrap();
+    
+    // Seed a record only in the replica
+    let record = json!({
+        "id": "replica1",
+        "original_url": "https://example.com/from-replica",
+        "short_url": "http://localhost:8080/replica1",
+        "ref_id": "replica_user",
+        "created_at": "2026-01-17T13:40:00Z",
+        "clicks": 0
+    })
+    .to_string();
+    let write_txn = replica.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        table.insert("replica1", record.as_str()).unwrap();
+        let mut index = write_txn.open_table(TABLE_REF_INDEX).unwrap();
+        index.insert("replica_user:1768657200000000", "replica1").unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let state = AppState::new(primary).with_read_replica(replica);
+    let app = create_app(state);
+    
+    let response = get(&app, "/replica1").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://example.com/from-replica"
+    );
+    
+    let response = get(&app, "/api/urls?ref_id=replica_user").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total_fetched"], 1);
+    assert_eq!(body["data"][0]["id"], "replica1");
+}
+
+#[tokio::test]
+async fn test_rename_keeps_old_slug_as_alias() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(
+        &app,
+        json!({ "url": "https://example.com/campaign", "ref_id": "renamer", "custom_id": "old-slug" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = post_json(
+        &app,
+        "/api/urls/old-slug/rename",
+        json!({ "new_id": "new-slug", "ref_id": "renamer" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], "new-slug");
+    assert!(body["short_url"].as_str().unwrap().ends_with("/new-slug"));
+    
+    // Both slugs resolve to the same destination
+    for uri in ["/new-slug", "/old-slug"] {
+        let response = get(&app, uri).await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT, "{}", uri);
+        assert_eq!(response.headers().get("location").unwrap(), "https://example.com/campaign");
+    }
+    
+    // The alias keeps the old slug reserved
+    let response = create_url(&app, json!({ "url": "https://other.example", "custom_id": "old-slug" })).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    
+    // The listing reflects the new slug
+    let response = get(&app, "/api/urls?ref_id=renamer").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["id"], "new-slug");
+}
+
+#[tokio::test]
+async fn test_rename_checks_ownership_and_availability() {
+    let (app, _temp_db) = setup_test_app();
+    
+    for slug in ["mine", "taken"] {
+        let response = create_url(
+            &app,
+            json!({ "url": "https://example.com", "ref_id": "owner", "custom_id": slug }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = post_json(&app, "/api/urls/mine/rename", json!({ "new_id": "free", "ref_id": "intruder" })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    
+    let response = post_json(&app, "/api/urls/mine/rename", json!({ "new_id": "taken", "ref_id": "owner" })).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    
+    let response = post_json(&app, "/api/urls/missing/rename", json!({ "new_id": "free" })).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_rename_password_guesses_share_the_lockout() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        password_max_attempts: 2,
+        password_lockout: std::time::Duration::from_secs(60),
+        ..Config::default()
+    });
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/guarded",
+        "custom_id": "guarded",
+        "password": "correct-horse"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    for _ in 0..2 {
+        let response = post_json(&app, "/api/urls/guarded/rename", json!({ "new_id": "moved", "password": "wrong" })).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    
+    // Locked now, for renames and redirects alike, even with the right password
+    let response = post_json(&app, "/api/urls/guarded/rename", json!({ "new_id": "moved", "password": "correct-horse" })).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "password_locked");
+    
+    let response = get(&app, "/guarded?password=correct-horse").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_resolve_by_full_short_url() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/resolved", "custom_id": "resolveme" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created = response_json(response.into_body()).await;
+    let short_url = created["short_url"].as_str().unwrap();
+    
+    let encoded = utf8_percent_encode(short_url, NON_ALPHANUMERIC);
+    let response = get(&app, &format!("/api/urls/resolve?short_url={}", encoded)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], "resolveme");
+    assert_eq!(body["original_url"], "https://example.com/resolved");
+    
+    // Same domain, unknown slug
+    let missing = short_url.replace("resolveme", "nothere");
+    let encoded = utf8_percent_encode(&missing, NON_ALPHANUMERIC);
+    let response = get(&app, &format!("/api/urls/resolve?short_url={}", encoded)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_resolve_rejects_foreign_domain() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com", "custom_id": "foreign" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let encoded = utf8_percent_encode("https://evil.example/foreign", NON_ALPHANUMERIC);
+    let response = get(&app, &format!("/api/urls/resolve?short_url={}", encoded)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "foreign_domain");
+}
+
+#[tokio::test]
+async fn test_empty_list_returns_200_by_default() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = get(&app, "/api/urls?ref_id=nobody").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"], json!([]));
+}
+
+#[tokio::test]
+async fn test_empty_list_returns_404_when_configured() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        empty_list_404: true,
+        ..Config::default()
+    });
+    
+    let response = get(&app, "/api/urls?ref_id=nobody").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "no_links");
+    
+    // A ref_id with links is unaffected
+    let response = create_url(&app, json!({ "url": "https://example.com", "ref_id": "somebody" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = get(&app, "/api/urls?ref_id=somebody").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_kill_disables_link_but_keeps_record() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(
+        &app,
+        json!({ "url": "https://spam.example", "ref_id": "abuser", "custom_id": "spammy" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = get(&app, "/spammy").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // No ref_id needed: support staff act regardless of owner
+    let response = post_json(&app, "/api/admin/kill/spammy", json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let request_id = response.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], "spammy");
+    assert_eq!(body["request_id"], request_id);
+    assert!(body["disabled_at"].is_string());
+    
+    let response = get(&app, "/spammy").await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "link_disabled");
+    
+    // The record is still there for audit, marked inactive
+    let response = get(&app, "/api/urls?ref_id=abuser").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["id"], "spammy");
+    assert_eq!(body["data"][0]["is_active"], false);
+    
+    let response = post_json(&app, "/api/admin/kill/nothing", json!({})).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_custom_request_id_header() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        request_id_header: HeaderName::from_static("x-correlation-id"),
+        ..Config::default()
+    });
+    
+    // An incoming id is honored and echoed under the configured name
+    let request = Request::builder()
+        .uri("/api/auth/check")
+        .header("X-Correlation-Id", "corr-123")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.headers()["x-correlation-id"], "corr-123");
+    assert!(response.headers().get("x-request-id").is_none());
+    
+    // The default header name is no longer read; a fresh id is generated
+    let request = Request::builder()
+        .uri("/api/auth/check")
+        .header("X-Request-Id", "ignored")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let generated = response.headers()["x-correlation-id"].to_str().unwrap();
+    assert!(!generated.is_empty());
+    assert_ne!(generated, "ignored");
+}
+
+#[tokio::test]
+async fn test_time_format_epoch_ms() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = post_json(
+        &app,
+        "/api/urls?time_format=epoch_ms",
+        json!({ "url": "https://example.com", "ref_id": "epoch", "expires_in_seconds": 3600 }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response_json(response.into_body()).await;
+    let created_ms = body["created_at"].as_i64().expect("created_at should be an integer");
+    
+    let response = get(&app, "/api/urls?ref_id=epoch&time_format=epoch_ms").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["created_at"].as_i64(), Some(created_ms));
+    let expires_ms = body["data"][0]["expires_at"].as_i64().expect("expires_at should be an integer");
+    assert_eq!(expires_ms - created_ms, 3_600_000);
+    
+    // RFC3339 stays the default
+    let response = get(&app, "/api/urls?ref_id=epoch").await;
+    let body = response_json(response.into_body()).await;
+    let created_at = body["data"][0]["created_at"].as_str().unwrap();
+    let parsed = chrono::DateTime::parse_from_rfc3339(created_at).unwrap();
+    assert_eq!(parsed.timestamp_millis(), created_ms);
+    
+    let response = get(&app, "/api/urls?ref_id=epoch&time_format=unix").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_toggle_pauses_and_resumes_link() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(
+        &app,
+        json!({ "url": "https://example.com", "ref_id": "owner", "custom_id": "pausable" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = post_json(&app, "/api/urls/pausable/toggle?ref_id=someone_else", json!({})).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    
+    let response = post_json(&app, "/api/urls/pausable/toggle?ref_id=owner", json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["is_active"], false);
+    assert!(body["disabled_at"].is_string());
+    
+    let response = get(&app, "/pausable").await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    
+    let response = post_json(&app, "/api/urls/pausable/toggle?ref_id=owner", json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["is_active"], true);
+    assert!(body["disabled_at"].is_null());
+    
+    let response = get(&app, "/pausable").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // A link killed by an admin cannot be re-enabled by its owner
+    let response = post_json(&app, "/api/admin/kill/pausable", json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = post_json(&app, "/api/urls/pausable/toggle?ref_id=owner", json!({})).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "link_killed");
+    
+    let response = post_json(&app, "/api/urls/missing/toggle", json!({})).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_jsonld_describes_link() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/article", "custom_id": "seo" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = get(&app, "/api/urls/seo/jsonld").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/ld+json");
+    let body = response_json(response.into_body()).await;
+    
+    assert_eq!(body["@context"], "https://schema.org");
+    assert_eq!(body["@type"], "WebPage");
+    assert_eq!(body["identifier"], "seo");
+    assert_eq!(body["potentialAction"]["target"], "https://example.com/article");
+    assert!(body["dateCreated"].is_string());
+    
+    let response = get(&app, "/api/urls/unknown/jsonld").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_retry_after_with_jitter_stays_in_window() {
+    let mut seen = std::collections::HashSet::new();
+    for _ in 0..1000 {
+        let retry_after = retry_after_with_jitter(std::time::Duration::from_secs(10));
+        assert!((10..=15).contains(&retry_after), "{}", retry_after);
+        seen.insert(retry_after);
+    }
+    // Spread out rather than one fixed value
+    assert!(seen.len() > 1);
+    
+    // Sub-second waits round up to at least a second, plus up to one of jitter
+    for _ in 0..100 {
+        let retry_after = retry_after_with_jitter(std::time::Duration::from_millis(200));
+        assert!((1..=2).contains(&retry_after), "{}", retry_after);
+    }
+    assert!((3..=5).contains(&retry_after_with_jitter(std::time::Duration::from_millis(2500))));
+}
+
+#[tokio::test]
+async fn test_rate_limit_ignores_ref_id_query() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        rate_limit_per_minute: 1,
+        rate_limit_burst: 2,
+        rate_limit_exempt_ref_ids: ["internal".to_string()].into_iter().collect(),
+        ..Config::default()
+    });
+    
+    // Without a JWT subject the ref_id query does not pick the bucket
+    for ref_id in ["tenant", "other"] {
+        let response = get(&app, &format!("/api/urls?ref_id={}", ref_id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = get(&app, "/api/urls?ref_id=tenant").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().get("retry-after").is_some());
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "rate_limited");
+    
+    // Nor does claiming an exempt ref_id
+    let response = get(&app, "/api/urls?ref_id=internal").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    
+    // Public redirects are not rate limited
+    let response = get(&app, "/does-not-exist").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Helper function to GET `uri` over a connection from `peer`, optionally with `X-Forwarded-For`
+async fn get_from_peer(app: &axum::Router, uri: &str, peer: &str, forwarded: Option<&str>) -> axum::response::Response {
+    let mut request = Request::builder()
+        .uri(uri)
+        .extension(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 40000)));
+    if let Some(forwarded) = forwarded {
+        request = request.header("x-forwarded-for", forwarded);
+    }
+    app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_rate_limit_ignores_spoofed_forwarded_for() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        rate_limit_per_minute: 1,
+        rate_limit_burst: 2,
+        trusted_proxies: ["10.0.0.1".parse().unwrap()].into_iter().collect(),
+        ..Config::default()
+    });
+    
+    // A direct client rotating X-Forwarded-For stays in its own bucket
+    for forwarded in ["198.51.100.1", "198.51.100.2"] {
+        let response = get_from_peer(&app, "/api/urls?ref_id=a", "203.0.113.7", Some(forwarded)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = get_from_peer(&app, "/api/urls?ref_id=a", "203.0.113.7", Some("198.51.100.3")).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    
+    // Another peer has a bucket of its own
+    let response = get_from_peer(&app, "/api/urls?ref_id=a", "203.0.113.8", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    // Behind the trusted proxy, the forwarded clients are told apart...
+    for client in ["198.51.100.1", "198.51.100.2"] {
+        let response = get_from_peer(&app, "/api/urls?ref_id=a", "10.0.0.1", Some(client)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    // ...but a value the client prepended to the proxy's entry is ignored
+    let response = get_from_peer(&app, "/api/urls?ref_id=a", "10.0.0.1", Some("192.0.2.99, 198.51.100.1")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = get_from_peer(&app, "/api/urls?ref_id=a", "10.0.0.1", Some("192.0.2.100, 198.51.100.1")).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_disabled_link_shows_its_message() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/promo", "custom_id": "promo" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = post_json(
+        &app,
+        "/api/admin/kill/promo",
+        json!({ "message": "The <spring> promotion has ended" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    // API clients get JSON
+    let response = get(&app, "/promo").await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["message"], "The <spring> promotion has ended");
+    
+    // Browsers get an (escaped) HTML page
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/promo")
+                .header("accept", "text/html")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::GONE);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let html = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(html.contains("The &lt;spring&gt; promotion has ended"));
+}
+
+#[tokio::test]
+async fn test_raw_record_returns_stored_string_verbatim() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/raw", "custom_id": "rawlink" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // Seed a corrupt record that no other endpoint can deserialize
+    let malformed = r#"{"id":"broken","original_url":42,"#;
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        table.insert("broken", malformed).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let stored = {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE_URLS).unwrap();
+        let value = table.get("rawlink").unwrap().unwrap();
+        value.value().to_string()
+    };
+    
+    for (id, expected) in [("rawlink", stored.as_str()), ("broken", malformed)] {
+        let response = get(&app, &format!("/api/admin/raw/{}", id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(String::from_utf8(bytes.to_vec()).unwrap(), expected);
+    }
+    
+    let response = get(&app, "/api/admin/raw/absent").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_urls_returns_500_for_malformed_record() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/healthy" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        table.insert("broken", r#"{"id":"broken","original_url":42,"#).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let response = get(&app, "/api/urls").await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "storage_error");
+    assert!(body["error"].is_string());
+    
+    // The server keeps answering after the failure
+    let response = get(&app, "/api/admin/raw/broken").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_indexed_reads_return_500_for_malformed_record() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    create_url(&app, json!({ "url": "https://example.com/healthy", "custom_id": "healthy", "ref_id": "owner" })).await;
+    create_url(&app, json!({ "url": "https://example.com/broken", "custom_id": "broken", "ref_id": "owner" })).await;
+    
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        table.insert("broken", r#"{"id":"broken","original_url":42,"#).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    // Neither a later page nor the lookups skip it as missing
+    for uri in ["/api/urls?ref_id=owner", "/api/urls?ref_id=owner&page=2&limit=1", "/api/urls/broken/stats", "/broken"] {
+        let response = get(&app, uri).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR, "{}", uri);
+        assert_eq!(response_json(response.into_body()).await["code"], "storage_error");
+    }
+}
+
+#[tokio::test]
+async fn test_list_uses_ref_id_default_limit_preference() {
+    let (app, _temp_file) = setup_test_app();
+
+    for i in 0..3 {
+        create_url(
+            &app,
+            json!({
+                "url": format!("https://example.com/{}", i),
+                "ref_id": "prefs_user"
+            }),
+        )
+        .await;
+    }
+
+    // Without a preference the global default (10) applies
+    let response = get(&app, "/api/urls?ref_id=prefs_user").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["limit"], 10);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/api/preferences")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"ref_id": "prefs_user", "default_limit": 2}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get(&app, "/api/preferences?ref_id=prefs_user").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["default_limit"], 2);
+
+    let response = get(&app, "/api/urls?ref_id=prefs_user").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["limit"], 2);
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    // An explicit limit still wins, and other tenants are unaffected
+    let response = get(&app, "/api/urls?ref_id=prefs_user&limit=3").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 3);
+
+    let response = get(&app, "/api/urls?ref_id=someone_else").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["limit"], 10);
+}
+
+#[tokio::test]
+async fn test_redirect_includes_canonical_link_header() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/canonical", "custom_id": "canon" })).await;
+    let created = response_json(response.into_body()).await;
+    let short_url = created["short_url"].as_str().unwrap();
+    
+    let response = get(&app, "/canon").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        response.headers().get("link").unwrap(),
+        format!("<{}>; rel=\"canonical\"", short_url).as_str()
+    );
+}
+
+#[tokio::test]
+async fn test_batch_create_applies_shared_defaults() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let response = post_json(&app, "/api/urls/batch-with-defaults", json!({
+        "defaults": {
+            "ref_id": "campaign_7",
+            "tags": ["spring", "email"],
+            "expires_in_seconds": 3600
+        },
+        "urls": [
+            "https://example.com/a",
+            "https://example.com/b",
+            { "url": "https://example.com/c", "custom_id": "spring-c", "tags": ["vip"] },
+            { "url": "https://example.com/d", "custom_id": "api" }
+        ]
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["created"].as_array().unwrap().len(), 3);
+    assert_eq!(body["failed"][0]["index"], 3);
+    assert_eq!(body["failed"][0]["code"], "invalid_custom_id");
+    
+    let response = get(&app, "/api/urls?ref_id=campaign_7").await;
+    let body = response_json(response.into_body()).await;
+    let links = body["data"].as_array().unwrap();
+    assert_eq!(links.len(), 3);
+    for link in links {
+        assert_eq!(link["ref_id"], "campaign_7");
+        assert!(link["expires_at"].is_string());
+        let expected = if link["id"] == "spring-c" { json!(["vip"]) } else { json!(["spring", "email"]) };
+        assert_eq!(link["tags"], expected);
+    }
+}
+
+#[tokio::test]
+async fn test_default_tags_apply_when_request_omits_tags() {
+    let (app, _temp_file) = setup_test_app_with_config(Config {
+        default_tags: vec!["campaign".to_string(), "q3".to_string()],
+        ..Config::default()
+    });
+    
+    create_url(&app, json!({ "url": "https://example.com/a", "ref_id": "tagged", "custom_id": "tag-default" })).await;
+    create_url(&app, json!({
+        "url": "https://example.com/b",
+        "ref_id": "tagged",
+        "custom_id": "tag-explicit",
+        "tags": ["vip"]
+    }))
+    .await;
+    create_url(&app, json!({ "url": "https://example.com/c", "ref_id": "tagged", "custom_id": "tag-none", "tags": [] })).await;
+    
+    let response = get(&app, "/api/urls?ref_id=tagged").await;
+    let body = response_json(response.into_body()).await;
+    for link in body["data"].as_array().unwrap() {
+        let expected = match link["id"].as_str().unwrap() {
+            "tag-default" => json!(["campaign", "q3"]),
+            "tag-explicit" => json!(["vip"]),
+            _ => json!([]),
+        };
+        assert_eq!(link["tags"], expected, "{}", link["id"]);
+    }
+    
+    let tags: Vec<String> = (0..21).map(|i| format!("t{}", i)).collect();
+    let response = create_url(&app, json!({ "url": "https://example.com/d", "tags": tags })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "too_many_tags");
+}
+
+#[tokio::test]
+async fn test_create_stores_source_from_header_or_body() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .header("X-Client-Source", "cli")
+                .body(Body::from(json!({ "url": "https://example.com/src", "ref_id": "sourced", "custom_id": "src-header" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = get(&app, "/api/urls/src-header/stats").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["source"], "cli");
+    
+    // The body wins over the header; no source at all stays null
+    create_url(&app, json!({ "url": "https://example.com/b", "ref_id": "sourced", "custom_id": "src-body", "source": "web" })).await;
+    create_url(&app, json!({ "url": "https://example.com/c", "ref_id": "sourced", "custom_id": "src-none" })).await;
+    let response = get(&app, "/api/urls?ref_id=sourced").await;
+    let body = response_json(response.into_body()).await;
+    for link in body["data"].as_array().unwrap() {
+        let expected = match link["id"].as_str().unwrap() {
+            "src-header" => json!("cli"),
+            "src-body" => json!("web"),
+            _ => Value::Null,
+        };
+        assert_eq!(link["source"], expected, "{}", link["id"]);
+    }
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/d", "source": "x".repeat(65) })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "invalid_source");
+}
+
+#[tokio::test]
+async fn test_disable_redirect_route_leaves_only_the_api() {
+    let (app, _temp_file) = setup_test_app_with_config(Config {
+        disable_redirect_route: true,
+        ..Config::default()
+    });
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/api-only", "custom_id": "someslug" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // The router itself has no match: an empty 404, not the handler's JSON
+    let response = get(&app, "/someslug").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.into_body().collect().await.unwrap().to_bytes().is_empty());
+    
+    let response = get(&app, "/api/urls/someslug/stats").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn test_normalize_tags_dedupes_case_insensitively() {
+    let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+    
+    assert_eq!(normalize_tags(tags(&["A", "a", "b"]), TagCase::Preserve), ["A", "b"]);
+    assert_eq!(normalize_tags(tags(&["A", "a", "b"]), TagCase::Lowercase), ["a", "b"]);
+    assert_eq!(normalize_tags(tags(&[" Spring", "email", "SPRING ", "", "Email"]), TagCase::Preserve), ["Spring", "email"]);
+}
+
+#[tokio::test]
+async fn test_create_stores_deduplicated_tags() {
+    let (app, _temp_file) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/t", "ref_id": "dedup-tags", "tags": ["A", "a", "b", "B"] })).await;
+    let response = get(&app, "/api/urls?ref_id=dedup-tags").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["tags"], json!(["A", "b"]));
+}
+
+#[tokio::test]
+async fn test_bulk_create_skips_failed_items() {
+    let (app, _temp_file) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/existing", "custom_id": "taken-before" })).await;
+    
+    let response = post_json(&app, "/api/urls/bulk", json!({
+        "items": [
+            { "url": "https://example.com/1", "ref_id": "importer", "custom_id": "import-1" },
+            { "url": "https://example.com/2", "ref_id": "importer", "custom_id": "import-1" },
+            { "url": "https://example.com/3", "ref_id": "importer" },
+            { "url": "not a url", "ref_id": "importer" },
+            { "url": "https://example.com/5", "ref_id": "importer", "custom_id": "taken-before" },
+            { "url": "https://example.com/6", "ref_id": "importer", "custom_id": "import-6" }
+        ]
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["created"], 3);
+    assert_eq!(body["failed"], 3);
+    
+    let results = body["results"].as_array().unwrap();
+    let statuses: Vec<&str> = results.iter().map(|result| result["status"].as_str().unwrap()).collect();
+    assert_eq!(statuses, ["created", "failed", "created", "failed", "failed", "created"]);
+    assert_eq!(results[0]["id"], "import-1");
+    assert_eq!(results[1]["code"], "id_taken");
+    assert_eq!(results[3]["code"], "invalid_url");
+    assert_eq!(results[4]["code"], "id_taken");
+    assert_eq!(results[4]["url"], "https://example.com/5");
+    
+    // The duplicate did not overwrite the first item, and the others were committed
+    let response = get(&app, "/import-1").await;
+    assert_eq!(response.headers()["location"], "https://example.com/1");
+    assert_eq!(get(&app, "/import-6").await.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // Items list in the order given
+    let response = get(&app, "/api/urls?ref_id=importer").await;
+    let body = response_json(response.into_body()).await;
+    let ids: Vec<&str> = body["data"].as_array().unwrap().iter().map(|link| link["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["import-6", results[2]["id"].as_str().unwrap(), "import-1"]);
+}
+
+#[tokio::test]
+async fn test_bulk_create_rejects_oversized_batch() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let items: Vec<Value> = (0..1001).map(|i| json!({ "url": format!("https://example.com/{}", i) })).collect();
+    let response = post_json(&app, "/api/urls/bulk", json!({ "items": items })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "batch_too_large");
+    assert_eq!(body["max"], 1000);
+}
+
+#[tokio::test]
+async fn test_max_batch_size_caps_every_batch_endpoint() {
+    let (app, _temp_file) = setup_test_app_with_config(Config {
+        max_batch_size: 2,
+        ..Config::default()
+    });
+    
+    let items: Vec<Value> = (0..3).map(|i| json!({ "url": format!("https://example.com/{}", i) })).collect();
+    let response = post_json(&app, "/api/urls/bulk", json!({ "items": items })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "batch_too_large");
+    assert_eq!(body["max"], 2);
+    
+    let urls: Vec<String> = (0..3).map(|i| format!("https://example.com/{}", i)).collect();
+    let response = post_json(&app, "/api/urls/batch-with-defaults", json!({ "urls": urls })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "batch_too_large");
+    assert_eq!(body["max"], 2);
+    
+    let response = post_json(&app, "/api/urls/bulk", json!({ "items": &items[..2] })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rotate_all_retires_old_slugs() {
+    let (app, _temp_file) = setup_test_app();
+    
+    for slug in ["leak-a", "leak-b"] {
+        create_url(&app, json!({ "url": format!("https://example.com/{}", slug), "ref_id": "leaky", "custom_id": slug })).await;
+    }
+    create_url(&app, json!({ "url": "https://example.com/secret", "ref_id": "leaky", "custom_id": "leak-pw", "password": "pw" })).await;
+    create_url(&app, json!({ "url": "https://example.com/other", "ref_id": "other", "custom_id": "not-mine" })).await;
+    get(&app, "/leak-a").await;
+    let before = response_json(get(&app, "/api/urls/leak-a/stats").await.into_body()).await;
+    
+    // A rename alias leaked too, so it must stop resolving as well
+    post_json(&app, "/api/urls/leak-b/rename", json!({ "new_id": "leak-b2" })).await;
+    
+    let response = post_json(&app, "/api/urls/rotate-all?ref_id=leaky", json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    let rotated = body["rotated"].as_object().unwrap();
+    assert_eq!(rotated.len(), 2);
+    assert_eq!(body["skipped"], json!([{ "id": "leak-pw", "code": "password_protected" }]));
+    
+    for old in ["leak-a", "leak-b", "leak-b2"] {
+        assert_eq!(get(&app, &format!("/{}", old)).await.status(), StatusCode::NOT_FOUND);
+    }
+    assert_eq!(get(&app, "/not-mine").await.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    let new_a = rotated["leak-a"].as_str().unwrap();
+    let response = get(&app, &format!("/{}", new_a)).await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers()["location"], "https://example.com/leak-a");
+    let new_b = rotated["leak-b2"].as_str().unwrap();
+    assert_eq!(get(&app, &format!("/{}", new_b)).await.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // Clicks (plus the visit above) and created_at moved with the link
+    let after = response_json(get(&app, &format!("/api/urls/{}/stats", new_a)).await.into_body()).await;
+    assert_eq!(before["clicks"], 1);
+    assert_eq!(after["clicks"], 2);
+    assert_eq!(after["created_at"], before["created_at"]);
+    assert!(after["short_url"].as_str().unwrap().ends_with(new_a));
+    
+    // The tenant's listing shows the new slugs
+    let body = response_json(get(&app, "/api/urls?ref_id=leaky").await.into_body()).await;
+    let mut ids: Vec<&str> = body["data"].as_array().unwrap().iter().map(|link| link["id"].as_str().unwrap()).collect();
+    ids.sort();
+    let mut expected = vec![new_a, new_b, "leak-pw"];
+    expected.sort();
+    assert_eq!(ids, expected);
+    
+    let response = post_json(&app, "/api/urls/rotate-all", json!({})).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_expired_link_returns_gone() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/old", "custom_id": "expiring", "expires_in_seconds": 60 })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(get(&app, "/expiring").await.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // Move the expiry into the past
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        let stored = table.get("expiring").unwrap().unwrap().value().to_string();
+        let mut record: Value = serde_json::from_str(&stored).unwrap();
+        record["expires_at"] = json!("2020-01-01T00:00:00Z");
+        table.insert("expiring", record.to_string().as_str()).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let response = get(&app, "/expiring").await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "link_expired");
+}
+
+#[tokio::test]
+async fn test_expires_at_accepts_timestamps_and_durations() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    // A live link with a relative deadline
+    let response = create_url(&app, json!({ "url": "https://example.com/live", "custom_id": "live", "expires_at": "7d" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(get(&app, "/live").await.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/day", "custom_id": "day", "expires_at": "24h" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // An absolute deadline already in the past
+    let response = create_url(
+        &app,
+        json!({ "url": "https://example.com/past", "custom_id": "past", "expires_at": "2020-01-01T00:00:00Z" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = get(&app, "/past").await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    assert_eq!(response_json(response.into_body()).await["code"], "link_expired");
+    
+    {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE_URLS).unwrap();
+        let live: Value = serde_json::from_str(table.get("live").unwrap().unwrap().value()).unwrap();
+        let created_at = chrono::DateTime::parse_from_rfc3339(live["created_at"].as_str().unwrap()).unwrap();
+        let expires_at = chrono::DateTime::parse_from_rfc3339(live["expires_at"].as_str().unwrap()).unwrap();
+        assert_eq!((expires_at - created_at).num_days(), 7);
+    }
+    
+    for expires_at in ["soon", "7x", "-1d", "0h", ""] {
+        let response = create_url(&app, json!({ "url": "https://example.com", "expires_at": expires_at })).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{:?} should be rejected", expires_at);
+        assert_eq!(response_json(response.into_body()).await["code"], "invalid_expiry");
+    }
+    let response = create_url(&app, json!({ "url": "https://example.com", "expires_at": "1d", "expires_in_seconds": 60 })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_record_without_expiry_field_still_redirects() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    // Shape of a record written before expiry existed
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_URLS).unwrap();
+        let legacy = json!({
+            "id": "legacy",
+            "original_url": "https://example.com/legacy",
+            "short_url": "http://localhost:8080/legacy",
+            "ref_id": null,
+            "created_at": "2024-01-01T00:00:00Z"
+        });
+        table.insert("legacy", legacy.to_string().as_str()).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let response = get(&app, "/legacy").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+}
+
+#[tokio::test]
+async fn test_create_trims_submitted_url() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "  \n https://example.com/pasted\r\n\t", "custom_id": "pasted" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["original_url"], "https://example.com/pasted");
+    
+    let response = get(&app, "/pasted").await;
+    assert_eq!(response.headers().get("location").unwrap(), "https://example.com/pasted");
+    
+    // Embedded control characters are dropped as well
+    let response = create_url(&app, json!({ "url": "https://example.com/spl\nit" })).await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["original_url"], "https://example.com/split");
+    
+    let response = create_url(&app, json!({ "url": " \n\t " })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "empty_url");
+}
+
+#[tokio::test]
+async fn test_create_keeps_url_verbatim_when_trimming_disabled() {
+    let (app, _temp_file) = setup_test_app_with_config(Config {
+        trim_urls: false,
+        ..Config::default()
+    });
+    
+    let response = create_url(&app, json!({ "url": " https://example.com/raw " })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["original_url"], " https://example.com/raw ");
+}
+
+#[tokio::test]
+async fn test_create_success_status_is_configurable() {
+    for (configured, expected) in [(201, StatusCode::CREATED), (200, StatusCode::OK)] {
+        let (app, _temp_file) = setup_test_app_with_config(Config {
+            create_success_status: configured,
+            ..Config::default()
+        });
+        
+        let response = create_url(&app, json!({ "url": "https://example.com/status", "custom_id": "status" })).await;
+        assert_eq!(response.status(), expected);
+        let body = response_json(response.into_body()).await;
+        assert_eq!(body["id"], "status");
+        assert_eq!(body["original_url"], "https://example.com/status");
+    }
+}
+
+#[tokio::test]
+async fn test_dedup_within_ref_id_scope() {
+    let (app, _temp_file) = setup_test_app();
+    let payload = |ref_id: &str| json!({ "url": "https://example.com/dup", "ref_id": ref_id, "dedup": true });
+    
+    let response = create_url(&app, payload("tenant_a")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let first = response_json(response.into_body()).await;
+    
+    // Same owner: the existing link comes back
+    let response = create_url(&app, payload("tenant_a")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], first["id"]);
+    
+    // Another owner gets a link of their own
+    let response = create_url(&app, payload("tenant_b")).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response_json(response.into_body()).await;
+    assert_ne!(body["id"], first["id"]);
+    
+    // Without the flag a new link is always created
+    let response = create_url(&app, json!({ "url": "https://example.com/dup", "ref_id": "tenant_a" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_dedup_global_scope_uses_destination_index() {
+    let (app, _temp_file) = setup_test_app_with_config(Config {
+        dedup_scope: DedupScope::Global,
+        ..Config::default()
+    });
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/shared", "ref_id": "tenant_a", "custom_id": "shared" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // Any owner, or none, is handed the existing link
+    for ref_id in [json!("tenant_b"), Value::Null] {
+        let response = create_url(&app, json!({ "url": "https://example.com/shared", "ref_id": ref_id, "dedup": true })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response.into_body()).await;
+        assert_eq!(body["id"], "shared");
+    }
+    
+    // Once the link is gone, a new one is created
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/shared?ref_id=tenant_a")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/shared", "dedup": true })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response_json(response.into_body()).await;
+    assert_ne!(body["id"], "shared");
+}
+
+#[tokio::test]
+async fn test_destinations_lists_distinct_urls_with_counts() {
+    let (app, _temp_file) = setup_test_app();
+    
+    for url in [
+        "https://example.com/b",
+        "https://example.com/a",
+        "https://example.com/c",
+        "https://example.com/c",
+        "https://example.com/a",
+        "https://example.com/c",
+    ] {
+        create_url(&app, json!({ "url": url, "ref_id": "auditor" })).await;
+    }
+    create_url(&app, json!({ "url": "https://example.com/other", "ref_id": "someone_else" })).await;
+    
+    let response = get(&app, "/api/urls/destinations?ref_id=auditor").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total_links"], 6);
+    assert_eq!(
+        body["destinations"],
+        json!([
+            { "url": "https://example.com/c", "count": 3 },
+            { "url": "https://example.com/a", "count": 2 },
+            { "url": "https://example.com/b", "count": 1 }
+        ])
+    );
+    
+    let response = get(&app, "/api/urls/destinations").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_create_with_initial_clicks() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/migrated", "custom_id": "migrated", "initial_clicks": 100 })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    create_url(&app, json!({ "url": "https://example.com/fresh", "custom_id": "fresh" })).await;
+    
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    for (id, expected) in [("migrated", 100), ("fresh", 0)] {
+        let record: Value = serde_json::from_str(table.get(id).unwrap().unwrap().value()).unwrap();
+        assert_eq!(record["clicks"], expected);
+    }
+}
+
+#[tokio::test]
+async fn test_redirect_increments_clicks() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com", "custom_id": "counted", "ref_id": "counter" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    for _ in 0..3 {
+        let response = get(&app, "/counted").await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    }
+    
+    // Monitoring hits are not counted
+    let response = get(&app, "/counted?no_count=true").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    {
+        let read_txn = db.begin_read().unwrap();
+        let table = read_txn.open_table(TABLE_URLS).unwrap();
+        let record: Value = serde_json::from_str(table.get("counted").unwrap().unwrap().value()).unwrap();
+        assert_eq!(record["clicks"], 3);
+    }
+    
+    // Listings resolve index entries to the updated record
+    let response = get(&app, "/api/urls?ref_id=counter").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["clicks"], 3);
+}
+
+#[tokio::test]
+async fn test_list_sorted_by_last_accessed() {
+    let (app, _temp_db) = setup_test_app();
+    
+    for id in ["never", "older", "newer"] {
+        create_url(&app, json!({ "url": "https://example.com", "custom_id": id, "ref_id": "recent" })).await;
+    }
+    
+    get(&app, "/older").await;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    get(&app, "/newer").await;
+    
+    let response = get(&app, "/api/urls?ref_id=recent&sort=last_accessed_desc").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    let ids: Vec<&str> = body["data"].as_array().unwrap().iter().map(|link| link["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["newer", "older", "never"]);
+    assert!(body["data"][2]["last_accessed_at"].is_null());
+    
+    // Sorting happens before pagination
+    let response = get(&app, "/api/urls?ref_id=recent&sort=last_accessed_desc&limit=1&page=2").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["id"], "older");
+    
+    let response = get(&app, "/api/urls?ref_id=recent&sort=bogus").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_saturated_redirects_still_redirect_but_drop_counts() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, Config {
+        max_concurrent_redirects: 1,
+        ..Config::default()
+    });
+    let app = create_app(state.clone());
+    
+    // A webhook receiver that accepts connections but never answers, so the
+    // first counted click keeps the only counting slot busy
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let webhook = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (accepted_tx, mut accepted) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            accepted_tx.send(stream).unwrap();
+        }
+    });
+    
+    create_url(&app, json!({ "url": "https://example.com", "custom_id": "viral", "click_webhook": webhook })).await;
+    
+    assert_eq!(get(&app, "/viral").await.status(), StatusCode::TEMPORARY_REDIRECT);
+    let stalled = tokio::time::timeout(std::time::Duration::from_secs(5), accepted.recv())
+        .await
+        .expect("webhook was not called")
+        .unwrap();
+    
+    // A burst of concurrent redirects is served, but neither counted nor reported
+    let burst = (0..20).map(|_| {
+        let app = app.clone();
+        tokio::spawn(async move { get(&app, "/viral").await.status() })
+    });
+    for status in futures_util::future::join_all(burst).await {
+        assert_eq!(status.unwrap(), StatusCode::TEMPORARY_REDIRECT);
+    }
+    
+    let response = get(&app, "/api/admin/metrics").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["dropped_click_counts"], 20);
+    assert_eq!(state.metrics.dropped_click_counts(), 20);
+    
+    let response = get(&app, "/api/urls/viral/stats").await;
+    assert_eq!(response_json(response.into_body()).await["clicks"], 1);
+    assert!(accepted.try_recv().is_err());
+    
+    // Once the stalled webhook fails, the slot frees up and clicks count again
+    drop(stalled);
+    let mut clicks = 1;
+    for _ in 0..50 {
+        get(&app, "/viral").await;
+        let response = get(&app, "/api/urls/viral/stats").await;
+        clicks = response_json(response.into_body()).await["clicks"].as_u64().unwrap();
+        if clicks > 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(clicks, 2);
+}
+
+#[tokio::test]
+async fn test_merge_sums_clicks_and_aliases_deleted_source() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/a", "custom_id": "dup-a", "ref_id": "owner", "initial_clicks": 5 })).await;
+    create_url(&app, json!({ "url": "https://example.com/b", "custom_id": "dup-b", "ref_id": "owner", "initial_clicks": 7 })).await;
+    create_url(&app, json!({ "url": "https://example.com/c", "custom_id": "other", "ref_id": "someone" })).await;
+    
+    let response = post_json(
+        &app,
+        "/api/urls/merge",
+        json!({ "source_id": "dup-a", "target_id": "other", "ref_id": "owner" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    
+    let response = post_json(
+        &app,
+        "/api/urls/merge",
+        json!({ "source_id": "dup-a", "target_id": "dup-b", "ref_id": "owner", "delete_source": true }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["clicks"], 12);
+    assert_eq!(body["source_deleted"], true);
+    
+    let response = get(&app, "/api/urls/dup-b/stats").await;
+    assert_eq!(response_json(response.into_body()).await["clicks"], 12);
+    
+    // The old slug now leads to the target
+    let response = get(&app, "/dup-a").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers().get("location").unwrap(), "https://example.com/b");
+    let response = get(&app, "/api/urls?ref_id=owner").await;
+    assert_eq!(response_json(response.into_body()).await["total_fetched"], 1);
+}
+
+#[tokio::test]
+async fn test_merge_keeps_source_repointed() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/a", "custom_id": "keep-a", "initial_clicks": 3 })).await;
+    create_url(&app, json!({ "url": "https://example.com/b", "custom_id": "keep-b", "initial_clicks": 4 })).await;
+    
+    let response = post_json(&app, "/api/urls/merge", json!({ "source_id": "keep-a", "target_id": "keep-b" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response_json(response.into_body()).await["clicks"], 7);
+    
+    let response = get(&app, "/api/urls/keep-a/stats").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["clicks"], 0);
+    assert_eq!(body["original_url"], "https://example.com/b");
+    
+    let response = post_json(&app, "/api/urls/merge", json!({ "source_id": "keep-a", "target_id": "keep-a" })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = post_json(&app, "/api/urls/merge", json!({ "source_id": "keep-a", "target_id": "missing" })).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[cfg(not(feature = "analytics"))]
+#[tokio::test]
+async fn test_redirects_and_counts_without_analytics() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    create_url(&app, json!({ "url": "https://example.com/lean", "custom_id": "lean" })).await;
+    let response = get(&app, "/lean").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers().get("location").unwrap(), "https://example.com/lean");
+    
+    // The total counter is always kept; the event log is compiled out
+    let response = get(&app, "/api/urls/lean/stats").await;
+    assert_eq!(response_json(response.into_body()).await["clicks"], 1);
+    let read_txn = db.begin_read().unwrap();
+    assert!(read_txn.open_table(TABLE_CLICK_EVENTS).unwrap().is_empty().unwrap());
+    
+    let response = get(&app, "/api/urls/lean/heatmap").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_stats_returns_metadata_and_clicks() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/stats", "custom_id": "stats", "ref_id": "owner" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    get(&app, "/stats").await;
+    get(&app, "/stats").await;
+    
+    let response = get(&app, "/api/urls/stats/stats?ref_id=owner").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], "stats");
+    assert_eq!(body["original_url"], "https://example.com/stats");
+    assert!(body["short_url"].as_str().unwrap().ends_with("/stats"));
+    assert_eq!(body["ref_id"], "owner");
+    assert!(body["created_at"].is_string());
+    assert_eq!(body["clicks"], 2);
+    
+    // Without a ref_id ownership is not checked, as for deletion
+    let response = get(&app, "/api/urls/stats/stats").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_stats_not_found_and_wrong_owner() {
+    let (app, _temp_db) = setup_test_app();
+    
+    let response = get(&app, "/api/urls/missing/stats").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "not_found");
+    
+    create_url(&app, json!({ "url": "https://example.com", "custom_id": "private", "ref_id": "owner" })).await;
+    let response = get(&app, "/api/urls/private/stats?ref_id=intruder").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "forbidden");
+}
+
+#[tokio::test]
+async fn test_by_date_returns_links_created_that_day() {
+    let (app, db, _temp_db) = setup_test_app_with_db();
+    
+    // Seed links around the boundaries of 2026-01-17 (UTC)
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table_main = write_txn.open_table(TABLE_URLS).unwrap();
+        let mut table = write_txn.open_table(TABLE_REF_INDEX).unwrap();
+        for (id, created_at) in [
+            ("before", "2026-01-16T23:59:59.999999Z"),
+            ("midnight", "2026-01-17T00:00:00Z"),
+            ("noon", "2026-01-17T12:00:00Z"),
+            ("after", "2026-01-18T00:00:00Z"),
+        ] {
+            let created_at: chrono::DateTime<chrono::Utc> = created_at.parse().unwrap();
+            let record = json!({
+                "id": id,
+                "original_url": format!("https://example.com/{}", id),
+                "short_url": format!("http://localhost:8080/{}", id),
+                "ref_id": "reporter",
+                "created_at": created_at,
+            });
+            table_main.insert(id, record.to_string().as_str()).unwrap();
+            let key = format!("reporter:{}", created_at.timestamp_micros());
+            table.insert(key.as_str(), id).unwrap();
+        }
+    }
+    write_txn.commit().unwrap();
+    
+    // A link created now never falls on the seeded day
+    create_url(&app, json!({ "url": "https://example.com/today", "ref_id": "reporter" })).await;
+    
+    let response = get(&app, "/api/urls/by-date?ref_id=reporter&date=2026-01-17").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["data"][0]["id"], "midnight");
+    assert_eq!(body["data"][1]["id"], "noon");
+    
+    let response = get(&app, "/api/urls/by-date?ref_id=reporter&date=17-01-2026").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "invalid_date");
+}
+
+#[tokio::test]
+async fn test_head_list_returns_headers_without_body() {
+    let (app, _temp_file) = setup_test_app();
+    
+    for i in 0..3 {
+        create_url(&app, json!({ "url": format!("https://example.com/{}", i), "ref_id": "monitor" })).await;
+    }
+    
+    let response = get(&app, "/api/urls?ref_id=monitor&limit=1").await;
+    assert_eq!(response.headers().get("x-total-count").unwrap(), "3");
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("HEAD")
+                .uri("/api/urls?ref_id=monitor&limit=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("x-total-count").unwrap(), "3");
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/json");
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(bytes.is_empty());
+}
+
+#[tokio::test]
+async fn test_redirect_log_stream_pushes_redirects() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://live.example.com/page", "custom_id": "live-log" })).await;
+    
+    // Subscribing happens when the handler runs, before the body is polled
+    let response = get(&app, "/api/admin/redirect-log/stream").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut body = response.into_body();
+    
+    let response = get(&app, "/live-log").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+        .await
+        .expect("No event arrived")
+        .unwrap()
+        .unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(text.starts_with("event: redirect\n"), "unexpected frame: {}", text);
+    
+    let data = text
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .expect("event should carry data");
+    let event: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(event["id"], "live-log");
+    assert_eq!(event["destination_host"], "live.example.com");
+    assert_eq!(event["counted"], true);
+    assert!(event["timestamp"].is_string());
+}
+
+#[tokio::test]
+async fn test_permanent_links_redirect_with_301() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/forever", "custom_id": "evergreen", "ref_id": "seo", "permanent": true })).await;
+    create_url(&app, json!({ "url": "https://example.com/campaign", "custom_id": "seasonal" })).await;
+    
+    let response = get(&app, "/evergreen").await;
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()["location"], "https://example.com/forever");
+    assert!(response.headers().get("link").is_some());
+    
+    let response = get(&app, "/seasonal").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // The flag is part of the record
+    let response = get(&app, "/api/urls?ref_id=seo").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"][0]["permanent"], true);
+}
+
+#[tokio::test]
+async fn test_force_https_redirects_plain_http() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        force_https: true,
+        ..Config::default()
+    });
+    create_url(&app, json!({ "url": "https://example.com/secure", "custom_id": "secure" })).await;
+    
+    let forwarded = |uri: &str, proto: &str| {
+        Request::builder()
+            .uri(uri)
+            .header("Host", "sho.rt")
+            .header("X-Forwarded-Proto", proto)
+            .body(Body::empty())
+            .unwrap()
+    };
+    
+    let response = app.clone().oneshot(forwarded("/api/urls?ref_id=me&page=2", "http")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()["location"], "https://sho.rt/api/urls?ref_id=me&page=2");
+    assert!(response.headers().get("x-request-id").is_some());
+    
+    // Requests that arrived over https are handled normally
+    let response = app.clone().oneshot(forwarded("/secure", "https")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers()["location"], "https://example.com/secure");
+    
+    // Health checks stay on plain http
+    let response = app.clone().oneshot(forwarded("/health", "http")).await.unwrap();
+    assert_ne!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    
+    // Disabled by default
+    let (app, _temp_db) = setup_test_app_with_config(Config::default());
+    let response = app.oneshot(forwarded("/api/auth/check", "http")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_leaderboard_lists_most_clicked_links() {
+    let (app, _temp_file) = setup_test_app();
+    
+    for (slug, clicks) in [("lb-low", 3), ("lb-top", 50), ("lb-mid", 20), ("lb-tie", 20), ("lb-none", 0)] {
+        let response = create_url(&app, json!({
+            "url": format!("https://{}.example.com/page", slug),
+            "custom_id": slug,
+            "initial_clicks": clicks
+        }))
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = get(&app, "/api/admin/leaderboard?limit=3").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(
+        body["data"],
+        json!([
+            { "id": "lb-top", "destination_host": "lb-top.example.com", "clicks": 50 },
+            { "id": "lb-mid", "destination_host": "lb-mid.example.com", "clicks": 20 },
+            { "id": "lb-tie", "destination_host": "lb-tie.example.com", "clicks": 20 },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_creation_rate_counts_links_per_bucket() {
+    let (app, db, _temp_file) = setup_test_app_with_db();
+    
+    // Links created now land in the latest of the default 24 hourly buckets
+    for i in 0..3 {
+        let response = create_url(&app, json!({ "url": format!("https://rate.example.com/{}", i) })).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    let response = get(&app, "/api/admin/creation-rate").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["bucket"], "hour");
+    assert_eq!(body["total"], 3);
+    let series = body["series"].as_array().unwrap();
+    assert_eq!(series.len(), 24);
+    assert_eq!(series[23]["created"], 3);
+    
+    // Seed counters for fixed past hours
+    let write_txn = db.begin_write().unwrap();
+    for at in ["2026-01-17T09:10:00Z", "2026-01-17T09:50:00Z", "2026-01-17T11:00:00Z", "2026-01-18T02:30:00Z"] {
+        record_creation(&write_txn, at.parse().unwrap()).unwrap();
+    }
+    write_txn.commit().unwrap();
+    
+    let response = get(&app, "/api/admin/creation-rate?from=2026-01-17T09:30:00Z&to=2026-01-17T11:59:00Z").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["from"], "2026-01-17T09:00:00Z");
+    assert_eq!(body["total"], 3);
+    assert_eq!(
+        body["series"],
+        json!([
+            { "start": "2026-01-17T09:00:00Z", "created": 2 },
+            { "start": "2026-01-17T10:00:00Z", "created": 0 },
+            { "start": "2026-01-17T11:00:00Z", "created": 1 },
+        ])
+    );
+    
+    let response = get(&app, "/api/admin/creation-rate?bucket=day&from=2026-01-16&to=2026-01-18").await;
+    let body = response_json(response.into_body()).await;
+    let counts: Vec<u64> = body["series"].as_array().unwrap().iter().map(|b| b["created"].as_u64().unwrap()).collect();
+    assert_eq!(counts, [0, 3, 1]);
+    
+    for uri in [
+        "/api/admin/creation-rate?from=2026-01-18&to=2026-01-17",
+        "/api/admin/creation-rate?from=yesterday",
+        "/api/admin/creation-rate?from=2020-01-01&to=2026-01-01",
+    ] {
+        assert_eq!(get(&app, uri).await.status(), StatusCode::BAD_REQUEST, "{}", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_oldest_links_in_ascending_age() {
+    let (app, _temp_file) = setup_test_app();
+    
+    for slug in ["old-1", "old-2", "old-3", "old-4", "old-5"] {
+        let response = create_url(&app, json!({
+            "url": format!("https://example.com/{}", slug),
+            "ref_id": "retention",
+            "custom_id": slug
+        }))
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    }
+    
+    // A rename keeps the link's age; a deleted link drops out
+    let response = post_json(&app, "/api/urls/old-2/rename", json!({ "new_id": "old-2b" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(Request::builder().method("DELETE").uri("/api/old-1").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    let response = get(&app, "/api/admin/oldest?limit=3").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    let data = body["data"].as_array().unwrap();
+    let ids: Vec<&str> = data.iter().map(|link| link["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["old-2b", "old-3", "old-4"]);
+    let created: Vec<&str> = data.iter().map(|link| link["created_at"].as_str().unwrap()).collect();
+    assert!(created.windows(2).all(|pair| pair[0] < pair[1]));
+    
+    let response = get(&app, "/api/admin/oldest").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["data"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_top_by_clicks_over_large_input() {
+    // Streamed lazily: only the heap of `limit` entries is ever held
+    let links = (0..1_000_000u64).map(|i| (format!("s{}", i), (i * 7919) % 1_000_003, ()));
+    let top = top_by_clicks(links, 5);
+    
+    let clicks: Vec<u64> = top.iter().map(|(_, clicks, _)| *clicks).collect();
+    let mut expected: Vec<u64> = (0..1_000_000u64).map(|i| (i * 7919) % 1_000_003).collect();
+    expected.sort_unstable_by(|a, b| b.cmp(a));
+    assert_eq!(clicks, expected[..5]);
+    
+    assert!(top_by_clicks(Vec::<(String, u64, ())>::new(), 5).is_empty());
+}
+
+/// Helper function to visit a short link with an optional `Referer`
+async fn visit_from(app: &axum::Router, uri: &str, referrer: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri(uri);
+    if let Some(referrer) = referrer {
+        request = request.header("referer", referrer);
+    }
+    app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_allowed_referrers_block_other_sites() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/embed",
+        "custom_id": "embed-only",
+        "allowed_referrers": [" Partner.example "]
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let allowed = visit_from(&app, "/embed-only", Some("https://partner.example/page")).await;
+    assert_eq!(allowed, StatusCode::TEMPORARY_REDIRECT);
+    let subdomain = visit_from(&app, "/embed-only", Some("https://blog.partner.example/")).await;
+    assert_eq!(subdomain, StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(visit_from(&app, "/embed-only", None).await, StatusCode::TEMPORARY_REDIRECT);
+    
+    for referrer in ["https://evil.example/", "https://notpartner.example/", "https://partner.example.evil.com/"] {
+        assert_eq!(visit_from(&app, "/embed-only", Some(referrer)).await, StatusCode::FORBIDDEN, "{}", referrer);
+    }
+    
+    // Blocked visits are not counted
+    let response = get(&app, "/api/urls/embed-only/stats").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["clicks"], 3);
+}
+
+/// Helper function to visit `uri` as the client connecting from `ip`
+async fn visit_as(app: &axum::Router, uri: &str, ip: &str) -> StatusCode {
+    get_from_peer(app, uri, ip, None).await.status()
+}
+
+#[tokio::test]
+async fn test_max_unique_visitors_refuses_new_visitors_past_the_cap() {
+    let (app, db, _temp_file) = setup_test_app_with_db();
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/invite",
+        "custom_id": "invite",
+        "max_unique_visitors": 2
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    // Repeat visits by the same IP take a single place
+    for _ in 0..3 {
+        assert_eq!(visit_as(&app, "/invite", "203.0.113.1").await, StatusCode::TEMPORARY_REDIRECT);
+    }
+    assert_eq!(visit_as(&app, "/invite", "203.0.113.2").await, StatusCode::TEMPORARY_REDIRECT);
+    
+    let response = get_from_peer(&app, "/invite", "203.0.113.3", None).await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "visitor_limit_reached");
+    
+    // Known visitors keep access, also after a rename
+    let response = post_json(&app, "/api/urls/invite/rename", json!({ "new_id": "invite-2" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(visit_as(&app, "/invite-2", "203.0.113.2").await, StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(visit_as(&app, "/invite-2", "203.0.113.4").await, StatusCode::GONE);
+    
+    // Only hashes are stored, never the addresses
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_VISITORS).unwrap();
+    let keys: Vec<String> = table.iter().unwrap().map(|entry| entry.unwrap().0.value().to_string()).collect();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.iter().all(|key| key.starts_with("invite-2:") && !key.contains("203.0.113")));
+}
+
+#[tokio::test]
+async fn test_max_unique_visitors_ignores_spoofed_forwarded_for_and_no_count() {
+    let (app, _temp_db) = setup_test_app_with_config(Config {
+        trusted_proxies: ["10.0.0.1".parse().unwrap()].into_iter().collect(),
+        ..Config::default()
+    });
+    
+    create_url(&app, json!({ "url": "https://example.com/vip", "custom_id": "vip", "max_unique_visitors": 2 })).await;
+    
+    // Monitoring visits pass but take no place
+    for ip in ["203.0.113.10", "203.0.113.11", "203.0.113.12"] {
+        assert_eq!(visit_as(&app, "/vip?no_count=true", ip).await, StatusCode::TEMPORARY_REDIRECT);
+    }
+    
+    // A direct client cannot pose as new visitors with made-up headers
+    assert_eq!(visit_as(&app, "/vip", "203.0.113.1").await, StatusCode::TEMPORARY_REDIRECT);
+    for forwarded in ["198.51.100.1", "198.51.100.2"] {
+        let response = get_from_peer(&app, "/vip", "203.0.113.1", Some(forwarded)).await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    }
+    
+    // Behind the trusted proxy, the forwarded client is the visitor
+    let response = get_from_peer(&app, "/vip", "10.0.0.1", Some("198.51.100.1")).await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    let response = get_from_peer(&app, "/vip", "10.0.0.1", Some("198.51.100.2")).await;
+    assert_eq!(response.status(), StatusCode::GONE);
+    
+    // The cap still applies to monitoring visits by new visitors
+    assert_eq!(visit_as(&app, "/vip?no_count=true", "203.0.113.13").await, StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_require_referrer_blocks_direct_visits() {
+    let (app, _temp_file) = setup_test_app();
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/strict",
+        "custom_id": "strict",
+        "allowed_referrers": ["partner.example"],
+        "require_referrer": true
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    assert_eq!(visit_from(&app, "/strict", None).await, StatusCode::FORBIDDEN);
+    let allowed = visit_from(&app, "/strict", Some("https://partner.example/")).await;
+    assert_eq!(allowed, StatusCode::TEMPORARY_REDIRECT);
+}
+
+#[tokio::test]
+async fn test_bincode_record_format_serves_links() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, Config {
+        record_format: RecordFormat::Bincode,
+        ..Config::default()
+    });
+    let db = state.db.clone();
+    let app = create_app(state);
+    
+    let response = create_url(&app, json!({ "url": "https://example.com/binary", "custom_id": "binary" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    for _ in 0..2 {
+        let response = get(&app, "/binary").await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()["location"], "https://example.com/binary");
+    }
+    
+    let response = get(&app, "/api/urls/binary/stats").await;
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["clicks"], 2);
+    
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_URLS).unwrap();
+    let stored = table.get("binary").unwrap().unwrap();
+    assert_eq!(RecordFormat::of(stored.value()), RecordFormat::Bincode);
+}
+
+#[tokio::test]
+async fn test_bundle_redirects_to_a_random_member() {
+    let (app, _temp_db) = setup_test_app();
+    let members = ["https://mirror-a.example.com/file", "https://mirror-b.example.com/file"];
+    
+    let response = post_json(&app, "/api/bundles", json!({ "members": members, "custom_id": "mirrors" })).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["id"], "mirrors");
+    assert_eq!(body["members"], json!(members));
+    
+    let mut seen = std::collections::HashSet::new();
+    for _ in 0..40 {
+        let response = get(&app, "/mirrors").await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        let location = response.headers().get("location").unwrap().to_str().unwrap().to_string();
+        assert!(members.contains(&location.as_str()), "unexpected destination {}", location);
+        seen.insert(location);
+    }
+    // 40 fair coin flips all landing on one side is vanishingly unlikely
+    assert_eq!(seen.len(), 2);
+    
+    for members in [json!(["https://only.example.com"]), json!(["https://a.example.com", "javascript:alert(1)"])] {
+        let response = post_json(&app, "/api/bundles", json!({ "members": members })).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_json(response.into_body()).await;
+        assert_eq!(body["code"], "invalid_bundle");
+    }
+}
+
+#[tokio::test]
+async fn test_long_routes_outlast_the_default_timeout() {
+    use axum::routing;
+    use shortener::route::with_timeouts;
+    use std::time::Duration;
+    
+    let config = Config {
+        request_timeout: Duration::from_millis(50),
+        long_request_timeout: Duration::from_secs(5),
+        ..Config::default()
+    };
+    let slow = || async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "done"
+    };
+    let app: axum::Router = with_timeouts(
+        axum::Router::new().route("/urls", routing::post(slow)),
+        axum::Router::new().route("/urls/export", routing::get(slow)),
+        &config,
+    );
+    
+    let response = post_json(&app, "/urls", json!({})).await;
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    let response = get(&app, "/urls/export").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    
+    // The real create is timed too, here while waiting for a slow request body
+    let (app, _temp_db) = setup_test_app_with_config(config);
+    let body = futures_util::stream::once(async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Ok::<_, std::convert::Infallible>(json!({ "url": "https://example.com/slow" }).to_string())
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .body(Body::from_stream(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+}
+
+#[tokio::test]
+async fn test_list_urls_as_csv() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/a", "ref_id": "sheet", "custom_id": "sheet-a" })).await;
+    create_url(&app, json!({ "url": "https://example.com/b?x=1,2", "ref_id": "sheet", "custom_id": "sheet-b" })).await;
+    create_url(&app, json!({ "url": "https://example.com/c", "ref_id": "other" })).await;
+    
+    let response = get(&app, "/api/urls?ref_id=sheet&format=csv").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/csv; charset=utf-8");
+    assert_eq!(response.headers().get("x-total-count").unwrap(), "2");
+    let body = String::from_utf8(response.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines[0], "id,original_url,clicks,created_at");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("sheet-b,\"https://example.com/b?x=1,2\",0,"));
+    assert!(lines[2].starts_with("sheet-a,https://example.com/a,0,"));
+    
+    // The Accept header selects CSV too, and pagination still applies
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/urls?ref_id=sheet&limit=1&page=2")
+                .header("accept", "text/csv")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = String::from_utf8(response.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap();
+    assert_eq!(body.lines().collect::<Vec<_>>().len(), 2);
+    assert!(body.lines().nth(1).unwrap().starts_with("sheet-a,"));
+}
+
+#[tokio::test]
+async fn test_named_counters_increment_and_read_back() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/shop", "ref_id": "shop", "custom_id": "checkout" })).await;
+    
+    let response = get(&app, "/api/urls/checkout/counter/conversions").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response_json(response.into_body()).await["value"], 0);
+    
+    for expected in 1..=3 {
+        let response = post_json(&app, "/api/urls/checkout/counter/conversions/incr?ref_id=shop", json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response.into_body()).await;
+        assert_eq!(body["name"], "conversions");
+        assert_eq!(body["value"], expected);
+    }
+    post_json(&app, "/api/urls/checkout/counter/signups/incr", json!({})).await;
+    
+    let body = response_json(get(&app, "/api/urls/checkout/counter/conversions").await.into_body()).await;
+    assert_eq!(body, json!({ "id": "checkout", "name": "conversions", "value": 3 }));
+    let body = response_json(get(&app, "/api/urls/checkout/counter/signups").await.into_body()).await;
+    assert_eq!(body["value"], 1);
+    
+    // Counters are not clicks
+    let body = response_json(get(&app, "/api/urls/checkout/stats").await.into_body()).await;
+    assert_eq!(body["clicks"], 0);
+    
+    let response = post_json(&app, "/api/urls/checkout/counter/conversions/incr?ref_id=intruder", json!({})).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = get(&app, "/api/urls/checkout/counter/bad%3Aname").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = get(&app, "/api/urls/missing-slug/counter/conversions").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    
+    // Renaming carries the counters along
+    let response = post_json(&app, "/api/urls/checkout/rename", json!({ "new_id": "pay", "ref_id": "shop" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(get(&app, "/api/urls/pay/counter/conversions").await.into_body()).await;
+    assert_eq!(body["value"], 3);
+}
+
+#[tokio::test]
+async fn test_stats_include_clicks_of_redirects_just_served() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/fresh", "custom_id": "fresh" })).await;
+    
+    // No flush step: each read right after a redirect already sees its click
+    for expected in 1..=3 {
+        let response = get(&app, "/fresh").await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        
+        let body = response_json(get(&app, "/api/urls/fresh/stats").await.into_body()).await;
+        assert_eq!(body["clicks"], expected);
+        let body = response_json(get(&app, "/api/urls").await.into_body()).await;
+        assert_eq!(body["data"][0]["clicks"], expected);
+    }
+}
+
+#[tokio::test]
+async fn test_list_tags_of_a_ref_id() {
+    let (app, _temp_db) = setup_test_app();
+    
+    create_url(&app, json!({ "url": "https://example.com/a", "ref_id": "tagger", "tags": ["spring", "email"] })).await;
+    create_url(&app, json!({ "url": "https://example.com/b", "ref_id": "tagger", "tags": ["email", "vip"] })).await;
+    create_url(&app, json!({ "url": "https://example.com/c", "ref_id": "tagger" })).await;
+    create_url(&app, json!({ "url": "https://example.com/d", "ref_id": "other", "tags": ["private"] })).await;
+    
+    let response = get(&app, "/api/urls/tags?ref_id=tagger").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body, json!({ "ref_id": "tagger", "tags": ["email", "spring", "vip"] }));
+    
+    let response = get(&app, "/api/urls/tags").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_metrics_count_transactions() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, Config::default());
+    let app = create_app(state.clone());
+    
+    let metrics = || async {
+        let body = response_json(get(&app, "/api/admin/metrics").await.into_body()).await;
+        (body["read_transactions"].as_u64().unwrap(), body["write_transactions"].as_u64().unwrap())
+    };
+    assert_eq!(metrics().await, (0, 0));
+    
+    // A create writes once
+    create_url(&app, json!({ "url": "https://example.com/tx", "custom_id": "tx" })).await;
+    assert_eq!(metrics().await, (0, 1));
+    
+    // A redirect reads the link, then writes its click
+    get(&app, "/tx").await;
+    assert_eq!(metrics().await, (1, 2));
+    
+    // Uncounted visits and stats only read
+    get(&app, "/tx?no_count=true").await;
+    get(&app, "/api/urls/tx/stats").await;
+    assert_eq!(metrics().await, (3, 2));
+    assert_eq!(state.metrics.write_transactions(), 2);
+}
+
+#[tokio::test]
+async fn test_trailing_slash_is_trimmed_only_when_enabled() {
+    let (app, _temp_db) = setup_test_app_with_config(Config { trim_trailing_slash: true, ..Config::default() });
+    create_url(&app, json!({ "url": "https://example.com/slash", "custom_id": "abc123" })).await;
+    
+    let response = get(&app, "/abc123/").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers().get("location").unwrap(), "https://example.com/slash");
+    assert_eq!(get(&app, "/abc123").await.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    // Only one slash is trimmed, and the API is unaffected
+    assert_eq!(get(&app, "/abc123//").await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(get(&app, "/api/").await.status(), StatusCode::NOT_FOUND);
+    
+    let body = response_json(get(&app, "/api/urls/abc123/stats").await.into_body()).await;
+    assert_eq!(body["clicks"], 2);
+    
+    let (app, _temp_db) = setup_test_app();
+    create_url(&app, json!({ "url": "https://example.com/slash", "custom_id": "abc123" })).await;
+    assert_eq!(get(&app, "/abc123/").await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(get(&app, "/abc123").await.status(), StatusCode::TEMPORARY_REDIRECT);
+}
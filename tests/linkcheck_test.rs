@@ -0,0 +1,180 @@
+//! Integration tests for destination reachability checks
+//! 
+//! Destinations point at throwaway axum servers bound to an ephemeral local
+//! port, so no external network access is needed.
+
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{Request, StatusCode},
+    response::Redirect,
+    routing::any,
+    Router,
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+use shortener::config::Config;
+use shortener::database::{init_db, AppState};
+use shortener::route::create_app;
+
+/// Helper function to create a test application with an explicit configuration
+fn setup_test_app(config: Config) -> (axum::Router, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, config);
+    
+    (create_app(state), temp_db)
+}
+
+/// Helper function to serve `router` on an ephemeral port, returning its base URL
+async fn spawn_mock_server(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// Helper function to issue a JSON request against the app
+async fn send(app: &axum::Router, method: &str, uri: &str, payload: Value) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Helper function to parse response body as JSON
+async fn response_json(body: Body) -> Value {
+    let bytes = body
+        .collect()
+        .await
+        .expect("Failed to read response body")
+        .to_bytes();
+    
+    serde_json::from_slice(&bytes).expect("Failed to parse JSON")
+}
+
+#[tokio::test]
+async fn test_validate_reports_mixed_statuses() {
+    let mock = spawn_mock_server(
+        Router::new()
+            .route("/ok", any(|| async { StatusCode::OK }))
+            .route("/gone", any(|| async { StatusCode::NOT_FOUND }))
+            .route("/broken", any(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .route(
+                "/slow",
+                any(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    StatusCode::OK
+                }),
+            ),
+    )
+    .await;
+    
+    let (app, _temp_db) = setup_test_app(Config {
+        validate_timeout: Duration::from_millis(300),
+        ..Config::default()
+    });
+    
+    for (slug, path) in [("vok", "/ok"), ("vgone", "/gone"), ("vbroken", "/broken"), ("vslow", "/slow")] {
+        let response = send(
+            &app,
+            "POST",
+            "/api/urls",
+            json!({ "url": format!("{}{}", mock, path), "ref_id": "qa", "custom_id": slug }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = send(
+        &app,
+        "POST",
+        "/api/urls/validate",
+        json!({ "ids": ["vok", "vgone", "vbroken", "vslow", "nope"] }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    
+    assert_eq!(body["vok"], json!({ "status": 200, "reachable": true }));
+    assert_eq!(body["vgone"], json!({ "status": 404, "reachable": false }));
+    assert_eq!(body["vbroken"], json!({ "status": 500, "reachable": false }));
+    assert_eq!(body["vslow"], json!({ "status": null, "reachable": false, "error": "timeout" }));
+    assert_eq!(body["nope"], json!({ "status": null, "reachable": false, "error": "not_found" }));
+}
+
+#[tokio::test]
+async fn test_validate_rejects_oversized_batch() {
+    let (app, _temp_db) = setup_test_app(Config {
+        validate_max_batch: 2,
+        ..Config::default()
+    });
+    
+    let response = send(&app, "POST", "/api/urls/validate", json!({ "ids": ["a", "b", "c"] })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response_json(response.into_body()).await;
+    assert_eq!(body["code"], "batch_too_large");
+    assert_eq!(body["max"], 2);
+}
+
+#[tokio::test]
+async fn test_validate_follows_redirects_and_detects_loops() {
+    let mock = spawn_mock_server(
+        Router::new()
+            .route("/start", any(|| async { Redirect::temporary("/landing") }))
+            .route("/landing", any(|| async { StatusCode::OK }))
+            .route("/ping", any(|| async { Redirect::temporary("/pong") }))
+            .route("/pong", any(|| async { Redirect::temporary("/ping") }))
+            .route(
+                "/chain/{n}",
+                any(|Path(n): Path<u32>| async move { Redirect::temporary(&format!("/chain/{}", n + 1)) }),
+            ),
+    )
+    .await;
+    
+    let (app, _temp_db) = setup_test_app(Config {
+        max_redirect_hops: 3,
+        ..Config::default()
+    });
+    
+    for (slug, path) in [("hfollow", "/start"), ("hloop", "/ping"), ("hchain", "/chain/0")] {
+        let response = send(
+            &app,
+            "POST",
+            "/api/urls",
+            json!({ "url": format!("{}{}", mock, path), "ref_id": "qa", "custom_id": slug }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+    
+    let response = send(
+        &app,
+        "POST",
+        "/api/urls/validate",
+        json!({ "ids": ["hfollow", "hloop", "hchain"] }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response_json(response.into_body()).await;
+    
+    assert_eq!(body["hfollow"], json!({ "status": 200, "reachable": true }));
+    assert_eq!(body["hloop"], json!({ "status": 307, "reachable": false, "error": "redirect_loop" }));
+    assert_eq!(body["hchain"], json!({ "status": 307, "reachable": false, "truncated": true }));
+}
@@ -0,0 +1,99 @@
+//! Integration tests for slug reservations and the expiry sweep
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use chrono::{Duration, Utc};
+use redb::ReadableDatabase;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tower::ServiceExt;
+
+use shortener::database::{init_db, AppState, TABLE_RESERVATIONS};
+use shortener::model::Reservation;
+use shortener::reservations::sweep_expired;
+use shortener::route::create_app;
+
+/// Helper function to create a test application, also returning its database handle
+fn setup_test_app() -> (axum::Router, Arc<redb::Database>, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::new(db);
+    let db = state.db.clone();
+    
+    (create_app(state), db, temp_db)
+}
+
+/// Helper function to POST a JSON payload, returning the status
+async fn post_json(app: &axum::Router, uri: &str, payload: Value) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Helper function to store a reservation directly
+fn insert_reservation(db: &redb::Database, slug: &str, ref_id: &str, expires_in: Duration) {
+    let reservation = Reservation {
+        ref_id: Some(ref_id.to_string()),
+        expires_at: Utc::now() + expires_in,
+    };
+    let write_txn = db.begin_write().unwrap();
+    {
+        let mut table = write_txn.open_table(TABLE_RESERVATIONS).unwrap();
+        table
+            .insert(slug, serde_json::to_string(&reservation).unwrap().as_str())
+            .unwrap();
+    }
+    write_txn.commit().unwrap();
+}
+
+#[tokio::test]
+async fn test_reservation_holds_slug_for_its_owner() {
+    let (app, _db, _temp_db) = setup_test_app();
+    
+    let status = post_json(&app, "/api/urls/reserve", json!({ "id": "spring-sale", "ref_id": "owner" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    
+    // Others can neither reserve nor create it
+    let status = post_json(&app, "/api/urls/reserve", json!({ "id": "spring-sale", "ref_id": "rival" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let status = post_json(&app, "/api/urls", json!({ "url": "https://example.com", "custom_id": "spring-sale", "ref_id": "rival" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    
+    // The owner can, which consumes the reservation
+    let status = post_json(&app, "/api/urls", json!({ "url": "https://example.com", "custom_id": "spring-sale", "ref_id": "owner" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_sweep_removes_only_expired_reservations() {
+    let (app, db, _temp_db) = setup_test_app();
+    
+    insert_reservation(&db, "stale", "owner", Duration::seconds(-60));
+    insert_reservation(&db, "fresh", "owner", Duration::seconds(600));
+    
+    // An expired reservation no longer blocks anyone, even before the sweep
+    let status = post_json(&app, "/api/urls/reserve", json!({ "id": "stale", "ref_id": "rival" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    insert_reservation(&db, "stale", "owner", Duration::seconds(-60));
+    
+    assert_eq!(sweep_expired(&db, Utc::now()).unwrap(), 1);
+    
+    let read_txn = db.begin_read().unwrap();
+    let table = read_txn.open_table(TABLE_RESERVATIONS).unwrap();
+    assert!(table.get("stale").unwrap().is_none());
+    assert!(table.get("fresh").unwrap().is_some());
+}
@@ -0,0 +1,527 @@
+//! Integration tests for slug rules: prefix/suffix configuration and reserved paths
+//! 
+//! Configuration is passed explicitly through `AppState::with_config` so the
+//! tests never touch process-wide environment variables.
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tower::ServiceExt;
+
+use shortener::config::Config;
+use shortener::database::{init_db, AppState};
+use shortener::route::create_app;
+use shortener::slug::{is_reserved_path, validate_slug, SlugChecks, MAX_CUSTOM_SLUG_LENGTH, RESERVED_PATHS};
+use shortener::slug_generator::{
+    Base62Slug, HashSlug, RandomSlug, SlugContext, SlugGenerator, SlugStrategy, WordsSlug,
+};
+
+/// Helper function to create a test application with an explicit configuration
+fn setup_test_app(config: Config) -> (axum::Router, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::with_config(db, config);
+    
+    (create_app(state), temp_db)
+}
+
+/// Helper function to create a short URL, returning the status and JSON body
+async fn create_url(app: &axum::Router, payload: Value) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+/// Helper function to build a configuration with the given affixes
+fn branded(prefix: &str, suffix: &str, affix_custom: bool) -> Config {
+    Config {
+        slug_prefix: prefix.to_string(),
+        slug_suffix: suffix.to_string(),
+        slug_affix_custom: affix_custom,
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn test_generated_slugs_carry_prefix_and_suffix() {
+    let (app, _temp_db) = setup_test_app(branded("go-", "-promo", false));
+    
+    for _ in 0..5 {
+        let (status, body) = create_url(&app, json!({ "url": "https://example.com" })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        
+        let id = body["id"].as_str().unwrap();
+        assert!(id.starts_with("go-") && id.ends_with("-promo"), "unexpected slug {}", id);
+        assert_eq!(id.len(), "go-".len() + 6 + "-promo".len());
+        assert!(body["short_url"].as_str().unwrap().ends_with(id));
+    }
+}
+
+#[tokio::test]
+async fn test_custom_ids_cannot_enter_reserved_affix_space() {
+    let (app, _temp_db) = setup_test_app(branded("go-", "", false));
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "go-abc123" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_custom_id");
+    
+    // Custom ids outside the prefix space are stored untouched
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "summer" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["id"], "summer");
+}
+
+#[tokio::test]
+async fn test_custom_ids_are_wrapped_when_configured() {
+    let (app, _temp_db) = setup_test_app(branded("go-", "", true));
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "summer" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["id"], "go-summer");
+    
+    // Uniqueness is checked on the wrapped slug
+    let (status, _) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "summer" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "api" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_custom_id");
+}
+
+#[tokio::test]
+async fn test_preview_url_matches_created_short_url() {
+    let (app, _temp_db) = setup_test_app(branded("go-", "", true));
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/urls/preview-url?id=launch")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let preview: Value = serde_json::from_slice(&bytes).unwrap();
+    
+    let (status, created) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "launch" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(preview["short_url"], created["short_url"]);
+    assert_eq!(preview["id"], "go-launch");
+}
+
+#[test]
+fn test_is_reserved_path_matches_first_segment() {
+    for path in ["api", "API", "/api", "/api/urls", "health", "metrics", "favicon.ico", "robots.txt", "static/app.css"] {
+        assert!(is_reserved_path(path), "{} should be reserved", path);
+    }
+    
+    for path in ["", "abc123", "apis", "my-api", "/docs/api", "healthy"] {
+        assert!(!is_reserved_path(path), "{} should not be reserved", path);
+    }
+}
+
+#[test]
+fn test_every_reserved_path_is_matched() {
+    // Guards new entries against typos such as a leading slash or upper case
+    for reserved in RESERVED_PATHS {
+        assert!(!reserved.starts_with('/') && !reserved.contains('/'), "{}", reserved);
+        assert_eq!(reserved.to_ascii_lowercase(), *reserved);
+        assert!(is_reserved_path(reserved));
+        assert!(is_reserved_path(&format!("/{}/anything", reserved.to_ascii_uppercase())));
+    }
+}
+
+#[tokio::test]
+async fn test_reserved_paths_rejected_on_create_and_not_redirected() {
+    let (app, _temp_db) = setup_test_app(Config::default());
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "Health" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_custom_id");
+    
+    let response = app
+        .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_unsafe_custom_ids_rejected_on_create() {
+    let (app, _temp_db) = setup_test_app(Config::default());
+    
+    let overlong = "x".repeat(MAX_CUSTOM_SLUG_LENGTH + 1);
+    for custom_id in ["a/b", overlong.as_str(), "api", "   ", "", "hello world", "café"] {
+        let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": custom_id })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "custom_id {:?}", custom_id);
+        assert_eq!(body["code"], "invalid_custom_id");
+    }
+    
+    let longest = "y".repeat(MAX_CUSTOM_SLUG_LENGTH);
+    for custom_id in ["spring_sale-2026", longest.as_str()] {
+        let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": custom_id })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["id"], custom_id);
+    }
+    
+    // Validation runs before the conflict check
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "spring_sale-2026/" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_custom_id");
+}
+
+#[tokio::test]
+async fn test_capacity_reports_space_and_fill_ratio() {
+    let (app, _temp_db) = setup_test_app(Config {
+        slug_length: 1,
+        ..Config::default()
+    });
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com/generated" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["id"].as_str().unwrap().len(), 1);
+    
+    for i in 0..30 {
+        let (status, _) = create_url(&app, json!({ "url": "https://example.com", "custom_id": format!("c{}", i) })).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    
+    let capacity = |app: axum::Router| async move {
+        let response = app
+            .oneshot(Request::builder().uri("/api/admin/capacity").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice::<Value>(&bytes).unwrap()
+    };
+    
+    let body = capacity(app.clone()).await;
+    assert_eq!(body["slug_space"], 62);
+    assert_eq!(body["used"], 31);
+    assert_eq!(body["remaining"], 31);
+    assert_eq!(body["fill_ratio"], 0.5);
+    assert!(body["warning"].is_string());
+    
+    // Below the threshold there is no warning
+    let (app, _temp_db) = setup_test_app(Config {
+        slug_length: 2,
+        ..Config::default()
+    });
+    create_url(&app, json!({ "url": "https://example.com" })).await;
+    let body = capacity(app).await;
+    assert_eq!(body["slug_space"], 62 * 62);
+    assert_eq!(body["used"], 1);
+    assert!(body["warning"].is_null());
+    
+    // The words scheme has a fixed space whatever the length
+    let (app, _temp_db) = setup_test_app(Config {
+        slug_length: 2,
+        slug_strategy: SlugStrategy::Words,
+        ..Config::default()
+    });
+    let body = capacity(app).await;
+    assert_eq!(body["slug_space"], 32 * 32 * 100);
+    assert_eq!(body["remaining"], 32 * 32 * 100);
+    
+    // An unbounded counter never fills up, however short the slugs
+    let (app, _temp_db) = setup_test_app(Config {
+        slug_length: 1,
+        slug_strategy: SlugStrategy::Base62,
+        ..Config::default()
+    });
+    for _ in 0..2 {
+        create_url(&app, json!({ "url": "https://example.com" })).await;
+    }
+    let body = capacity(app).await;
+    assert!(body["slug_space"].is_null());
+    assert_eq!(body["used"], 2);
+    assert!(body["remaining"].is_null());
+    assert!(body["fill_ratio"].is_null());
+    assert!(body["warning"].is_null());
+}
+
+#[test]
+fn test_validate_slug_reports_each_rule() {
+    let config = branded("go-", "", false);
+    let never_taken = |_: &str| false;
+    
+    let checks = validate_slug(&config, "summer-sale_2", never_taken);
+    assert_eq!(
+        checks,
+        SlugChecks { charset: true, length: true, reserved: true, numeric: true, available: true }
+    );
+    assert!(checks.valid());
+    
+    let checks = validate_slug(&config, "a/b", never_taken);
+    assert!(!checks.charset && checks.length && checks.reserved);
+    
+    assert!(!validate_slug(&config, "", never_taken).length);
+    assert!(!validate_slug(&config, &"x".repeat(MAX_CUSTOM_SLUG_LENGTH + 1), never_taken).length);
+    assert!(validate_slug(&config, &"x".repeat(MAX_CUSTOM_SLUG_LENGTH), never_taken).length);
+    
+    assert!(!validate_slug(&config, "api", never_taken).reserved);
+    assert!(!validate_slug(&config, "go-abc", never_taken).reserved);
+    
+    assert!(!validate_slug(&config, "12345", never_taken).numeric);
+    assert!(validate_slug(&config, "12345a", never_taken).numeric);
+    
+    let checks = validate_slug(&config, "taken", |slug| slug == "taken");
+    assert!(!checks.available);
+    assert!(!checks.valid());
+}
+
+#[test]
+fn test_validate_slug_checks_availability_of_stored_form() {
+    let config = branded("go-", "", true);
+    
+    let mut looked_up = String::new();
+    let checks = validate_slug(&config, "launch", |slug| {
+        looked_up = slug.to_string();
+        false
+    });
+    assert!(checks.valid());
+    assert_eq!(looked_up, "go-launch");
+}
+
+#[tokio::test]
+async fn test_validate_slug_endpoint() {
+    let (app, _temp_db) = setup_test_app(Config::default());
+    
+    let (status, _) = create_url(&app, json!({ "url": "https://example.com", "custom_id": "launch" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    
+    let validate = |app: axum::Router, query: &'static str| async move {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/urls/validate-slug?{}", query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+    };
+    
+    let (status, body) = validate(app.clone(), "id=fresh-slug").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["valid"], true);
+    
+    let (_, body) = validate(app.clone(), "id=launch").await;
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["checks"]["available"], false);
+    assert_eq!(body["checks"]["charset"], true);
+    
+    let (_, body) = validate(app.clone(), "id=API").await;
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["checks"]["reserved"], false);
+    
+    let (status, body) = validate(app, "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "missing_id");
+}
+
+/// Helper function to build a generator context for `url`
+fn slug_context<'a>(config: &'a Config, url: &'a str) -> SlugContext<'a> {
+    SlugContext {
+        config,
+        url,
+        ref_id: Some("user_123"),
+        created_at: chrono::Utc::now(),
+        attempt: 0,
+    }
+}
+
+/// Whether `slug` is a non-empty single path segment of `[A-Za-z0-9_-]`
+fn is_safe_slug(slug: &str) -> bool {
+    !slug.is_empty() && slug.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+#[test]
+fn test_random_slug_generator_uses_slug_length() {
+    let config = Config {
+        slug_length: 10,
+        ..Config::default()
+    };
+    let ctx = slug_context(&config, "https://example.com");
+    
+    let first = RandomSlug.generate(&ctx);
+    let second = RandomSlug.generate(&ctx);
+    assert_eq!(first.len(), 10);
+    assert!(first.chars().all(|ch| ch.is_ascii_alphanumeric()));
+    assert_ne!(first, second);
+}
+
+#[test]
+fn test_base62_slug_generator_increases_within_one_instant() {
+    let config = Config::default();
+    let ctx = slug_context(&config, "https://example.com");
+    let generator = Base62Slug::default();
+    
+    // Same created_at every time, yet every slug is new and sorts after the previous one
+    let slugs: Vec<String> = (0..100).map(|_| generator.generate(&ctx)).collect();
+    for pair in slugs.windows(2) {
+        assert!(pair[0].len() < pair[1].len() || (pair[0].len() == pair[1].len() && pair[0] < pair[1]));
+    }
+    assert!(slugs.iter().all(|slug| slug.chars().all(|ch| ch.is_ascii_alphanumeric())));
+}
+
+#[test]
+fn test_words_slug_generator_format() {
+    let config = Config::default();
+    let ctx = slug_context(&config, "https://example.com");
+    
+    for _ in 0..20 {
+        let slug = WordsSlug.generate(&ctx);
+        let parts: Vec<&str> = slug.split('-').collect();
+        assert_eq!(parts.len(), 3, "unexpected slug {}", slug);
+        assert!(parts[0].chars().all(|ch| ch.is_ascii_lowercase()));
+        assert!(parts[1].chars().all(|ch| ch.is_ascii_lowercase()));
+        assert_eq!(parts[2].len(), 2);
+        assert!(parts[2].chars().all(|ch| ch.is_ascii_digit()));
+    }
+}
+
+#[test]
+fn test_hash_slug_generator_is_deterministic() {
+    let config = Config::default();
+    let ctx = slug_context(&config, "https://example.com/a");
+    
+    let slug = HashSlug.generate(&ctx);
+    assert_eq!(slug.len(), config.slug_length);
+    assert!(is_safe_slug(&slug));
+    assert_eq!(HashSlug.generate(&ctx), slug);
+    
+    // Any input changes the slug
+    let other_url = SlugContext { url: "https://example.com/b", ..ctx };
+    assert_ne!(HashSlug.generate(&other_url), slug);
+    let other_owner = SlugContext { ref_id: None, ..ctx };
+    assert_ne!(HashSlug.generate(&other_owner), slug);
+    let retry = SlugContext { attempt: 1, ..ctx };
+    assert_ne!(HashSlug.generate(&retry), slug);
+}
+
+#[tokio::test]
+async fn test_slug_strategy_drives_generated_slugs() {
+    assert_eq!("Words".parse::<SlugStrategy>().unwrap(), SlugStrategy::Words);
+    assert!("hashids".parse::<SlugStrategy>().is_err());
+    
+    let (app, _temp_db) = setup_test_app(Config {
+        slug_strategy: SlugStrategy::Words,
+        slug_prefix: "go-".to_string(),
+        ..Config::default()
+    });
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id = body["id"].as_str().unwrap();
+    assert!(id.starts_with("go-"));
+    assert_eq!(id.split('-').count(), 4, "unexpected slug {}", id);
+    assert!(is_safe_slug(id));
+}
+
+/// Generator replaying fixed slugs, then repeating the last one
+struct ScriptedSlug {
+    slugs: Vec<&'static str>,
+    calls: AtomicUsize,
+}
+
+impl SlugGenerator for ScriptedSlug {
+    fn generate(&self, _ctx: &SlugContext) -> String {
+        let call = self.calls.fetch_add(1, Ordering::Relaxed);
+        self.slugs[call.min(self.slugs.len() - 1)].to_string()
+    }
+
+    fn space(&self, _config: &Config) -> Option<u64> {
+        Some(self.slugs.len() as u64)
+    }
+}
+
+/// Helper function to create a test application whose generated slugs follow `slugs`
+fn setup_scripted_app(slugs: Vec<&'static str>) -> (axum::Router, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).expect("Failed to initialize test database");
+    let mut state = AppState::with_config(db, Config::default());
+    state.slug_generator = Arc::new(ScriptedSlug { slugs, calls: AtomicUsize::new(0) });
+    
+    (create_app(state), temp_db)
+}
+
+#[tokio::test]
+async fn test_generated_slug_collision_is_retried() {
+    let (app, _temp_db) = setup_scripted_app(vec!["taken", "taken", "free01"]);
+    
+    let (status, _) = create_url(&app, json!({ "url": "https://example.com/a", "custom_id": "taken" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com/b" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["id"], "free01");
+    assert!(body["short_url"].as_str().unwrap().ends_with("/free01"));
+}
+
+#[tokio::test]
+async fn test_generated_slug_gives_up_after_bounded_attempts() {
+    let (app, _temp_db) = setup_scripted_app(vec!["taken"]);
+    
+    let (status, _) = create_url(&app, json!({ "url": "https://example.com/a", "custom_id": "taken" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com/b" })).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "slug_generation_failed");
+}
+
+#[tokio::test]
+async fn test_slug_collisions_are_counted_and_retries_capped() {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db = init_db(temp_db.path().to_str().unwrap()).expect("Failed to initialize test database");
+    let config = Config {
+        slug_length: 1,
+        slug_max_retries: 3,
+        ..Config::default()
+    };
+    let state = AppState::with_config(db, config);
+    let app = create_app(state.clone());
+    
+    // With one-character slugs, taking all 62 leaves nothing to generate
+    for slug in ('a'..='z').chain('A'..='Z').chain('0'..='9') {
+        let (status, _) = create_url(&app, json!({ "url": "https://example.com", "custom_id": slug.to_string() })).await;
+        assert_eq!(status, StatusCode::CREATED, "custom id {}", slug);
+    }
+    assert_eq!(state.metrics.slug_collisions(), 0);
+    
+    let (status, body) = create_url(&app, json!({ "url": "https://example.com/full" })).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "slug_generation_failed");
+    
+    // The first slug and exactly SLUG_MAX_RETRIES replacements were tried
+    assert_eq!(state.metrics.slug_collisions(), 4);
+}
@@ -0,0 +1,183 @@
+//! Integration tests for per-link click webhooks
+//! 
+//! The webhook receiver is a throwaway axum server bound to an ephemeral local
+//! port that forwards every payload it receives over a channel.
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+use shortener::database::{init_db, AppState};
+use shortener::route::create_app;
+
+/// Helper function to create a test application
+fn setup_test_app() -> (axum::Router, NamedTempFile) {
+    let temp_db = NamedTempFile::new().expect("Failed to create temp file");
+    let db_path = temp_db.path().to_str().unwrap();
+    
+    let db = init_db(db_path).expect("Failed to initialize test database");
+    let state = AppState::new(db);
+    
+    (create_app(state), temp_db)
+}
+
+/// Helper function to start a webhook receiver, returning its URL and the received payloads
+async fn spawn_receiver() -> (String, mpsc::UnboundedReceiver<Value>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let router = Router::new()
+        .route(
+            "/hook",
+            post(|State(sender): State<mpsc::UnboundedSender<Value>>, Json(payload): Json<Value>| async move {
+                sender.send(payload).unwrap();
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .with_state(sender);
+    
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    (format!("http://{}/hook", addr), receiver)
+}
+
+/// Helper function to create a short URL from a JSON payload
+async fn create_url(app: &axum::Router, payload: Value) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Helper function to set a link's click webhook, returning the status
+async fn set_webhook(app: &axum::Router, id: &str, payload: Value) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/urls/{}/webhook", id))
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Helper function to visit a short link
+async fn visit(app: &axum::Router, id: &str) -> StatusCode {
+    app.clone()
+        .oneshot(Request::builder().uri(format!("/{}", id)).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_click_webhook_fires_on_redirect() {
+    let (app, _temp_db) = setup_test_app();
+    let (webhook, mut received) = spawn_receiver().await;
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/hooked",
+        "custom_id": "hooked",
+        "click_webhook": webhook
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/hooked")
+                .header("referer", "https://news.example.com")
+                .header("user-agent", "webhook-test")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    
+    let payload = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("webhook was not called")
+        .unwrap();
+    assert_eq!(payload["id"], "hooked");
+    assert_eq!(payload["referrer"], "https://news.example.com");
+    assert_eq!(payload["user_agent"], "webhook-test");
+    assert!(payload["timestamp"].is_string());
+}
+
+#[tokio::test]
+async fn test_click_webhook_must_be_http_url() {
+    let (app, _temp_db) = setup_test_app();
+    
+    for webhook in ["not a url", "ftp://hooks.example.com/clicks", "/relative/path"] {
+        let response = create_url(&app, json!({
+            "url": "https://example.com/bad-hook",
+            "click_webhook": webhook
+        }))
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", webhook);
+    }
+}
+
+#[tokio::test]
+async fn test_click_webhook_can_be_set_and_cleared_after_creation() {
+    let (app, _temp_db) = setup_test_app();
+    let (webhook, mut received) = spawn_receiver().await;
+    
+    let response = create_url(&app, json!({
+        "url": "https://example.com/later",
+        "custom_id": "later",
+        "ref_id": "hook_owner"
+    }))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    
+    assert_eq!(set_webhook(&app, "later", json!({ "click_webhook": "not a url" })).await, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        set_webhook(&app, "later", json!({ "click_webhook": webhook, "ref_id": "someone_else" })).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(set_webhook(&app, "missing", json!({ "click_webhook": webhook })).await, StatusCode::NOT_FOUND);
+    
+    assert_eq!(
+        set_webhook(&app, "later", json!({ "click_webhook": webhook, "ref_id": "hook_owner" })).await,
+        StatusCode::OK
+    );
+    assert_eq!(visit(&app, "later").await, StatusCode::TEMPORARY_REDIRECT);
+    let payload = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("webhook was not called")
+        .unwrap();
+    assert_eq!(payload["id"], "later");
+    
+    assert_eq!(set_webhook(&app, "later", json!({ "ref_id": "hook_owner" })).await, StatusCode::OK);
+    assert_eq!(visit(&app, "later").await, StatusCode::TEMPORARY_REDIRECT);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), received.recv()).await.is_err(),
+        "webhook fired after being cleared"
+    );
+}